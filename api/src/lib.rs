@@ -1,9 +1,14 @@
+mod memory;
+
+use core::time::Duration;
 use serde_derive::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::convert::TryInto;
 use std::fmt::Debug;
-use std::time::Duration;
 use thiserror::Error;
 
+pub use memory::Memory;
+
 /// A `PlayerId` uniquely describes a single Player
 #[derive(Copy, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
 #[repr(transparent)]
@@ -31,6 +36,19 @@ impl Coord {
             y: y.try_into().ok().unwrap_or(0),
         }
     }
+
+    /// Returns the number of steps it takes to walk from this coordinate to `other` without
+    /// moving diagonally
+    pub fn manhattan_distance(self, other: Coord) -> usize {
+        ((self.x - other.x).abs() + (self.y - other.y).abs()) as usize
+    }
+
+    /// Returns the four coordinates directly next to this coordinate
+    pub fn neighbors(self) -> impl Iterator<Item = Coord> {
+        Direction::all_directions()
+            .into_iter()
+            .map(move |direction| self + direction)
+    }
 }
 
 // Conversion from a tuple and back
@@ -57,13 +75,317 @@ pub struct Unit {
     pub id: UnitId,
     pub player: PlayerId,
     pub location: Coord,
+
+    /// The kind of unit
+    #[serde(default)]
+    pub kind: UnitKind,
+
+    /// The remaining health of the unit, if the rules of the game use health
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub health: Option<u32>,
+
+    /// The remaining energy of the unit, if the rules of the game use energy
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub energy: Option<u32>,
+
+    /// The status effects that currently affect the unit
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub status: Vec<StatusEffect>,
+}
+
+impl Unit {
+    /// Constructs a unit of the default kind without health, energy or status effects
+    pub fn new(id: UnitId, player: PlayerId, location: Coord) -> Self {
+        Unit {
+            id,
+            player,
+            location,
+            kind: UnitKind::default(),
+            health: None,
+            energy: None,
+            status: Vec::new(),
+        }
+    }
+}
+
+/// The different kinds of units
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UnitKind {
+    Robot,
+}
+
+impl Default for UnitKind {
+    fn default() -> Self {
+        UnitKind::Robot
+    }
+}
+
+/// A temporary effect on a unit
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub struct StatusEffect {
+    /// The name of the effect
+    pub name: String,
+
+    /// The number of turns after which the effect wears off, `None` if it lasts indefinitely
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub turns_remaining: Option<usize>,
+}
+
+/// Unique identifier of a specific `Item`
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[repr(transparent)]
+pub struct ItemId(pub usize);
+
+/// An objective item that units can pick up, carry around and deliver to a goal tile
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub struct Item {
+    pub id: ItemId,
+
+    /// The kind of item
+    #[serde(default)]
+    pub kind: ItemKind,
+
+    /// The location of the item, the location of its carrier if it is carried
+    pub location: Coord,
+
+    /// The unit that carries the item, `None` if it lies on the ground
+    #[serde(default)]
+    pub carrier: Option<UnitId>,
+
+    /// The first turn in which the carrier of the item is able to move again. Carrying an item
+    /// slows a unit down.
+    #[serde(default)]
+    pub next_move: usize,
+}
+
+/// The different kinds of items
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ItemKind {
+    /// A data core that has to be delivered to a goal tile
+    DataCore,
+}
+
+impl Default for ItemKind {
+    fn default() -> Self {
+        ItemKind::DataCore
+    }
 }
 
-/// A `PlayerWorld` represents only the visible parts of a world for a specific player.
+/// An obstacle that stands on a floor tile. Obstacles block movement and vision like walls do,
+/// but units can break them down with `Break` actions.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub struct Obstacle {
+    pub kind: ObstacleKind,
+    pub location: Coord,
+
+    /// The number of `Break` actions it takes to destroy the obstacle
+    pub durability: u32,
+}
+
+/// The different kinds of obstacles
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ObstacleKind {
+    Crate,
+    Rubble,
+}
+
+impl ObstacleKind {
+    /// Returns the durability of a new obstacle of this kind
+    pub fn durability(self) -> u32 {
+        match self {
+            ObstacleKind::Crate => 1,
+            ObstacleKind::Rubble => 3,
+        }
+    }
+}
+
+/// A `PlayerWorld` represents only the visible parts of a world for a specific player. It contains
+/// all the units of the player and the units of other players that are within sight, together
+/// with all the markers the player placed and the items within sight. Units of other players that
+/// were seen before but are out of sight now are listed in `sightings`.
 #[derive(Clone, Eq, PartialEq, Debug, Hash, Serialize, Deserialize)]
 pub struct PlayerWorld {
     pub units: Vec<Unit>,
     pub tiles: Vec<PlayerTile>,
+    #[serde(default)]
+    pub markers: Vec<Marker>,
+    #[serde(default)]
+    pub items: Vec<Item>,
+    #[serde(default)]
+    pub sightings: Vec<Sighting>,
+    #[serde(default)]
+    pub obstacles: Vec<Obstacle>,
+}
+
+/// The last known location of a unit of another player that is currently out of sight. A sighting
+/// is forgotten once its location is in sight again and the unit is no longer there.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub struct Sighting {
+    pub unit: UnitId,
+    pub player: PlayerId,
+    pub location: Coord,
+
+    /// The number of turns since the unit was seen at `location`
+    pub turns_ago: usize,
+}
+
+/// A marker placed on a tile with a `Mark` action. Markers are only visible to the player that
+/// placed them, wherever its units are. A tile holds at most one marker per player, placing a new
+/// marker replaces the old one.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub struct Marker {
+    pub coord: Coord,
+    pub tag: u8,
+}
+
+impl PlayerWorld {
+    /// Returns the units owned by the given player
+    pub fn my_units(&self, player_id: PlayerId) -> impl Iterator<Item = &Unit> {
+        self.units
+            .iter()
+            .filter(move |unit| unit.player == player_id)
+    }
+
+    /// Returns the units of other players than the given player that are within sight
+    pub fn visible_enemies(&self, player_id: PlayerId) -> impl Iterator<Item = &Unit> {
+        self.units
+            .iter()
+            .filter(move |unit| unit.player != player_id)
+    }
+
+    /// Returns the type of the tile at the given coordinate or `None` if the tile is not visible.
+    /// This searches through all tiles, use `tile_grid` when looking up many tiles.
+    pub fn tile_at(&self, coord: Coord) -> Option<TileType> {
+        self.tiles
+            .iter()
+            .find(|tile| tile.coord == coord)
+            .map(|tile| tile.tile_type)
+    }
+
+    /// Returns the visible tiles indexed by their coordinate
+    pub fn tile_grid(&self) -> TileGrid {
+        TileGrid {
+            tiles: self
+                .tiles
+                .iter()
+                .map(|tile| (tile.coord, tile.tile_type))
+                .collect(),
+        }
+    }
+}
+
+/// A `SpectatorWorld` is a snapshot of the complete world as seen by someone watching the battle,
+/// unlike a `PlayerWorld` nothing is hidden. It is meant for viewers and other tools that are not
+/// part of the engine.
+#[derive(Clone, Eq, PartialEq, Debug, Hash, Serialize, Deserialize)]
+pub struct SpectatorWorld {
+    /// The turn that is about to be played
+    pub turn: usize,
+
+    /// The width of the map in tiles
+    pub width: usize,
+
+    /// The height of the map in tiles
+    pub height: usize,
+
+    /// All the tiles of the map, row by row
+    pub tiles: Vec<TileType>,
+
+    /// All the units in the world
+    pub units: Vec<Unit>,
+
+    /// All the items in the world that have not been delivered yet
+    #[serde(default)]
+    pub items: Vec<Item>,
+
+    /// All the obstacles in the world
+    #[serde(default)]
+    pub obstacles: Vec<Obstacle>,
+}
+
+impl SpectatorWorld {
+    /// Returns the type of the tile at the given coordinate or `None` if the coordinate lies
+    /// outside of the map
+    pub fn tile_at(&self, coord: Coord) -> Option<TileType> {
+        if coord.x < 0
+            || coord.y < 0
+            || coord.x as usize >= self.width
+            || coord.y as usize >= self.height
+        {
+            return None;
+        }
+        self.tiles
+            .get(coord.y as usize * self.width + coord.x as usize)
+            .copied()
+    }
+
+    /// Returns all tiles together with their coordinate, row by row
+    pub fn iter_tiles(&self) -> impl Iterator<Item = (Coord, TileType)> + '_ {
+        let width = self.width;
+        self.tiles.iter().enumerate().map(move |(index, tile)| {
+            (
+                Coord::new((index % width) as isize, (index / width) as isize),
+                *tile,
+            )
+        })
+    }
+
+    /// Returns the units owned by the given player
+    pub fn units_of(&self, player_id: PlayerId) -> impl Iterator<Item = &Unit> {
+        self.units
+            .iter()
+            .filter(move |unit| unit.player == player_id)
+    }
+}
+
+/// A sparse grid of tiles that allows quick lookup of tiles by their coordinate. Indexing the grid
+/// with a coordinate of a tile that is not in the grid panics, use `get` to handle missing tiles.
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub struct TileGrid {
+    tiles: HashMap<Coord, TileType>,
+}
+
+impl TileGrid {
+    /// Returns the type of the tile at the given coordinate, if it is known
+    pub fn get(&self, coord: Coord) -> Option<TileType> {
+        self.tiles.get(&coord).copied()
+    }
+
+    /// Returns true if the tile at the given coordinate is known
+    pub fn contains(&self, coord: Coord) -> bool {
+        self.tiles.contains_key(&coord)
+    }
+
+    /// Returns the number of known tiles
+    pub fn len(&self) -> usize {
+        self.tiles.len()
+    }
+
+    /// Returns true if no tiles are known
+    pub fn is_empty(&self) -> bool {
+        self.tiles.is_empty()
+    }
+
+    /// Returns all known tiles
+    pub fn iter(&self) -> impl Iterator<Item = (Coord, TileType)> + '_ {
+        self.tiles.iter().map(|(coord, tile)| (*coord, *tile))
+    }
+
+    /// Adds or replaces the tile at the given coordinate
+    pub fn insert(&mut self, coord: Coord, tile_type: TileType) {
+        self.tiles.insert(coord, tile_type);
+    }
+}
+
+impl std::ops::Index<Coord> for TileGrid {
+    type Output = TileType;
+
+    fn index(&self, coord: Coord) -> &Self::Output {
+        &self.tiles[&coord]
+    }
 }
 
 /// The type for a single tile in the world
@@ -73,12 +395,26 @@ pub enum TileType {
     Wall,
     Floor,
     Exit,
+
+    /// A tile to which items have to be delivered
+    Goal,
+
+    /// A tile introduced by a plugin of the engine. `id` identifies the kind of tile, `passable`
+    /// tells whether units can enter it.
+    Custom {
+        id: u8,
+        passable: bool,
+    },
 }
 
 impl TileType {
     /// Returns true if this is a type of tile that can be entered
     pub fn can_enter(self) -> bool {
-        matches!(self, TileType::Floor | TileType::Exit)
+        match self {
+            TileType::Floor | TileType::Exit | TileType::Goal => true,
+            TileType::Wall => false,
+            TileType::Custom { passable, .. } => passable,
+        }
     }
 }
 
@@ -91,10 +427,220 @@ pub struct PlayerTile {
 }
 
 /// Describes a possible action that can be performed in the world as ordered by a specific player.
-#[derive(Clone, Eq, PartialEq, Debug, Hash, Serialize, Deserialize)]
+///
+/// Actions are serialized with an `action` tag, e.g. `{"action":"move","unit":0,"direction":"up"}`.
+/// The older tuple format, e.g. `{"Move":[0,"up"]}`, is still accepted when deserializing.
+///
+/// Actions that are added to the engine by plugins are sent as `custom` actions, e.g.
+/// `{"action":"custom","unit":0,"name":"dig","data":"up"}`. What `data` contains is up to the
+/// plugin that handles the action.
+#[derive(Clone, Eq, PartialEq, Debug, Hash, Serialize)]
 #[serde(tag = "action", rename_all = "snake_case")]
 pub enum PlayerAction {
-    Move { unit: UnitId, direction: Direction },
+    Move {
+        unit: UnitId,
+        direction: Direction,
+    },
+    Mark {
+        unit: UnitId,
+        tag: u8,
+    },
+    PickUp {
+        unit: UnitId,
+    },
+    Drop {
+        unit: UnitId,
+    },
+    Link {
+        unit: UnitId,
+        target: UnitId,
+    },
+    Break {
+        unit: UnitId,
+        direction: Direction,
+    },
+    Custom {
+        unit: UnitId,
+        name: String,
+        data: String,
+    },
+}
+
+impl PlayerAction {
+    /// Returns the kind of this action
+    pub fn kind(&self) -> ActionKind {
+        match self {
+            PlayerAction::Move { .. } => ActionKind::Move,
+            PlayerAction::Mark { .. } => ActionKind::Mark,
+            PlayerAction::PickUp { .. } => ActionKind::PickUp,
+            PlayerAction::Drop { .. } => ActionKind::Drop,
+            PlayerAction::Link { .. } => ActionKind::Link,
+            PlayerAction::Break { .. } => ActionKind::Break,
+            PlayerAction::Custom { .. } => ActionKind::Custom,
+        }
+    }
+
+    /// Returns the unit that is ordered to perform the action
+    pub fn unit(&self) -> UnitId {
+        match *self {
+            PlayerAction::Move { unit, .. }
+            | PlayerAction::Mark { unit, .. }
+            | PlayerAction::PickUp { unit }
+            | PlayerAction::Drop { unit }
+            | PlayerAction::Link { unit, .. }
+            | PlayerAction::Break { unit, .. }
+            | PlayerAction::Custom { unit, .. } => unit,
+        }
+    }
+}
+
+/// The different kinds of actions that exist, without any parameters
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ActionKind {
+    Move,
+
+    /// Placing a marker on the tile a unit stands on
+    Mark,
+
+    /// Picking up the item on the tile a unit stands on
+    PickUp,
+
+    /// Dropping the item a unit carries
+    Drop,
+
+    /// Spending energy on a sensor link to another unit of the same player, which lets that unit
+    /// see further during the next turn
+    Link,
+
+    /// Damaging the obstacle next to a unit
+    Break,
+
+    /// Any of the actions added by plugins of the engine
+    Custom,
+}
+
+/// The number of action points that actions cost, see `WorldInfo::action_points`
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ActionCosts {
+    /// The cost of moving a unit a single tile
+    #[serde(rename = "move")]
+    pub movement: usize,
+
+    /// The cost of placing a marker
+    pub mark: usize,
+
+    /// The cost of picking up an item
+    pub pick_up: usize,
+
+    /// The cost of dropping an item
+    pub drop: usize,
+
+    /// The cost of setting up a sensor link
+    pub link: usize,
+
+    /// The cost of damaging an obstacle
+    #[serde(rename = "break")]
+    pub smash: usize,
+
+    /// The cost of custom actions, plugins may decide on a different cost per action
+    pub custom: usize,
+}
+
+impl ActionCosts {
+    /// Returns the cost of an action of the given kind
+    pub fn cost(&self, kind: ActionKind) -> usize {
+        match kind {
+            ActionKind::Move => self.movement,
+            ActionKind::Mark => self.mark,
+            ActionKind::PickUp => self.pick_up,
+            ActionKind::Drop => self.drop,
+            ActionKind::Link => self.link,
+            ActionKind::Break => self.smash,
+            ActionKind::Custom => self.custom,
+        }
+    }
+}
+
+impl Default for ActionCosts {
+    fn default() -> Self {
+        ActionCosts {
+            movement: 1,
+            mark: 1,
+            pick_up: 1,
+            drop: 1,
+            link: 1,
+            smash: 1,
+            custom: 1,
+        }
+    }
+}
+
+mod compat {
+    use super::{ActionKind, Direction, PlayerAction, UnitId};
+    use serde::de::{Deserialize, Deserializer, Error};
+    use serde_derive::Deserialize;
+
+    /// The fields of a `PlayerAction` in both the tagged format and the older tuple format. Which
+    /// fields are required depends on the `action`.
+    #[derive(Deserialize)]
+    struct Fields {
+        action: Option<ActionKind>,
+        unit: Option<UnitId>,
+        direction: Option<Direction>,
+        tag: Option<u8>,
+        target: Option<UnitId>,
+        name: Option<String>,
+        data: Option<String>,
+
+        /// A move in the older tuple format, e.g. `{"Move":[0,"up"]}`
+        #[serde(rename = "Move", alias = "move")]
+        tuple_move: Option<(UnitId, Direction)>,
+    }
+
+    fn required<T, E: Error>(value: Option<T>, field: &'static str) -> Result<T, E> {
+        value.ok_or_else(|| E::missing_field(field))
+    }
+
+    impl<'de> Deserialize<'de> for PlayerAction {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            let fields = Fields::deserialize(deserializer)?;
+            let action = match (fields.action, fields.tuple_move) {
+                (Some(action), _) => action,
+                (None, Some((unit, direction))) => {
+                    return Ok(PlayerAction::Move { unit, direction })
+                }
+                (None, None) => return Err(D::Error::missing_field("action")),
+            };
+            let unit = required(fields.unit, "unit")?;
+            Ok(match action {
+                ActionKind::Move => PlayerAction::Move {
+                    unit,
+                    direction: required(fields.direction, "direction")?,
+                },
+                ActionKind::Mark => PlayerAction::Mark {
+                    unit,
+                    tag: required(fields.tag, "tag")?,
+                },
+                ActionKind::PickUp => PlayerAction::PickUp { unit },
+                ActionKind::Drop => PlayerAction::Drop { unit },
+                ActionKind::Link => PlayerAction::Link {
+                    unit,
+                    target: required(fields.target, "target")?,
+                },
+                ActionKind::Break => PlayerAction::Break {
+                    unit,
+                    direction: required(fields.direction, "direction")?,
+                },
+                ActionKind::Custom => PlayerAction::Custom {
+                    unit,
+                    name: required(fields.name, "name")?,
+                    data: fields.data.unwrap_or_default(),
+                },
+            })
+        }
+    }
 }
 
 /// A direction
@@ -137,8 +683,23 @@ impl std::ops::AddAssign<Direction> for Coord {
     }
 }
 
+impl std::ops::Add<Coord> for Coord {
+    type Output = Coord;
+
+    fn add(self, rhs: Coord) -> Self::Output {
+        Coord::new(self.x + rhs.x, self.y + rhs.y)
+    }
+}
+
+impl std::ops::AddAssign<Coord> for Coord {
+    fn add_assign(&mut self, rhs: Coord) {
+        *self = *self + rhs;
+    }
+}
+
 impl Direction {
     /// Returns a random direction
+    #[cfg(feature = "rand")]
     pub fn random<Rng: rand::Rng>(rng: &mut Rng) -> Self {
         match rng.gen_range(0, 4) {
             0 => Direction::Left,
@@ -148,6 +709,36 @@ impl Direction {
         }
     }
 
+    /// Returns the direction pointing the other way
+    pub fn opposite(self) -> Direction {
+        match self {
+            Direction::Left => Direction::Right,
+            Direction::Right => Direction::Left,
+            Direction::Up => Direction::Down,
+            Direction::Down => Direction::Up,
+        }
+    }
+
+    /// Returns the direction after a quarter turn clockwise, e.g. `Up` becomes `Right`
+    pub fn rotate_cw(self) -> Direction {
+        match self {
+            Direction::Left => Direction::Up,
+            Direction::Right => Direction::Down,
+            Direction::Up => Direction::Right,
+            Direction::Down => Direction::Left,
+        }
+    }
+
+    /// Returns the direction after a quarter turn counter-clockwise, e.g. `Up` becomes `Left`
+    pub fn rotate_ccw(self) -> Direction {
+        match self {
+            Direction::Left => Direction::Down,
+            Direction::Right => Direction::Up,
+            Direction::Up => Direction::Left,
+            Direction::Down => Direction::Right,
+        }
+    }
+
     /// Returns all directions
     pub fn all_directions() -> Vec<Direction> {
         vec![
@@ -159,7 +750,7 @@ impl Direction {
     }
 }
 
-pub type PlayerMemory = serde_json::value::Value;
+pub type PlayerMemory = Memory;
 
 #[derive(Serialize, Deserialize, Error, Debug)]
 pub enum RunnerError {
@@ -180,8 +771,42 @@ pub enum RunnerError {
 
     #[error("Program returned invalid data")]
     DataError(String),
+
+    #[error("the program returned {size} bytes of memory, past the limit of {limit} bytes")]
+    MemoryLimitExceeded { size: usize, limit: usize },
+
+    #[error("a message of {size} bytes is past the limit of {limit} bytes")]
+    MessageTooLarge { size: usize, limit: usize },
+
+    #[error("the program trapped: {0}")]
+    Trapped(String),
+
+    #[error(
+        "the program was written for api version {actual} but the engine uses version {expected}"
+    )]
+    VersionMismatch { expected: usize, actual: usize },
 }
 
+impl RunnerError {
+    /// Returns a number that identifies the kind of error. Codes are stable, they never change
+    /// meaning and are never reused, so tooling can rely on them.
+    pub fn code(&self) -> u16 {
+        match self {
+            RunnerError::InternalError => 1,
+            RunnerError::InitError(_) => 2,
+            RunnerError::NoData => 3,
+            RunnerError::IO(_) => 4,
+            RunnerError::Timeout(_) => 5,
+            RunnerError::DataError(_) => 6,
+            RunnerError::MemoryLimitExceeded { .. } => 7,
+            RunnerError::VersionMismatch { .. } => 8,
+            RunnerError::MessageTooLarge { .. } => 9,
+            RunnerError::Trapped(_) => 10,
+        }
+    }
+}
+
+#[cfg(feature = "json")]
 impl From<serde_json::Error> for RunnerError {
     fn from(err: serde_json::Error) -> Self {
         Self::DataError(err.to_string())
@@ -194,14 +819,115 @@ impl From<std::io::Error> for RunnerError {
     }
 }
 
+/// The kinds of weather that can limit how far units can see
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WeatherKind {
+    Fog,
+    Darkness,
+}
+
+/// Describes the dimensions of the world and the rules under which the game is played
+#[derive(Clone, Eq, PartialEq, Debug, Hash, Serialize, Deserialize)]
+pub struct WorldInfo {
+    /// The width of the map in tiles
+    pub width: usize,
+
+    /// The height of the map in tiles
+    pub height: usize,
+
+    /// The number of tiles a unit can see in every direction this turn, this may be reduced by the
+    /// weather
+    pub vision_range: usize,
+
+    /// The weather conditions that limit the vision range this turn
+    #[serde(default)]
+    pub weather: Vec<WeatherKind>,
+
+    /// The turn after which the game ends and the player closest to completing the objective wins,
+    /// if any
+    pub max_turns: Option<usize>,
+
+    /// The kinds of actions that units are allowed to perform. This differs between matches with
+    /// different rules, bots should only submit actions that are listed here.
+    pub actions: Vec<ActionKind>,
+
+    /// The number of action points every unit can spend per turn, `None` if units can perform
+    /// any number of actions. Actions that exceed the budget of a unit are rejected.
+    #[serde(default)]
+    pub action_points: Option<usize>,
+
+    /// The number of action points every kind of action costs
+    #[serde(default)]
+    pub action_costs: ActionCosts,
+}
+
+impl WorldInfo {
+    /// Returns true if the rules of the game allow units to perform actions of the given kind.
+    /// Actions that are not allowed are rejected by the engine.
+    pub fn is_allowed(&self, kind: ActionKind) -> bool {
+        self.actions.contains(&kind)
+    }
+
+    /// Returns true if the coordinate lies within the bounds of the map
+    pub fn in_bounds(&self, coord: Coord) -> bool {
+        coord.x >= 0
+            && coord.y >= 0
+            && coord.x < self.width as isize
+            && coord.y < self.height as isize
+    }
+}
+
 /// The input for a `PlayerRunner`
 #[derive(Serialize, Deserialize)]
 pub struct PlayerInput<T: Debug = PlayerMemory> {
     pub version: usize,
     pub player_id: PlayerId,
     pub turn: usize,
+    pub world_info: WorldInfo,
     pub world: PlayerWorld,
     pub memory: T,
+
+    /// The noises the units of the player heard during the previous turn, including noises made
+    /// outside of their field of view
+    #[serde(default)]
+    pub noises: Vec<Noise>,
+
+    /// The tiles of the map that changed during the previous turn, anywhere on the map
+    #[serde(default)]
+    pub changed_tiles: Vec<PlayerTile>,
+}
+
+/// A noise heard by the units of a player. Only the rough direction and how loud it was are known,
+/// the exact location of the noise is not.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub struct Noise {
+    /// The direction in which the noise was made, seen from the closest unit that heard it
+    pub direction: Direction,
+
+    /// How loud the noise was when it reached the unit, the higher the closer
+    pub intensity: usize,
+}
+
+/// The input a player receives once before the first turn when the rules give players time to
+/// warm up, to prepare for the match like precomputing paths. The player answers with a
+/// `PlayerOutput` of which only the memory is used, the player starts the first turn with it.
+#[derive(Serialize, Deserialize)]
+pub struct MatchStart<T: Debug = PlayerMemory> {
+    pub version: usize,
+    pub player_id: PlayerId,
+    pub world_info: WorldInfo,
+
+    /// What the units of the player see before the first turn
+    pub world: PlayerWorld,
+    pub memory: T,
+}
+
+/// How a `MatchStart` is written to a program, as an object with a single `match_start` field to
+/// tell it apart from a `PlayerInput`
+#[derive(Serialize, Deserialize)]
+pub struct MatchStartMessage<T: Debug = PlayerMemory> {
+    pub match_start: MatchStart<T>,
 }
 
 /// The output of a `PlayerRunner`
@@ -209,6 +935,108 @@ pub struct PlayerInput<T: Debug = PlayerMemory> {
 pub struct PlayerOutput<T: Debug = PlayerMemory> {
     pub actions: Vec<PlayerAction>,
     pub memory: T,
+
+    /// The version of the api the program was written against, usually `API_VERSION`. The engine
+    /// rejects the output of programs that report a different version.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<usize>,
 }
 
-pub const API_VERSION: usize = 1;
+/// The version of the protocol between the engine and the players. This is bumped whenever a change
+/// is made that breaks existing players. Version 2 added goal tiles, `TileType::Goal`, and the tiles
+/// of plugins, `TileType::Custom`, which players built for version 1 cannot parse.
+pub const API_VERSION: usize = 2;
+
+/// Inputs that do not fit on a single line are written to programs in parts, one part per line
+/// that starts with this prefix. The parts are concatenated to form the JSON of the input.
+pub const INPUT_CHUNK_PREFIX: &str = "__mlr_chunk:";
+
+/// The line that follows the last part of an input that was written in parts
+pub const INPUT_END: &str = "__mlr_end";
+
+#[cfg(all(test, feature = "json"))]
+mod tests {
+    use super::*;
+
+    fn actions() -> Vec<PlayerAction> {
+        let unit = UnitId(1);
+        vec![
+            PlayerAction::Move {
+                unit,
+                direction: Direction::Up,
+            },
+            PlayerAction::Mark { unit, tag: 3 },
+            PlayerAction::PickUp { unit },
+            PlayerAction::Drop { unit },
+            PlayerAction::Link {
+                unit,
+                target: UnitId(2),
+            },
+            PlayerAction::Break {
+                unit,
+                direction: Direction::Left,
+            },
+            PlayerAction::Custom {
+                unit,
+                name: "dig".to_owned(),
+                data: "down".to_owned(),
+            },
+        ]
+    }
+
+    #[test]
+    fn actions_survive_a_round_trip() {
+        for action in actions() {
+            let json = serde_json::to_string(&action).unwrap();
+            assert_eq!(serde_json::from_str::<PlayerAction>(&json).unwrap(), action);
+        }
+    }
+
+    #[test]
+    fn actions_are_tagged() {
+        assert_eq!(
+            serde_json::to_string(&actions()[0]).unwrap(),
+            r#"{"action":"move","unit":1,"direction":"up"}"#
+        );
+    }
+
+    #[test]
+    fn tuple_moves_are_accepted() {
+        let expected = PlayerAction::Move {
+            unit: UnitId(1),
+            direction: Direction::Down,
+        };
+        for json in &[r#"{"Move":[1,"down"]}"#, r#"{"move":[1,"down"]}"#] {
+            assert_eq!(
+                serde_json::from_str::<PlayerAction>(json).unwrap(),
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn custom_actions_default_to_empty_data() {
+        assert_eq!(
+            serde_json::from_str::<PlayerAction>(r#"{"action":"custom","unit":1,"name":"dig"}"#)
+                .unwrap(),
+            PlayerAction::Custom {
+                unit: UnitId(1),
+                name: "dig".to_owned(),
+                data: String::new(),
+            }
+        );
+    }
+
+    #[test]
+    fn errors_of_the_tagged_format_are_reported() {
+        let error = |json| {
+            serde_json::from_str::<PlayerAction>(json)
+                .unwrap_err()
+                .to_string()
+        };
+        assert!(error(r#"{"action":"move","unit":1}"#).contains("missing field `direction`"));
+        assert!(error(r#"{"action":"fly","unit":1}"#).contains("unknown variant `fly`"));
+        assert!(error(r#"{"action":"mark","unit":1,"tag":300}"#).contains("300"));
+        assert!(error(r#"{"unit":1}"#).contains("missing field `action`"));
+    }
+}
@@ -0,0 +1,206 @@
+use serde::de::{Deserialize, Deserializer};
+use serde::ser::{Serialize, SerializeMap, Serializer};
+#[cfg(feature = "json")]
+use serde_json::Value;
+use std::sync::Arc;
+
+/// The key under which raw bytes are stored when memory is serialized
+const BYTES_KEY: &str = "$bytes";
+
+/// The memory of a player that is passed from one turn to the next. Memory is either an arbitrary
+/// JSON value or a blob of raw bytes. The contents are shared so cloning memory is cheap.
+///
+/// JSON memory is serialized as is. Raw bytes are serialized as `{"$bytes": "<hex>"}`. JSON memory
+/// is only available with the `json` feature.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Memory {
+    #[cfg(feature = "json")]
+    Json(Arc<Value>),
+    Bytes(Arc<[u8]>),
+}
+
+impl Memory {
+    /// Returns the number of bytes the memory occupies when it is sent to a player, the size of its
+    /// JSON. Raw bytes take up more than twice their own size, see `Memory`.
+    pub fn size(&self) -> usize {
+        match self {
+            #[cfg(feature = "json")]
+            Memory::Json(value) => serde_json::to_vec(value.as_ref()).map_or(0, |v| v.len()),
+            Memory::Bytes(bytes) => r#"{"":""}"#.len() + BYTES_KEY.len() + 2 * bytes.len(),
+        }
+    }
+
+    /// Returns true if both memories share the same contents without comparing them
+    pub fn ptr_eq(&self, other: &Memory) -> bool {
+        match (self, other) {
+            #[cfg(feature = "json")]
+            (Memory::Json(a), Memory::Json(b)) => Arc::ptr_eq(a, b),
+            (Memory::Bytes(a), Memory::Bytes(b)) => Arc::ptr_eq(a, b),
+            #[allow(unreachable_patterns)]
+            _ => false,
+        }
+    }
+
+    /// Returns the JSON value stored in the memory, if any
+    #[cfg(feature = "json")]
+    pub fn as_json(&self) -> Option<&Value> {
+        match self {
+            Memory::Json(value) => Some(value.as_ref()),
+            Memory::Bytes(_) => None,
+        }
+    }
+
+    /// Returns the raw bytes stored in the memory, if any
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            #[cfg(feature = "json")]
+            Memory::Json(_) => None,
+            Memory::Bytes(bytes) => Some(bytes.as_ref()),
+        }
+    }
+}
+
+impl Default for Memory {
+    #[cfg(feature = "json")]
+    fn default() -> Self {
+        Memory::Json(Arc::new(Value::Object(Default::default())))
+    }
+
+    #[cfg(not(feature = "json"))]
+    fn default() -> Self {
+        Memory::Bytes(Arc::new([]))
+    }
+}
+
+#[cfg(feature = "json")]
+impl From<Value> for Memory {
+    fn from(value: Value) -> Self {
+        Memory::Json(Arc::new(value))
+    }
+}
+
+impl From<Vec<u8>> for Memory {
+    fn from(bytes: Vec<u8>) -> Self {
+        Memory::Bytes(bytes.into())
+    }
+}
+
+impl Serialize for Memory {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            #[cfg(feature = "json")]
+            Memory::Json(value) => value.serialize(serializer),
+            Memory::Bytes(bytes) => {
+                let hex = bytes
+                    .iter()
+                    .map(|b| format!("{:02x}", b))
+                    .collect::<String>();
+                let mut map = serializer.serialize_map(Some(1))?;
+                map.serialize_entry(BYTES_KEY, &hex)?;
+                map.end()
+            }
+        }
+    }
+}
+
+#[cfg(feature = "json")]
+impl<'de> Deserialize<'de> for Memory {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = Value::deserialize(deserializer)?;
+        if let Some(bytes) = value
+            .as_object()
+            .filter(|object| object.len() == 1)
+            .and_then(|object| object.get(BYTES_KEY))
+            .and_then(Value::as_str)
+            .and_then(decode_hex)
+        {
+            return Ok(Memory::Bytes(bytes.into()));
+        }
+        Ok(Memory::Json(Arc::new(value)))
+    }
+}
+
+#[cfg(not(feature = "json"))]
+impl<'de> Deserialize<'de> for Memory {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(serde_derive::Deserialize)]
+        struct BytesRepr {
+            #[serde(rename = "$bytes")]
+            bytes: String,
+        }
+
+        let repr = BytesRepr::deserialize(deserializer)?;
+        decode_hex(&repr.bytes)
+            .map(|bytes| Memory::Bytes(bytes.into()))
+            .ok_or_else(|| serde::de::Error::custom("invalid hex encoded bytes"))
+    }
+}
+
+/// Decodes a string of hexadecimal digit pairs
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if hex.len() % 2 != 0 || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn size_of_bytes_is_the_size_of_their_json() {
+        for len in &[0, 1, 100] {
+            let memory = Memory::from(vec![0xab; *len]);
+            let json = serde_json::to_string(&memory).unwrap();
+            assert_eq!(memory.size(), json.len());
+        }
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn size_of_json_is_the_size_of_its_json() {
+        let memory = Memory::from(serde_json::json!({ "path": [1, 2, 3] }));
+        let json = serde_json::to_string(&memory).unwrap();
+        assert_eq!(memory.size(), json.len());
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn bytes_survive_a_round_trip() {
+        let memory = Memory::from(vec![0, 1, 0x7f, 0xff]);
+        let json = serde_json::to_string(&memory).unwrap();
+        assert_eq!(json, r#"{"$bytes":"00017fff"}"#);
+        assert_eq!(serde_json::from_str::<Memory>(&json).unwrap(), memory);
+    }
+
+    #[test]
+    fn decodes_hex() {
+        assert_eq!(decode_hex(""), Some(Vec::new()));
+        assert_eq!(decode_hex("00ff7F"), Some(vec![0x00, 0xff, 0x7f]));
+    }
+
+    #[test]
+    fn rejects_invalid_hex() {
+        assert_eq!(decode_hex("abc"), None);
+        assert_eq!(decode_hex("zz"), None);
+        assert_eq!(decode_hex("+1"), None);
+        assert_eq!(decode_hex("a\u{e9}0"), None);
+        assert_eq!(decode_hex("\u{e9}\u{e9}"), None);
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn invalid_hex_is_kept_as_json() {
+        let memory = serde_json::from_str::<Memory>(r#"{"$bytes":"zz"}"#).unwrap();
+        assert_eq!(memory.as_bytes(), None);
+        assert_eq!(
+            memory.as_json(),
+            Some(&serde_json::json!({ "$bytes": "zz" }))
+        );
+    }
+}
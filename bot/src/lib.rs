@@ -0,0 +1,202 @@
+//! Helpers for writing My Little Robots bots in Rust.
+//!
+//! A bot is a program that is started every turn. It reads a `PlayerInput` from stdin and writes
+//! its `PlayerOutput` to stdout. [`run_bot`] takes care of all of that, a bot only has to provide
+//! the function that decides what to do:
+//!
+//! ```no_run
+//! use mlr_bot::mlr_api::{PlayerInput, PlayerOutput};
+//!
+//! fn main() {
+//!     mlr_bot::run_bot(|input: PlayerInput<()>| PlayerOutput {
+//!         actions: Vec::new(),
+//!         memory: input.memory,
+//!         version: None,
+//!     })
+//! }
+//! ```
+//!
+//! When the rules of a match give players time to warm up the bot is started once more before
+//! the first turn, [`run_bot_with_warm_up`] lets it prepare for the match in that time.
+//!
+//! Bots compiled to WASI can skip stdin and stdout altogether with [`export_tick!`], the engine
+//! then calls the tick function of the module directly which is a lot faster. The same instance of
+//! the module plays every turn, so it can keep state in statics between turns.
+
+mod pathfinding;
+#[doc(hidden)]
+pub mod reactor;
+
+pub use mlr_api;
+pub use pathfinding::{find_path, find_path_to};
+
+use mlr_api::{
+    MatchStart, MatchStartMessage, PlayerInput, PlayerOutput, API_VERSION, INPUT_CHUNK_PREFIX,
+    INPUT_END,
+};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+use std::fmt::Debug;
+use std::io::BufRead;
+
+/// The prefix the engine looks for to find the output of a bot on stdout
+const OUTPUT_PREFIX: &str = "__mlr_output:";
+
+/// Reads the input of the current turn from stdin, calls `tick` with it and writes the output to
+/// stdout.
+///
+/// The memory of the previous turn is deserialized to `M`. If that fails, which is the case on the
+/// first turn, `M::default()` is used instead. The process exits with an error if the engine uses
+/// a different version of the api than the bot was built with. When the match starts with a warm
+/// up the bot keeps its memory as is, see [`run_bot_with_warm_up`] to prepare for the match.
+pub fn run_bot<M, F>(tick: F)
+where
+    M: Serialize + DeserializeOwned + Default + Debug,
+    F: FnOnce(PlayerInput<M>) -> PlayerOutput<M>,
+{
+    run_bot_with_warm_up(|start: MatchStart<M>| start.memory, tick)
+}
+
+/// Same as [`run_bot`] but calls `warm_up` instead of `tick` when the engine sends a `MatchStart`
+/// before the first turn. The memory `warm_up` returns is the memory of the first turn, this is
+/// where the results of expensive preparations are stored.
+pub fn run_bot_with_warm_up<M, W, F>(warm_up: W, tick: F)
+where
+    M: Serialize + DeserializeOwned + Default + Debug,
+    W: FnOnce(MatchStart<M>) -> M,
+    F: FnOnce(PlayerInput<M>) -> PlayerOutput<M>,
+{
+    let line = match read_input() {
+        Ok(line) => line,
+        Err(err) => fail(format!("could not read input: {}", err)),
+    };
+
+    match play_turn(&line, warm_up, tick) {
+        Ok(output) => println!("{}{}", OUTPUT_PREFIX, output),
+        Err(err) => fail(err),
+    }
+}
+
+/// Parses the JSON of the input, calls `warm_up` or `tick` with it depending on the kind of input
+/// and returns the JSON of the output
+fn play_turn<M, W, F>(input: &str, warm_up: W, tick: F) -> Result<String, String>
+where
+    M: Serialize + DeserializeOwned + Default + Debug,
+    W: FnOnce(MatchStart<M>) -> M,
+    F: FnOnce(PlayerInput<M>) -> PlayerOutput<M>,
+{
+    let input: Value =
+        serde_json::from_str(input).map_err(|err| format!("could not parse input: {}", err))?;
+
+    // Check the version first, an input of another version may not parse at all
+    let version = input
+        .get("match_start")
+        .unwrap_or(&input)
+        .get("version")
+        .and_then(Value::as_u64);
+    if let Some(version) = version {
+        check_version(version as usize)?;
+    }
+    let mut output = if input.get("match_start").is_some() {
+        let start = parse_match_start::<M>(input)?;
+        PlayerOutput {
+            actions: Vec::new(),
+            memory: warm_up(start),
+            version: None,
+        }
+    } else {
+        tick(parse_input::<M>(input)?)
+    };
+    output.version = Some(API_VERSION);
+    serde_json::to_string(&output).map_err(|err| format!("could not serialize output: {}", err))
+}
+
+/// Reads the JSON of the input of a turn from stdin. Large inputs are written by the engine in
+/// parts, one per line, which are put back together.
+fn read_input() -> std::io::Result<String> {
+    let stdin = std::io::stdin();
+    let mut lines = stdin.lock().lines();
+    let first = lines.next().transpose()?.unwrap_or_default();
+    let mut part = match first.strip_prefix(INPUT_CHUNK_PREFIX) {
+        Some(part) => part.to_string(),
+        None => return Ok(first),
+    };
+
+    let mut input = String::new();
+    loop {
+        input.push_str(&part);
+        part = match lines.next().transpose()? {
+            Some(line) if line == INPUT_END => return Ok(input),
+            Some(line) => match line.strip_prefix(INPUT_CHUNK_PREFIX) {
+                Some(part) => part.to_string(),
+                None => {
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        "expected another part of the input",
+                    ))
+                }
+            },
+            None => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::UnexpectedEof,
+                    "the input ended before all its parts were read",
+                ))
+            }
+        };
+    }
+}
+
+/// Parses the input of a turn and converts the memory to `M`
+fn parse_input<M: DeserializeOwned + Default + Debug>(
+    input: Value,
+) -> Result<PlayerInput<M>, String> {
+    let input: PlayerInput<Value> =
+        serde_json::from_value(input).map_err(|err| format!("could not parse input: {}", err))?;
+    check_version(input.version)?;
+
+    Ok(PlayerInput {
+        version: input.version,
+        player_id: input.player_id,
+        turn: input.turn,
+        world_info: input.world_info,
+        world: input.world,
+        memory: serde_json::from_value(input.memory).unwrap_or_default(),
+        noises: input.noises,
+        changed_tiles: input.changed_tiles,
+    })
+}
+
+/// Parses the `MatchStart` sent before the first turn and converts the memory to `M`
+fn parse_match_start<M: DeserializeOwned + Default + Debug>(
+    input: Value,
+) -> Result<MatchStart<M>, String> {
+    let MatchStartMessage { match_start: start } =
+        serde_json::from_value::<MatchStartMessage<Value>>(input)
+            .map_err(|err| format!("could not parse match start: {}", err))?;
+    check_version(start.version)?;
+    Ok(MatchStart {
+        version: start.version,
+        player_id: start.player_id,
+        world_info: start.world_info,
+        world: start.world,
+        memory: serde_json::from_value(start.memory).unwrap_or_default(),
+    })
+}
+
+/// Fails if the engine uses a different version of the api than the bot was built with
+fn check_version(version: usize) -> Result<(), String> {
+    if version != API_VERSION {
+        return Err(format!(
+            "the engine uses api version {} but this bot was built for version {}",
+            version, API_VERSION
+        ));
+    }
+    Ok(())
+}
+
+/// Reports an error to the engine and exits
+fn fail(message: impl AsRef<str>) -> ! {
+    eprintln!("{}", message.as_ref());
+    std::process::exit(1)
+}
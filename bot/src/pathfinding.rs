@@ -0,0 +1,89 @@
+use mlr_api::{Coord, Direction, TileGrid};
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, VecDeque};
+
+/// Finds the shortest path over known, enterable tiles from `from` to the closest tile for which
+/// `is_goal` returns true using a breadth first search. Returns the directions to walk or `None` if
+/// no such tile can be reached.
+pub fn find_path(
+    grid: &TileGrid,
+    from: Coord,
+    is_goal: impl Fn(Coord) -> bool,
+) -> Option<Vec<Direction>> {
+    let mut came_from = HashMap::new();
+    let mut queue = VecDeque::new();
+    queue.push_back(from);
+    came_from.insert(from, None);
+
+    while let Some(coord) = queue.pop_front() {
+        if is_goal(coord) {
+            return Some(reconstruct_path(&came_from, coord));
+        }
+        for direction in Direction::all_directions() {
+            let neighbor = coord + direction;
+            if !can_enter(grid, neighbor) || came_from.contains_key(&neighbor) {
+                continue;
+            }
+            came_from.insert(neighbor, Some((coord, direction)));
+            queue.push_back(neighbor);
+        }
+    }
+
+    None
+}
+
+/// Finds the shortest path over known, enterable tiles from `from` to `to` using A*. Returns the
+/// directions to walk or `None` if `to` cannot be reached.
+pub fn find_path_to(grid: &TileGrid, from: Coord, to: Coord) -> Option<Vec<Direction>> {
+    let mut came_from = HashMap::new();
+    let mut cost = HashMap::new();
+    let mut open = BinaryHeap::new();
+    came_from.insert(from, None);
+    cost.insert(from, 0);
+    open.push(Reverse((from.manhattan_distance(to), 0, (from.x, from.y))));
+
+    while let Some(Reverse((_, current_cost, (x, y)))) = open.pop() {
+        let coord = Coord::new(x, y);
+        if coord == to {
+            return Some(reconstruct_path(&came_from, coord));
+        }
+        if cost.get(&coord).map_or(false, |c| *c < current_cost) {
+            continue;
+        }
+        for direction in Direction::all_directions() {
+            let neighbor = coord + direction;
+            if !can_enter(grid, neighbor) {
+                continue;
+            }
+            let neighbor_cost = current_cost + 1;
+            if cost.get(&neighbor).map_or(true, |c| neighbor_cost < *c) {
+                cost.insert(neighbor, neighbor_cost);
+                came_from.insert(neighbor, Some((coord, direction)));
+                let estimate = neighbor_cost + neighbor.manhattan_distance(to);
+                open.push(Reverse((estimate, neighbor_cost, (neighbor.x, neighbor.y))));
+            }
+        }
+    }
+
+    None
+}
+
+/// Returns true if the tile at the given coordinate is known and can be entered
+fn can_enter(grid: &TileGrid, coord: Coord) -> bool {
+    grid.get(coord).map_or(false, |tile| tile.can_enter())
+}
+
+/// Walks back from `to` to the start of the search and returns the directions taken
+fn reconstruct_path(
+    came_from: &HashMap<Coord, Option<(Coord, Direction)>>,
+    to: Coord,
+) -> Vec<Direction> {
+    let mut path = Vec::new();
+    let mut coord = to;
+    while let Some(Some((previous, direction))) = came_from.get(&coord) {
+        path.push(*direction);
+        coord = *previous;
+    }
+    path.reverse();
+    path
+}
@@ -0,0 +1,86 @@
+//! Support for the reactor model of the WASI runner of the engine, see [`export_tick!`](crate::export_tick)
+
+use crate::play_turn;
+use mlr_api::{MatchStart, PlayerInput, PlayerOutput};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::cell::RefCell;
+use std::fmt::Debug;
+
+/// Exports the functions the engine calls to play a turn with a bot compiled to WASI, instead of
+/// running its `main` function and talking to it over stdin and stdout. `tick` is the same function
+/// that would be passed to [`run_bot`](crate::run_bot). A warm up function, like the one passed to
+/// [`run_bot_with_warm_up`](crate::run_bot_with_warm_up), can be passed before it as
+/// `export_tick!(warm_up, tick)`.
+///
+/// ```no_run
+/// use mlr_bot::mlr_api::{PlayerInput, PlayerOutput};
+///
+/// fn tick(input: PlayerInput<()>) -> PlayerOutput<()> {
+///     PlayerOutput {
+///         actions: Vec::new(),
+///         memory: input.memory,
+///         version: None,
+///     }
+/// }
+///
+/// mlr_bot::export_tick!(tick);
+/// # fn main() {}
+/// ```
+#[macro_export]
+macro_rules! export_tick {
+    ($tick:expr) => {
+        $crate::export_tick!(|start: $crate::mlr_api::MatchStart<_>| start.memory, $tick);
+    };
+    ($warm_up:expr, $tick:expr) => {
+        #[no_mangle]
+        pub extern "C" fn mlr_alloc(len: i32) -> i32 {
+            $crate::reactor::alloc(len)
+        }
+
+        #[no_mangle]
+        pub extern "C" fn mlr_tick(ptr: i32, len: i32) -> i64 {
+            $crate::reactor::tick(ptr, len, $warm_up, $tick)
+        }
+    };
+}
+
+thread_local! {
+    /// The output of the previous tick. The engine keeps the same instance between turns, so it
+    /// is kept until the next tick instead of leaking the output of every turn.
+    static OUTPUT: RefCell<Box<[u8]>> = RefCell::new(Box::default());
+}
+
+/// Reserves room for `len` bytes of input, the room is freed again by `tick`
+pub fn alloc(len: i32) -> i32 {
+    let buffer = vec![0u8; len.max(0) as usize].into_boxed_slice();
+    Box::leak(buffer).as_mut_ptr() as i32
+}
+
+/// Plays a turn, or warms up, with the input the engine wrote to memory reserved with `alloc`.
+/// Returns the location of the output, the pointer in the high and the length in the low 32 bits.
+pub fn tick<M, W, F>(ptr: i32, len: i32, warm_up: W, tick: F) -> i64
+where
+    M: Serialize + DeserializeOwned + Default + Debug,
+    W: FnOnce(MatchStart<M>) -> M,
+    F: FnOnce(PlayerInput<M>) -> PlayerOutput<M>,
+{
+    let input = unsafe {
+        Box::from_raw(std::ptr::slice_from_raw_parts_mut(
+            ptr as *mut u8,
+            len.max(0) as usize,
+        ))
+    };
+    let input = match std::str::from_utf8(&input) {
+        Ok(input) => input,
+        Err(err) => panic!("the input is not valid UTF-8: {}", err),
+    };
+    let output = match play_turn(input, warm_up, tick) {
+        Ok(output) => output.into_bytes().into_boxed_slice(),
+        Err(err) => panic!("{}", err),
+    };
+    let len = output.len() as i64;
+    let ptr = output.as_ptr() as u32 as i64;
+    OUTPUT.with(|previous| *previous.borrow_mut() = output);
+    (ptr << 32) | len
+}
@@ -1,10 +1,10 @@
 use mlr_api::{
-    Coord, Direction, PlayerAction, PlayerInput, PlayerOutput, TileType, Unit, UnitId, API_VERSION,
+    ActionKind, Coord, Direction, PlayerAction, PlayerInput, PlayerOutput, TileType, UnitId,
 };
 use serde_derive::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Default, Serialize, Deserialize)]
 struct Memory {
     #[serde(default)]
     directions: HashMap<UnitId, Direction>,
@@ -13,39 +13,20 @@ struct Memory {
     walls: HashSet<Coord>,
 }
 
-/// Returns the direction right from the current direction
-fn right(direction: Direction) -> Direction {
-    match direction {
-        Direction::Left => Direction::Up,
-        Direction::Right => Direction::Down,
-        Direction::Up => Direction::Right,
-        Direction::Down => Direction::Left,
-    }
-}
-
-/// Returns the direction left from the current direction
-fn left(direction: Direction) -> Direction {
-    match direction {
-        Direction::Left => Direction::Down,
-        Direction::Right => Direction::Up,
-        Direction::Up => Direction::Left,
-        Direction::Down => Direction::Right,
-    }
-}
-
 /// This function is called every tick. It should return actions for all the units that the player
 /// owns.
 fn tick(input: PlayerInput<Memory>) -> PlayerOutput<Memory> {
     let PlayerInput {
-        version,
+        version: _,
+        world_info,
         world,
         mut memory,
         player_id,
         turn: _,
+        noises: _,
+        changed_tiles: _,
     } = input;
 
-    assert_eq!(version, API_VERSION, "mismatched api version");
-
     let mut rng = rand::thread_rng();
 
     // Store vision
@@ -55,13 +36,16 @@ fn tick(input: PlayerInput<Memory>) -> PlayerOutput<Memory> {
         }
     }
 
-    // Get all units
-    let (my_units, _other_units): (Vec<&Unit>, Vec<&Unit>) =
-        world.units.iter().partition(|u| u.player == player_id);
-
-    // Move all units
+    // Move all units, if moving is allowed in this match
     let mut actions = Vec::new();
-    for unit in my_units {
+    if !world_info.is_allowed(ActionKind::Move) {
+        return PlayerOutput {
+            actions,
+            memory,
+            version: None,
+        };
+    }
+    for unit in world.my_units(player_id) {
         // Get the direction this unit took last time
         let current_direction = memory
             .directions
@@ -70,15 +54,15 @@ fn tick(input: PlayerInput<Memory>) -> PlayerOutput<Memory> {
             .unwrap_or_else(|| Direction::random(&mut rng));
 
         // We always want to go right
-        let mut direction = right(current_direction);
+        let mut direction = current_direction.rotate_cw();
 
         // Check if thats possible, otherwise, face to the left and try again
         let direction = loop {
             let new_pos = unit.location + direction;
-            if new_pos.x > 0 && new_pos.y > 0 && !memory.walls.contains(&new_pos) {
+            if world_info.in_bounds(new_pos) && !memory.walls.contains(&new_pos) {
                 break direction;
             } else {
-                direction = left(direction);
+                direction = direction.rotate_ccw();
             }
         };
 
@@ -92,18 +76,13 @@ fn tick(input: PlayerInput<Memory>) -> PlayerOutput<Memory> {
         });
     }
 
-    PlayerOutput { actions, memory }
+    PlayerOutput {
+        actions,
+        memory,
+        version: None,
+    }
 }
 
 fn main() {
-    let mut str = String::new();
-    std::io::stdin()
-        .read_line(&mut str)
-        .expect("could not read input");
-
-    let output =
-        tick(serde_json::from_str::<PlayerInput<Memory>>(&str).expect("could not convert input"));
-
-    let output_str = serde_json::to_string(&output).unwrap();
-    println!("__mlr_output:{}", output_str);
+    mlr_bot::run_bot(tick)
 }
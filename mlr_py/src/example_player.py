@@ -12,6 +12,10 @@ def turn(input: api.PlayerInput) -> api.PlayerOutput:
         api.Direction.UP,
     ]
 
+    # Moving might not be allowed in this match
+    if not input.world_info.is_allowed(api.ActionType.MOVE):
+        return api.PlayerOutput([])
+
     # Choose a random direction for now
     actions = [
         api.PlayerAction(u.id, random.choice(all_directions))
@@ -0,0 +1,55 @@
+"""Write My Little Robots bots in Python"""
+
+from .api import (
+    API_VERSION,
+    ActionType,
+    Coord,
+    Direction,
+    DropAction,
+    Item,
+    LinkAction,
+    MarkAction,
+    Marker,
+    MatchStart,
+    Noise,
+    Obstacle,
+    PickUpAction,
+    PlayerAction,
+    PlayerInput,
+    PlayerOutput,
+    PlayerWorld,
+    Sighting,
+    Tile,
+    TileType,
+    Unit,
+    WorldInfo,
+    do_turn,
+    from_json,
+)
+
+__all__ = [
+    "API_VERSION",
+    "ActionType",
+    "Coord",
+    "Direction",
+    "DropAction",
+    "Item",
+    "LinkAction",
+    "MarkAction",
+    "Marker",
+    "MatchStart",
+    "Noise",
+    "Obstacle",
+    "PickUpAction",
+    "PlayerAction",
+    "PlayerInput",
+    "PlayerOutput",
+    "PlayerWorld",
+    "Sighting",
+    "Tile",
+    "TileType",
+    "Unit",
+    "WorldInfo",
+    "do_turn",
+    "from_json",
+]
@@ -1,8 +1,17 @@
 import sys
 import json
-from typing import List, Any, Callable
+from typing import List, Any, Callable, Optional
 from enum import Enum
 
+API_VERSION = 2
+"""The version of the protocol between the engine and the players"""
+
+INPUT_CHUNK_PREFIX = "__mlr_chunk:"
+"""Large inputs are written in parts, one per line that starts with this prefix"""
+
+INPUT_END = "__mlr_end"
+"""The line that follows the last part of an input that was written in parts"""
+
 
 class TileType(Enum):
     """The type that a tile can be"""
@@ -10,16 +19,23 @@ class TileType(Enum):
     WALL = "wall"
     FLOOR = "floor"
     EXIT = "exit"
+    GOAL = "goal"
 
     @staticmethod
     def from_json(json: Any):
         t = json["type"]
-        if t == "wall":
+        if isinstance(t, dict) and "custom" in t:
+            # Tiles added by plugins of the engine are treated as floor or wall depending on
+            # whether they can be entered
+            return TileType.FLOOR if t["custom"]["passable"] else TileType.WALL
+        elif t == "wall":
             return TileType.WALL
         elif t == "floor":
             return TileType.FLOOR
         elif t == "exit":
             return TileType.EXIT
+        elif t == "goal":
+            return TileType.GOAL
         else:
             return TileType.WALL
 
@@ -37,6 +53,12 @@ class ActionType(Enum):
     """The type of actions that a player can take"""
 
     MOVE = "move"
+    MARK = "mark"
+    PICK_UP = "pick_up"
+    DROP = "drop"
+    LINK = "link"
+    BREAK = "break"
+    CUSTOM = "custom"
 
 
 class PlayerAction:
@@ -57,6 +79,56 @@ class PlayerAction:
         }
 
 
+class MarkAction:
+    """Places a marker on the tile the unit stands on, only the player itself can see it"""
+
+    def __init__(self, unit_id: int, tag: int):
+        self.unit_id = unit_id
+        self.tag = tag
+
+    def serialize(self):
+        return {
+            "action": ActionType.MARK.value,
+            "unit": self.unit_id,
+            "tag": self.tag,
+        }
+
+
+class PickUpAction:
+    """Picks up the item on the tile the unit stands on"""
+
+    def __init__(self, unit_id: int):
+        self.unit_id = unit_id
+
+    def serialize(self):
+        return {"action": ActionType.PICK_UP.value, "unit": self.unit_id}
+
+
+class DropAction:
+    """Drops the item the unit carries on the tile it stands on"""
+
+    def __init__(self, unit_id: int):
+        self.unit_id = unit_id
+
+    def serialize(self):
+        return {"action": ActionType.DROP.value, "unit": self.unit_id}
+
+
+class LinkAction:
+    """Spends energy to let another unit of the player see further during the next turn"""
+
+    def __init__(self, unit_id: int, target_id: int):
+        self.unit_id = unit_id
+        self.target_id = target_id
+
+    def serialize(self):
+        return {
+            "action": ActionType.LINK.value,
+            "unit": self.unit_id,
+            "target": self.target_id,
+        }
+
+
 class PlayerOutput:
     """The output that has to be sent back"""
 
@@ -69,6 +141,7 @@ class PlayerOutput:
         output = {
             "actions": [a.serialize() for a in self.actions],
             "memory": self.memory,
+            "version": API_VERSION,
         }
         return json.dumps(output)
 
@@ -106,68 +179,302 @@ class Tile:
 class Unit:
     """A unit in the world corresponding to a player"""
 
-    def __init__(self, id: int, player: int, location: Coord):
+    def __init__(
+        self,
+        id: int,
+        player: int,
+        location: Coord,
+        kind: str = "robot",
+        health: Optional[int] = None,
+        energy: Optional[int] = None,
+        status: Optional[List[Any]] = None,
+    ):
         self.id = id
         self.player = player
         self.location = location
+        self.kind = kind
+        self.health = health
+        self.energy = energy
+        self.status = status if status else []
+
+    @staticmethod
+    def from_json(json: Any):
+        return Unit(
+            json["id"],
+            json["player"],
+            Coord.from_json(json["location"]),
+            json.get("kind", "robot"),
+            json.get("health"),
+            json.get("energy"),
+            json.get("status"),
+        )
+
+    def __repr__(self):
+        return f"Unit(id={self.id}, player={self.player}, location={self.location})"
+
+
+class Marker:
+    """A marker that the player placed on a tile"""
+
+    def __init__(self, coord: Coord, tag: int):
+        self.coord = coord
+        self.tag = tag
+
+    def __repr__(self):
+        return f"Marker({self.coord}, {self.tag})"
+
+    @staticmethod
+    def from_json(json: Any):
+        return Marker(Coord.from_json(json["coord"]), json["tag"])
+
+
+class Item:
+    """An objective item that has to be delivered to a goal tile"""
+
+    def __init__(
+        self, id: int, kind: str, location: Coord, carrier: Optional[int], next_move: int
+    ):
+        self.id = id
+        self.kind = kind
+        self.location = location
+        self.carrier = carrier
+        self.next_move = next_move
+
+    def __repr__(self):
+        return f"Item(id={self.id}, location={self.location}, carrier={self.carrier})"
+
+    @staticmethod
+    def from_json(json: Any):
+        return Item(
+            json["id"],
+            json.get("kind", "data_core"),
+            Coord.from_json(json["location"]),
+            json.get("carrier"),
+            json.get("next_move", 0),
+        )
+
+
+class Obstacle:
+    """A crate or rubble that blocks movement and vision until it is broken"""
+
+    def __init__(self, kind: str, location: Coord, durability: int):
+        self.kind = kind
+        self.location = location
+        self.durability = durability
+
+    def __repr__(self):
+        return f"Obstacle({self.kind}, {self.location}, durability={self.durability})"
 
     @staticmethod
     def from_json(json: Any):
-        return Unit(json["id"], json["player"], json["location"])
+        return Obstacle(
+            json["kind"], Coord.from_json(json["location"]), json["durability"]
+        )
+
+
+class Sighting:
+    """The last known location of a unit of another player that is out of sight"""
+
+    def __init__(self, unit: int, player: int, location: Coord, turns_ago: int):
+        self.unit = unit
+        self.player = player
+        self.location = location
+        self.turns_ago = turns_ago
 
     def __repr__(self):
-        return f"Unit(id={self.id}, player={self.id}, location={self.location})"
+        return f"Sighting(unit={self.unit}, location={self.location}, turns_ago={self.turns_ago})"
+
+    @staticmethod
+    def from_json(json: Any):
+        return Sighting(
+            json["unit"], json["player"], Coord.from_json(json["location"]), json["turns_ago"]
+        )
 
 
 class PlayerWorld:
     """The entire world that the player knows"""
 
-    def __init__(self, units: List[Unit], tiles: List[Tile]):
+    def __init__(
+        self,
+        units: List[Unit],
+        tiles: List[Tile],
+        markers: Optional[List[Marker]] = None,
+        items: Optional[List[Item]] = None,
+        sightings: Optional[List[Sighting]] = None,
+        obstacles: Optional[List[Obstacle]] = None,
+    ):
         self.units = units
         self.tiles = tiles
+        self.markers = markers if markers else []
+        self.items = items if items else []
+        self.sightings = sightings if sightings else []
+        self.obstacles = obstacles if obstacles else []
 
     def __repr__(self):
         return f"PlayerWorld(units={self.units}, tiles={self.tiles})"
 
-    def get_units_for(self, player_id: int):
-        return [u for u in self.units if u.id == player_id]
+    def get_units_for(self, player_id: int) -> List[Unit]:
+        return [u for u in self.units if u.player == player_id]
+
+    def get_enemies_of(self, player_id: int) -> List[Unit]:
+        return [u for u in self.units if u.player != player_id]
 
     @staticmethod
     def from_json(json: Any):
         units = []
         for u in json["units"]:
-            units.append(Unit(u["id"], u["player"], Coord.from_json(u["location"])))
+            units.append(Unit.from_json(u))
         tiles = []
         for t in json["tiles"]:
             tiles.append(Tile.from_json(t))
-        return PlayerWorld(units, tiles)
+        markers = [Marker.from_json(m) for m in json.get("markers", [])]
+        items = [Item.from_json(i) for i in json.get("items", [])]
+        sightings = [Sighting.from_json(s) for s in json.get("sightings", [])]
+        obstacles = [Obstacle.from_json(o) for o in json.get("obstacles", [])]
+        return PlayerWorld(units, tiles, markers, items, sightings, obstacles)
+
+
+class WorldInfo:
+    """The dimensions of the world and the rules of the game"""
+
+    def __init__(
+        self,
+        width: int,
+        height: int,
+        vision_range: int,
+        max_turns: Optional[int],
+        actions: List[ActionType],
+        action_points: Optional[int] = None,
+        action_costs: Optional[dict] = None,
+        weather: Optional[List[str]] = None,
+    ):
+        self.width = width
+        self.height = height
+        self.vision_range = vision_range
+        self.max_turns = max_turns
+        self.actions = actions
+        self.action_points = action_points
+        self.action_costs = action_costs or {"move": 1, "custom": 1}
+        # The weather that limits the vision range this turn, e.g. "fog" or "darkness"
+        self.weather = weather if weather else []
+
+    def is_allowed(self, action_type: ActionType) -> bool:
+        """Returns true if units are allowed to perform this type of action in this match"""
+        return action_type in self.actions
+
+    def action_cost(self, action_type: ActionType) -> int:
+        """Returns the number of action points an action of this type costs"""
+        return self.action_costs.get(action_type.value, 1)
+
+    def in_bounds(self, coord: Coord) -> bool:
+        """Returns true if the coordinate lies within the map"""
+        return 0 <= coord.x < self.width and 0 <= coord.y < self.height
+
+    def __repr__(self):
+        return f"WorldInfo(width={self.width}, height={self.height}, vision_range={self.vision_range}, max_turns={self.max_turns}, actions={self.actions})"
+
+    @staticmethod
+    def from_json(json: Any):
+        return WorldInfo(
+            json["width"],
+            json["height"],
+            json["vision_range"],
+            json["max_turns"],
+            [ActionType(a) for a in json["actions"]],
+            json.get("action_points"),
+            json.get("action_costs"),
+            json.get("weather"),
+        )
+
+
+class Noise:
+    """A noise heard by the units of the player, only its rough direction is known"""
+
+    def __init__(self, direction: Direction, intensity: int):
+        self.direction = direction
+        self.intensity = intensity
+
+    def __repr__(self):
+        return f"Noise({self.direction}, {self.intensity})"
+
+    @staticmethod
+    def from_json(json: Any):
+        return Noise(Direction(json["direction"]), json["intensity"])
 
 
 class PlayerInput:
     """The input that the player receives"""
 
     def __init__(
-        self, player_id: int, turn: int, player_world: PlayerWorld, memory: Any
+        self,
+        player_id: int,
+        turn: int,
+        world_info: WorldInfo,
+        player_world: PlayerWorld,
+        memory: Any,
+        noises: Optional[List[Noise]] = None,
+        changed_tiles: Optional[List[Tile]] = None,
     ):
         self.player_id = player_id
         self.turn = turn
+        self.world_info = world_info
         self.player_world = player_world
         self.memory = memory
+        self.noises = noises if noises else []
+        self.changed_tiles = changed_tiles if changed_tiles else []
 
     def get_my_units(self) -> List[Unit]:
         """Get your own units"""
         return self.player_world.get_units_for(self.player_id)
 
+    def get_visible_enemies(self) -> List[Unit]:
+        """Get the units of other players that are within sight"""
+        return self.player_world.get_enemies_of(self.player_id)
+
     def __repr__(self):
         return f"PlayerInput(player_id={self.player_id}, turn={self.turn}, player_world={self.player_world}, memory={self.memory})"
 
     @staticmethod
     def from_json(json: Any):
         version = json["version"]
-        assert(version == 1, "mismatched api version")
+        assert version == API_VERSION, "mismatched api version"
         return PlayerInput(
             json["player_id"],
             json["turn"],
+            WorldInfo.from_json(json["world_info"]),
+            PlayerWorld.from_json(json["world"]),
+            json["memory"],
+            [Noise.from_json(n) for n in json.get("noises", [])],
+            [Tile.from_json(t) for t in json.get("changed_tiles", [])],
+        )
+
+
+class MatchStart:
+    """The input that the player receives once before the first turn, when the rules of the match
+    give players time to warm up"""
+
+    def __init__(
+        self,
+        player_id: int,
+        world_info: WorldInfo,
+        player_world: PlayerWorld,
+        memory: Any,
+    ):
+        self.player_id = player_id
+        self.world_info = world_info
+        self.player_world = player_world
+        self.memory = memory
+
+    def __repr__(self):
+        return f"MatchStart(player_id={self.player_id}, player_world={self.player_world}, memory={self.memory})"
+
+    @staticmethod
+    def from_json(json: Any):
+        version = json["version"]
+        assert version == API_VERSION, "mismatched api version"
+        return MatchStart(
+            json["player_id"],
+            WorldInfo.from_json(json["world_info"]),
             PlayerWorld.from_json(json["world"]),
             json["memory"],
         )
@@ -178,24 +485,59 @@ def from_json(json: Any) -> PlayerInput:
     return PlayerInput.from_json(json)
 
 
-def do_turn(turn_function: Callable[[PlayerInput], PlayerOutput], debug = False):
+def read_input() -> str:
+    """Read the json of the input of a turn from stdin, inputs written in parts are put together"""
+    line = sys.stdin.readline().rstrip("\n")
+    if not line.startswith(INPUT_CHUNK_PREFIX):
+        return line
+    parts = []
+    while line != INPUT_END:
+        if not line.startswith(INPUT_CHUNK_PREFIX):
+            raise ValueError("expected another part of the input")
+        parts.append(line[len(INPUT_CHUNK_PREFIX):])
+        line = sys.stdin.readline()
+        if not line:
+            raise EOFError("the input ended before all its parts were read")
+        line = line.rstrip("\n")
+    return "".join(parts)
+
+
+def do_turn(
+    turn_function: Callable[[PlayerInput], PlayerOutput],
+    debug = False,
+    warm_up_function: Optional[Callable[[MatchStart], Any]] = None,
+):
     """
     Read from stdin and call the turn function
 
     :param turn_function: The function that does the actual player turn
     :param debug: When debugging also print the input/output so that you can analyze it
+    :param warm_up_function: Called instead of the turn function when the match starts with a warm
+        up, returns the memory of the first turn. Without it the memory is kept as is.
     """
-    for line in sys.stdin:
-        # Convert to json
-        player_input = from_json(json.loads(line))
+    text = read_input()
+    if not text:
+        return
+
+    # Convert to json
+    message = json.loads(text)
+    if "match_start" in message:
+        match_start = MatchStart.from_json(message["match_start"])
+        if debug:
+            print(f"{match_start}")
+        memory = match_start.memory
+        if warm_up_function:
+            memory = warm_up_function(match_start)
+        player_output = PlayerOutput([], memory)
+    else:
+        player_input = from_json(message)
         if debug:
             print(f"{player_input}")
         # Call the supplied function
         player_output = turn_function(player_input)
 
-        if debug:
-            print(f"{player_output.to_json()}")
-        # Now write to stdout
-        print(f"__mlr_output:{player_output.to_json()}")
-        break
+    if debug:
+        print(f"{player_output.to_json()}")
+    # Now write to stdout
+    print(f"__mlr_output:{player_output.to_json()}")
 
@@ -1,73 +1,626 @@
-use crate::{GameState, Player, PlayerRunner, World};
-use async_std::sync::Sender;
-use mlr_api::{Coord, PlayerId};
-use serde_json::json;
+use crate::control::{sleep, Controller, Deadline};
+use crate::{
+    BattleControl, GameEvent, GameRules, GameState, Map, Player, PlayerLives, PlayerPenalties,
+    PlayerRunner, Plugin, Plugins, TieBreak, World, WorldDiff, WorldUpdate,
+};
+use futures::channel::mpsc::{Sender, UnboundedReceiver};
+use futures::SinkExt;
+use mlr_api::{Coord, ItemKind, ObstacleKind, PlayerId, PlayerMemory};
+use serde_derive::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::HashMap;
 use std::time::Duration;
+use thiserror::Error;
 
 /// A `Battle` is a struct that contains information about a battle to be played
 pub struct Battle {
-    players: Vec<Box<dyn PlayerRunner>>,
+    players: Vec<(String, Box<dyn PlayerRunner>)>,
+    map: Option<Map>,
+    spawns: Vec<Coord>,
+    items: Vec<(ItemKind, Coord)>,
+    obstacles: Vec<(ObstacleKind, Coord)>,
+    memories: HashMap<PlayerId, PlayerMemory>,
+    rules: GameRules,
+    plugins: Plugins,
+    observers: Vec<Box<dyn BattleObserver>>,
+    control: Option<UnboundedReceiver<BattleControl>>,
+    deadline: Option<Duration>,
 }
 
 impl Default for Battle {
     fn default() -> Self {
         Battle {
             players: Default::default(),
+            map: None,
+            spawns: Vec::new(),
+            items: Vec::new(),
+            obstacles: Vec::new(),
+            memories: HashMap::new(),
+            rules: GameRules::default(),
+            plugins: Plugins::default(),
+            observers: Vec::new(),
+            control: None,
+            deadline: None,
         }
     }
 }
 
+/// A `BattleObserver` is notified of everything that happens during a battle.
+pub trait BattleObserver: Send {
+    /// Called once before the first turn is played with the initial state of the world, the names
+    /// of the players indexed by `PlayerId` and the rules under which the battle is played.
+    fn on_start(&mut self, _world: &World, _players: &[String], _rules: &GameRules) {}
+
+    /// Called after every turn with the new state of the world and the events that occurred
+    /// during the turn.
+    fn on_turn(&mut self, _world: &World, _events: &[GameEvent]) {}
+
+    /// Called once when the battle has ended.
+    fn on_end(&mut self, _world: &World, _result: &MatchResult) {}
+}
+
+/// The outcome of a battle
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct MatchResult {
+    /// The player that won the battle or `None` if the battle ended in a draw
+    pub winner: Option<PlayerId>,
+
+    /// The number of turns that were played
+    pub turns: usize,
+
+    /// The misbehavior of every player and the penalties that were applied, indexed by `PlayerId`
+    #[serde(default)]
+    pub penalties: Vec<PlayerPenalties>,
+
+    /// True if the battle was aborted before it was decided
+    #[serde(default)]
+    pub aborted: bool,
+
+    /// The players that completed the objective in the same turn, ordered by `PlayerId`. Empty
+    /// unless the battle was decided by the tie break rule, see `GameRules::tie_break`.
+    #[serde(default)]
+    pub tied: Vec<PlayerId>,
+
+    /// The tie break rule that decided the battle, `None` if there was no tie
+    #[serde(default)]
+    pub tie_break: Option<TieBreak>,
+
+    /// True if the battle hit its turn limit or deadline and the winner was decided by the progress
+    /// the players made, see `ranking`
+    #[serde(default)]
+    pub adjudicated: bool,
+
+    /// The players ordered from best to worst when the battle hit its turn limit or deadline, by
+    /// their progress towards the objective and then by the number of invalid actions they sent.
+    /// Eliminated players come last. Empty if the battle ended otherwise.
+    #[serde(default)]
+    pub ranking: Vec<PlayerId>,
+
+    /// The memory of every player at the end of the battle, indexed by `PlayerId`. Not stored in
+    /// replays.
+    #[serde(skip)]
+    pub memories: Vec<PlayerMemory>,
+}
+
 impl Battle {
     /// Adds a player to the battle
     pub fn add_player(&mut self, player: Box<dyn PlayerRunner>) -> PlayerId {
+        let name = format!("Player {}", self.players.len());
+        self.add_named_player(name, player)
+    }
+
+    /// Adds a player with a human readable name to the battle
+    pub fn add_named_player(
+        &mut self,
+        name: impl Into<String>,
+        player: Box<dyn PlayerRunner>,
+    ) -> PlayerId {
         let player_id = PlayerId(self.players.len());
-        self.players.push(player);
+        self.players.push((name.into(), player));
         player_id
     }
+
+    /// Sets the map on which the battle is played. If no map is set a default map is generated.
+    pub fn set_map(&mut self, map: Map) {
+        self.map = Some(map);
+    }
+
+    /// Sets the locations at which the units of the players are spawned, the first player spawns
+    /// at the first location and so on. If there are more players than locations the locations
+    /// are reused. If no locations are set every player gets its own location on the map, spread
+    /// out as far as possible, see `Map::spawn_points`.
+    pub fn set_spawns(&mut self, spawns: Vec<Coord>) {
+        self.spawns = spawns;
+    }
+
+    /// Sets the objective items that lie on the map when the battle starts
+    pub fn set_items(&mut self, items: Vec<(ItemKind, Coord)>) {
+        self.items = items;
+    }
+
+    /// Sets the obstacles that stand on the map when the battle starts. Obstacles that would end
+    /// up on a wall or on a spawned unit are left out.
+    pub fn set_obstacles(&mut self, obstacles: Vec<(ObstacleKind, Coord)>) {
+        self.obstacles = obstacles;
+    }
+
+    /// Sets the memory the player starts the battle with, by default players start without any
+    /// memory
+    pub fn set_memory(&mut self, player: PlayerId, memory: PlayerMemory) {
+        self.memories.insert(player, memory);
+    }
+
+    /// Adds an observer that is notified of everything that happens during the battle
+    pub fn add_observer(&mut self, observer: Box<dyn BattleObserver>) {
+        self.observers.push(observer);
+    }
+
+    /// Sets the channel over which the battle can be paused, stepped and sped up while it is
+    /// running
+    pub fn set_control(&mut self, control: UnboundedReceiver<BattleControl>) {
+        self.control = Some(control);
+    }
+
+    /// Limits the time the whole battle may take. Once the deadline passes no more turns are
+    /// played and the battle is decided like when it hits its turn limit, see
+    /// `MatchResult::ranking`. The deadline includes the time the battle was paused. Without the
+    /// `native` feature there is no clock and the deadline is ignored.
+    pub fn set_deadline(&mut self, deadline: Duration) {
+        self.deadline = Some(deadline);
+    }
+
+    /// Sets the rules under which the battle is played
+    pub fn set_rules(&mut self, rules: GameRules) {
+        self.rules = rules;
+    }
+
+    /// Adds a plugin that extends the game with new actions, tiles or resolution phases. Custom
+    /// actions are only accepted if the rules allow `ActionKind::Custom`.
+    pub fn add_plugin(&mut self, plugin: impl Plugin) {
+        log::debug!("adding plugin {}", plugin.name());
+        plugin.build(&mut self.plugins);
+    }
+
+    /// Returns everything that was registered by the plugins of the battle
+    pub fn plugins(&self) -> &Plugins {
+        &self.plugins
+    }
+
+    /// Sets the maximum number of turns after which the battle ends. The player that made the most
+    /// progress towards the objective then wins, see `MatchResult::ranking`.
+    pub fn set_max_turns(&mut self, max_turns: Option<usize>) {
+        self.rules.max_turns = max_turns;
+    }
+
+    /// Checks that the battle can be played. Without explicit spawn locations every player needs
+    /// a floor tile of its own on the map, within the spawn tolerance of the rules if any.
+    pub fn check(&self) -> Result<(), BattleError> {
+        if let (Some(map), true) = (&self.map, self.spawns.is_empty()) {
+            let available = map.spawn_points(self.players.len()).len();
+            if available < self.players.len() {
+                return Err(BattleError::TooManyPlayers {
+                    players: self.players.len(),
+                    available,
+                });
+            }
+            if let Some(tolerance) = self.rules.spawn_tolerance {
+                if map
+                    .fair_spawn_points(self.players.len(), tolerance)
+                    .is_none()
+                {
+                    return Err(BattleError::NoFairSpawns { tolerance });
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// An error that prevents a battle from being played
+#[derive(Error, Debug, Clone, Eq, PartialEq)]
+pub enum BattleError {
+    #[error("the map only has room for {available} players but {players} are taking part")]
+    TooManyPlayers { players: usize, available: usize },
+
+    #[error(
+        "the map has no spawn points whose distances to an exit differ by at most {tolerance}"
+    )]
+    NoFairSpawns { tolerance: usize },
 }
 
 impl Battle {
-    /// Runs the battle to completion, returns the winning player.
+    /// Runs the battle to completion, returns the outcome of the battle. `tick_duration` specifies
+    /// the initial time to wait between turns. If `tick_update` is specified a snapshot of the
+    /// initial world is sent over it, followed by the changes of every turn.
+    ///
+    /// The battle does not depend on a specific async runtime; it can be driven by any executor.
+    /// Without the `native` feature there are no timers, turns are played without waiting and
+    /// pacing the battle is up to the caller.
     pub async fn run(
         self,
         tick_duration: Option<Duration>,
-        tick_update: Option<Sender<World>>,
-    ) -> PlayerId {
+        mut tick_update: Option<Sender<WorldUpdate>>,
+    ) -> MatchResult {
+        let mut observers = self.observers;
+        let mut controller = Controller::new(self.control, tick_duration);
+        let deadline = Deadline::after(self.deadline);
+        let player_names = self
+            .players
+            .iter()
+            .map(|(name, _)| name.clone())
+            .collect::<Vec<_>>();
+        let mut memories = self.memories;
         let players = self
             .players
             .into_iter()
             .enumerate()
-            .map(|(i, runner)| Player {
+            .map(|(i, (name, runner))| Player {
                 id: PlayerId(i),
+                name,
                 runner,
-                memory: json!({}),
+                memory: memories.remove(&PlayerId(i)).unwrap_or_default(),
+                penalties: PlayerPenalties::default(),
+                lives: PlayerLives::default(),
             })
             .collect::<Vec<_>>();
 
         let mut game_state = GameState {
             players,
-            world: World::default(),
+            world: self.map.map(World::new).unwrap_or_default(),
+            rules: self.rules,
+            plugins: self.plugins,
+            spawns: Vec::new(),
+            views: Default::default(),
         };
 
         // Spawn a unit for every player
-        for (i, player) in game_state.players.iter().enumerate() {
+        let spawns = if self.spawns.is_empty() {
+            let map = &game_state.world.map;
+            let count = game_state.players.len();
             game_state
-                .world
-                .spawn_unit(player.id, Coord::new(10 + i as isize * 10, 10));
+                .rules
+                .spawn_tolerance
+                .and_then(|tolerance| map.fair_spawn_points(count, tolerance))
+                .unwrap_or_else(|| map.spawn_points(count))
+        } else {
+            self.spawns
+        };
+        for (i, player) in game_state.players.iter().enumerate() {
+            let location = match spawns.len() {
+                0 => Coord::new(10 + i as isize * 10, 10),
+                len => spawns[i % len],
+            };
+            let unit = game_state.world.spawn_unit(player.id, location);
+            if let Some(unit) = game_state.world.unit_mut(unit) {
+                unit.energy = game_state.rules.starting_energy;
+            }
+            game_state.spawns.push(location);
+        }
+        for &(kind, location) in self.items.iter() {
+            game_state.world.spawn_item(kind, location);
+        }
+        for &(kind, location) in self.obstacles.iter() {
+            game_state.world.place_obstacle(kind, location);
+        }
+
+        for observer in observers.iter_mut() {
+            observer.on_start(&game_state.world, &player_names, &game_state.rules);
+        }
+        if let Some(sender) = &mut tick_update {
+            let _ = sender
+                .send(WorldUpdate::Snapshot(game_state.world.clone()))
+                .await;
         }
 
-        // Run the turn in a loop
-        loop {
-            game_state = game_state.turn().await;
-            if let Some(sender) = &tick_update {
-                sender.send(game_state.world.clone()).await
+        // Give the players time to prepare before the first turn
+        game_state.start_match().await;
+
+        // Run the turn in a loop until there is a winner or the battle ends in a draw
+        let mut aborted = false;
+        let mut ranking = Vec::new();
+        let mut tied = Vec::new();
+        let mut total_time = vec![0u64; game_state.players.len()];
+        let winner = loop {
+            if !controller.wait_for_turn().await {
+                aborted = true;
+                break None;
+            }
+            let previous_world = tick_update.as_ref().map(|_| game_state.world.clone());
+            let events = game_state.turn().await;
+            for event in events.iter() {
+                if let GameEvent::RunnerTimed { player, micros } = event {
+                    total_time[player.0] += micros;
+                }
+            }
+            for observer in observers.iter_mut() {
+                observer.on_turn(&game_state.world, &events);
+            }
+            if let (Some(sender), Some(previous_world)) = (&mut tick_update, previous_world) {
+                let diff = WorldDiff::between(&previous_world, &game_state.world, &events);
+                let _ = sender.send(WorldUpdate::Diff(diff)).await;
             }
-            if let Some(unit) = game_state.world.units_on_exits().next() {
-                break unit.player;
+            let finished = game_state.world.finished_players(&game_state.rules);
+            match finished.len() {
+                0 => {}
+                1 => break Some(finished[0]),
+                _ => {
+                    let winner = break_tie(&finished, &total_time, game_state.rules.tie_break);
+                    tied = finished;
+                    break winner;
+                }
             }
-            if let Some(duration) = &tick_duration {
-                async_std::task::sleep(*duration).await;
+            let mut remaining = game_state.remaining_players();
+            match (remaining.next(), remaining.next()) {
+                (None, _) => break None,
+                (Some(winner), None) if game_state.players.len() > 1 => break Some(winner),
+                _ => {}
             }
+            if game_state.rules.max_turns == Some(game_state.world.turn) || deadline.passed() {
+                ranking = rank_players(&game_state);
+                break adjudicated_winner(&game_state, &ranking);
+            }
+            if let Some(duration) = controller.tick_duration() {
+                sleep(duration).await;
+            }
+        };
+
+        let result = MatchResult {
+            winner,
+            turns: game_state.world.turn,
+            penalties: game_state
+                .players
+                .iter()
+                .map(|player| player.penalties.clone())
+                .collect(),
+            aborted,
+            tie_break: if tied.is_empty() {
+                None
+            } else {
+                Some(game_state.rules.tie_break)
+            },
+            tied,
+            adjudicated: !ranking.is_empty(),
+            ranking,
+            memories: game_state
+                .players
+                .iter()
+                .map(|player| player.memory.clone())
+                .collect(),
+        };
+
+        for observer in observers.iter_mut() {
+            observer.on_end(&game_state.world, &result);
         }
+
+        result
+    }
+}
+
+/// Orders all players from best to worst by their progress towards the objective, see
+/// `World::progress`, and then by the number of invalid actions they sent, the fewest first.
+/// Players that were eliminated or lost all their units come last.
+fn rank_players(game_state: &GameState) -> Vec<PlayerId> {
+    let mut ranking = game_state
+        .players
+        .iter()
+        .map(|player| player.id)
+        .collect::<Vec<_>>();
+    ranking.sort_by_key(|&player| Reverse(rank_key(game_state, player)));
+    ranking
+}
+
+/// Returns the first player of the `ranking` if it ranks strictly higher than the second player and
+/// was not eliminated, otherwise the battle ends in a draw. A player that plays alone cannot win
+/// by progress, it has to complete the objective.
+fn adjudicated_winner(game_state: &GameState, ranking: &[PlayerId]) -> Option<PlayerId> {
+    match ranking {
+        [first, second, ..] => {
+            let key = rank_key(game_state, *first);
+            let remaining = key.0;
+            if remaining && key != rank_key(game_state, *second) {
+                Some(*first)
+            } else {
+                None
+            }
+        }
+        _ => None,
+    }
+}
+
+/// The key by which `rank_players` orders the players, higher is better
+fn rank_key(game_state: &GameState, player: PlayerId) -> (bool, i64, Reverse<usize>) {
+    let remaining = game_state
+        .remaining_players()
+        .any(|remaining| remaining == player);
+    (
+        remaining,
+        game_state.world.progress(player, &game_state.rules),
+        Reverse(game_state.players[player.0].penalties.invalid_actions),
+    )
+}
+
+/// Decides the winner among the `tied` players that completed the objective in the same turn.
+/// `total_time` contains the time in microseconds every player spent running its turns, indexed by
+/// `PlayerId`.
+fn break_tie(tied: &[PlayerId], total_time: &[u64], tie_break: TieBreak) -> Option<PlayerId> {
+    match tie_break {
+        TieBreak::Draw => None,
+        TieBreak::LowestTime => {
+            let fastest = tied.iter().map(|player| total_time[player.0]).min()?;
+            let mut fastest_players = tied.iter().filter(|player| total_time[player.0] == fastest);
+            match (fastest_players.next(), fastest_players.next()) {
+                (Some(&winner), None) => Some(winner),
+                _ => None,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mlr_api::TileType;
+
+    /// An open map with exits along its left side
+    fn map() -> Map {
+        let mut map = Map::new(10, 3);
+        for y in 0..3 {
+            map[Coord::new(0, y)] = TileType::Exit;
+        }
+        map.compute_distance_to_exit();
+        map
+    }
+
+    /// A game state on `map`, the unit of the n-th player spawns at the n-th location
+    fn game_state(spawns: &[Coord]) -> GameState {
+        let mut world = World::new(map());
+        let players = spawns
+            .iter()
+            .enumerate()
+            .map(|(i, &location)| {
+                world.spawn_unit(PlayerId(i), location);
+                Player {
+                    id: PlayerId(i),
+                    name: format!("Player {}", i),
+                    runner: Box::new(|_| Err(crate::RunnerError::NoData)),
+                    memory: PlayerMemory::default(),
+                    penalties: PlayerPenalties::default(),
+                    lives: PlayerLives::default(),
+                }
+            })
+            .collect();
+        GameState {
+            players,
+            world,
+            rules: GameRules::default(),
+            plugins: Plugins::default(),
+            spawns: spawns.to_vec(),
+            views: Default::default(),
+        }
+    }
+
+    /// A battle on `map` between players that never move
+    fn idle_battle(spawns: Vec<Coord>) -> Battle {
+        let mut battle = Battle::default();
+        battle.set_map(map());
+        for _ in spawns.iter() {
+            battle.add_player(Box::new(|input: crate::PlayerInput| {
+                Ok(crate::PlayerOutput {
+                    actions: Vec::new(),
+                    memory: input.memory,
+                    version: None,
+                })
+            }));
+        }
+        battle.set_spawns(spawns);
+        battle
+    }
+
+    #[test]
+    fn turn_limit_is_decided_by_progress() {
+        let mut battle = idle_battle(vec![Coord::new(6, 1), Coord::new(3, 1)]);
+        battle.set_max_turns(Some(3));
+        let result = futures::executor::block_on(battle.run(None, None));
+        assert_eq!(result.turns, 3);
+        assert!(result.adjudicated);
+        assert_eq!(result.winner, Some(PlayerId(1)));
+        assert_eq!(result.ranking, vec![PlayerId(1), PlayerId(0)]);
+    }
+
+    #[test]
+    fn turn_limit_with_equal_progress_is_a_draw() {
+        let mut battle = idle_battle(vec![Coord::new(3, 0), Coord::new(3, 2)]);
+        battle.set_max_turns(Some(3));
+        let result = futures::executor::block_on(battle.run(None, None));
+        assert!(result.adjudicated);
+        assert_eq!(result.winner, None);
+    }
+
+    #[cfg(feature = "native")]
+    #[test]
+    fn deadline_is_decided_like_the_turn_limit() {
+        let mut battle = idle_battle(vec![Coord::new(6, 1), Coord::new(3, 1)]);
+        battle.set_deadline(Duration::from_secs(0));
+        let result = futures::executor::block_on(battle.run(None, None));
+        assert_eq!(result.turns, 1);
+        assert!(result.adjudicated);
+        assert_eq!(result.winner, Some(PlayerId(1)));
+    }
+
+    #[test]
+    fn ranks_players_by_progress() {
+        let game_state = game_state(&[Coord::new(5, 1), Coord::new(2, 1), Coord::new(8, 1)]);
+        let ranking = rank_players(&game_state);
+        assert_eq!(ranking, vec![PlayerId(1), PlayerId(0), PlayerId(2)]);
+        assert_eq!(adjudicated_winner(&game_state, &ranking), Some(PlayerId(1)));
+    }
+
+    #[test]
+    fn ranks_equal_progress_by_invalid_actions() {
+        let mut game_state = game_state(&[Coord::new(4, 0), Coord::new(4, 2)]);
+        game_state.players[0].penalties.invalid_actions = 2;
+        let ranking = rank_players(&game_state);
+        assert_eq!(ranking, vec![PlayerId(1), PlayerId(0)]);
+        assert_eq!(adjudicated_winner(&game_state, &ranking), Some(PlayerId(1)));
+    }
+
+    #[test]
+    fn equal_progress_and_invalid_actions_is_a_draw() {
+        let game_state = game_state(&[Coord::new(4, 0), Coord::new(4, 2)]);
+        let ranking = rank_players(&game_state);
+        assert_eq!(ranking, vec![PlayerId(0), PlayerId(1)]);
+        assert_eq!(adjudicated_winner(&game_state, &ranking), None);
+    }
+
+    #[test]
+    fn ranks_eliminated_players_last() {
+        let mut game_state = game_state(&[Coord::new(1, 1), Coord::new(6, 1), Coord::new(3, 1)]);
+        game_state.players[0].penalties.eliminated = true;
+        game_state
+            .world
+            .units
+            .retain(|unit| unit.player != PlayerId(2));
+        let ranking = rank_players(&game_state);
+        assert_eq!(ranking, vec![PlayerId(1), PlayerId(0), PlayerId(2)]);
+        assert_eq!(adjudicated_winner(&game_state, &ranking), Some(PlayerId(1)));
+    }
+
+    #[test]
+    fn eliminated_players_do_not_win() {
+        let mut game_state = game_state(&[Coord::new(1, 1), Coord::new(6, 1)]);
+        game_state.players[0].penalties.eliminated = true;
+        game_state.players[1].penalties.eliminated = true;
+        let ranking = rank_players(&game_state);
+        assert_eq!(ranking, vec![PlayerId(0), PlayerId(1)]);
+        assert_eq!(adjudicated_winner(&game_state, &ranking), None);
+    }
+
+    #[test]
+    fn a_single_player_does_not_win_by_progress() {
+        let game_state = game_state(&[Coord::new(1, 1)]);
+        let ranking = rank_players(&game_state);
+        assert_eq!(ranking, vec![PlayerId(0)]);
+        assert_eq!(adjudicated_winner(&game_state, &ranking), None);
+    }
+
+    #[test]
+    fn ties_are_broken_by_the_lowest_time() {
+        let tied = [PlayerId(0), PlayerId(2)];
+        let total_time = [300, 100, 200];
+        assert_eq!(
+            break_tie(&tied, &total_time, TieBreak::LowestTime),
+            Some(PlayerId(2))
+        );
+        assert_eq!(break_tie(&tied, &total_time, TieBreak::Draw), None);
+    }
+
+    #[test]
+    fn ties_with_equal_times_are_a_draw() {
+        let tied = [PlayerId(0), PlayerId(1)];
+        assert_eq!(
+            break_tie(&tied, &[100, 100, 50], TieBreak::LowestTime),
+            None
+        );
+        assert_eq!(break_tie(&[], &[], TieBreak::LowestTime), None);
     }
 }
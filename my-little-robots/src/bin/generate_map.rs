@@ -1,6 +1,6 @@
 use bracket_lib::prelude::*;
-use mlr::bracket_lib::draw_map;
-use mlr::map_builder::new_map_with_history;
+use mlr::bracket_lib::{draw_distance_overlay, draw_map};
+use mlr::map_builder::{new_map_with_history, MapWithSnapshots};
 use mlr::Map;
 
 fn main() {
@@ -11,71 +11,108 @@ fn main() {
 }
 
 fn try_main() -> BError {
-    let context = BTermBuilder::simple80x50()
-        .with_fancy_console(80, 50, "terminal8x8.png".to_string())
-        .with_title("My Little Robots - Map Generator")
-        .build()?;
-
     //let mut builder = mlr::map_builder::SimpleMapBuilder;
     //let mut builder = mlr::map_builder::PrimMazeBuilder;
     let mut builder = mlr::map_builder::CellularAutomata;
 
     let map_history = new_map_with_history(80, 50, &mut builder);
 
+    // Size the console to fit the generated map
+    let map = map_history.initial_map().clone();
+    let (width, height) = (map.width, map.height);
+    let context = BTermBuilder::simple(width, height)?
+        .with_fancy_console(width, height, "terminal8x8.png".to_string())
+        .with_title("My Little Robots - Map Generator")
+        .with_automatic_console_resize(true)
+        .build()?;
+
     main_loop(
         context,
         ApplicationState {
             map_history,
+            map,
             index: 0,
+            show_distance: true,
         },
     )
 }
 
 struct ApplicationState {
-    map_history: Vec<Map>,
+    map_history: MapWithSnapshots,
+
+    /// The version of the map at `index`, moved back and forth by applying the diffs of the
+    /// snapshots
+    map: Map,
     index: usize,
+    show_distance: bool,
 }
 
-/// Draw the overlay over the map, is currently used to show the distance to the exit
-pub fn draw_overlay(map: &Map, ctx: &mut BTerm) {
-    let height = map.height as isize;
-    let width = map.width as isize;
-
-    for y in 0..height {
-        for x in 0..width {
-            if let Some(_coord) = map.get_distance_to_exit((x, y)) {
-                ctx.set_fancy(
-                    PointF::new(x as f32, y as f32),
-                    1,
-                    Radians(0.0f32),
-                    PointF::new(0.5f32, 0.5f32),
-                    RGBA::from_u8(10, 255, 10, 200),
-                    RGBA::from_u8(0, 0, 0, 0),
-                    to_cp437('x'),
-                );
+impl ApplicationState {
+    /// Moves to the version of the map after the next snapshot, returns false at the last one
+    fn step_forward(&mut self) -> bool {
+        match self.map_history.snapshots().get(self.index + 1) {
+            Some(snapshot) => {
+                snapshot.diff.apply(&mut self.map);
+                self.index += 1;
+                true
             }
+            None => false,
+        }
+    }
+
+    /// Moves to the version of the map before the current snapshot
+    fn step_back(&mut self) {
+        if self.index > 0 {
+            self.map_history.snapshots()[self.index]
+                .diff
+                .revert(&mut self.map);
+            self.index -= 1;
         }
     }
 }
 
 impl GameState for ApplicationState {
     fn tick(&mut self, ctx: &mut BTerm) {
-        match ctx.key {
-            Some(VirtualKeyCode::Space) | Some(VirtualKeyCode::Right)
-                if self.index < self.map_history.len() - 1 =>
-            {
-                self.index += 1
+        let moved = match ctx.key {
+            Some(VirtualKeyCode::Space) | Some(VirtualKeyCode::Right) => self.step_forward(),
+            Some(VirtualKeyCode::Left) => {
+                self.step_back();
+                true
+            }
+            Some(VirtualKeyCode::End) => {
+                while self.step_forward() {}
+                true
+            }
+            Some(VirtualKeyCode::H) => {
+                self.show_distance = !self.show_distance;
+                false
             }
-            Some(VirtualKeyCode::Left) if self.index > 0 => self.index -= 1,
-            Some(VirtualKeyCode::End) => self.index = self.map_history.len() - 1,
-            _ => {}
+            _ => false,
         };
+        if moved {
+            self.map.compute_distance_to_exit();
+        }
 
         // Draw the world
-        ctx.cls();
         ctx.set_active_console(0);
-        draw_map(&self.map_history[self.index], |_| 1.0, ctx);
+        ctx.cls();
+        draw_map(&self.map, |_| 1.0, Point::zero(), ctx);
+        if self.show_distance {
+            draw_distance_overlay(&self.map, Point::zero(), ctx);
+        }
+
+        // Describe the step that produced this version of the map
         ctx.set_active_console(1);
-        draw_overlay(&self.map_history[self.index], ctx);
+        ctx.cls();
+        ctx.print(
+            1,
+            self.map.height - 1,
+            format!(
+                "{}/{}: {}",
+                self.index + 1,
+                self.map_history.snapshots().len(),
+                self.map_history.snapshots()[self.index].label
+            ),
+        );
     }
 }
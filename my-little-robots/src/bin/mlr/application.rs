@@ -1,15 +1,163 @@
 use bracket_lib::prelude::*;
-use mlr::bracket_lib::{draw_map, draw_ui, player_color, unit_glyph};
-use mlr::World;
-use mlr_api::{Coord, UnitId};
-use std::collections::{HashMap, HashSet};
+use futures::channel::mpsc::UnboundedSender;
+use mlr::bracket_lib::{
+    draw_distance_overlay, draw_items, draw_map, draw_obstacles, draw_sidebar, draw_ticker,
+    draw_ui, in_viewport, player_color, unit_glyph, SIDEBAR_WIDTH,
+};
+use mlr::{
+    BattleControl, BattleObserver, GameEvent, GameRules, Map, MatchResult, Scoreboard, World,
+};
+use mlr_api::{Coord, PlayerId, Unit, UnitId};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::ops::Deref;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// The fastest and slowest speed at which the battle can be played
+const MIN_TICK_DURATION: Duration = Duration::from_millis(10);
+const MAX_TICK_DURATION: Duration = Duration::from_millis(2000);
+
+/// The number of previous positions of a unit that are drawn as its trail
+const TRAIL_LENGTH: usize = 12;
+
+/// The number of previous worlds that are kept to rewind to
+const HISTORY_LENGTH: usize = 200;
+
+/// How fast the camera catches up with the units it follows, the fraction of the remaining
+/// distance that is covered every second
+const CAMERA_SPEED: f32 = 4.0;
+
+/// A short animation that is played on top of the world in response to an event
+#[derive(Clone)]
+enum Effect {
+    /// A tile lights up and fades out, e.g. when a unit bumps into a wall
+    Flash { location: Coord, color: RGBA },
+
+    /// Sparks fly out of a tile, e.g. when a unit reaches an exit
+    Sparkle { location: Coord, color: RGBA },
+}
+
+impl Effect {
+    /// Returns the effects that animate the given events
+    fn from_events(events: &[GameEvent], world: &World) -> Vec<Effect> {
+        let mut effects = Vec::new();
+        for event in events {
+            match event {
+                GameEvent::MoveBlocked {
+                    location,
+                    direction,
+                    ..
+                } => effects.push(Effect::Flash {
+                    location: *location + *direction,
+                    color: RGBA::named(ORANGE),
+                }),
+                GameEvent::ObstacleDestroyed { location, .. } => effects.push(Effect::Flash {
+                    location: *location,
+                    color: RGBA::named(ORANGE),
+                }),
+                GameEvent::UnitDied { location, .. } => effects.push(Effect::Flash {
+                    location: *location,
+                    color: RGBA::named(RED),
+                }),
+                GameEvent::UnitRespawned {
+                    player, location, ..
+                } => effects.push(Effect::Sparkle {
+                    location: *location,
+                    color: player_color(*player).into(),
+                }),
+                GameEvent::ExitMoved { to, .. } => effects.push(Effect::Sparkle {
+                    location: *to,
+                    color: RGBA::named(CYAN),
+                }),
+                GameEvent::ItemDelivered {
+                    player, location, ..
+                }
+                | GameEvent::ExitReached {
+                    player, location, ..
+                } => effects.push(Effect::Sparkle {
+                    location: *location,
+                    color: player_color(*player).into(),
+                }),
+                GameEvent::RunnerError { player, .. }
+                | GameEvent::Timeout { player }
+                | GameEvent::InvalidAction { player, .. }
+                | GameEvent::PlayerSuspended { player, .. }
+                | GameEvent::PlayerEliminated { player } => effects.extend(
+                    world
+                        .units
+                        .iter()
+                        .filter(|unit| unit.player == *player)
+                        .map(|unit| Effect::Flash {
+                            location: unit.location,
+                            color: RGBA::named(RED),
+                        }),
+                ),
+                GameEvent::ActionsSubmitted { .. }
+                | GameEvent::RunnerTimed { .. }
+                | GameEvent::UnitMoved { .. }
+                | GameEvent::MarkerPlaced { .. }
+                | GameEvent::NoiseMade { .. }
+                | GameEvent::TilesChanged { .. }
+                | GameEvent::ItemPickedUp { .. }
+                | GameEvent::ItemDropped { .. }
+                | GameEvent::SensorsLinked { .. }
+                | GameEvent::Custom { .. } => {}
+            }
+        }
+        effects
+    }
+
+    /// Draws the effect at the given point of its animation, `t` runs from 0 to 1
+    fn draw(&self, t: f32, offset: Point, map: &Map, ctx: &mut BTerm) {
+        let (location, color) = match self {
+            Effect::Flash { location, color } | Effect::Sparkle { location, color } => {
+                (location, *color)
+            }
+        };
+        let screen = Point::new(location.x, location.y) - offset;
+        if !in_viewport(screen, map) {
+            return;
+        }
+        let center = PointF::new(screen.x as f32, screen.y as f32 + 1.0);
+        let mut color = color;
+        match self {
+            Effect::Flash { .. } => {
+                color.a = 0.8 * (1.0 - t);
+                draw_glyph(ctx, center, color, to_cp437('█'));
+            }
+            Effect::Sparkle { .. } => {
+                color.a = 1.0 - t;
+                for i in 0..8 {
+                    let angle = i as f32 * std::f32::consts::PI / 4.0;
+                    let direction = PointF::new(angle.cos(), angle.sin());
+                    draw_glyph(ctx, center + direction * (t * 1.5), color, to_cp437('*'));
+                }
+            }
+        }
+    }
+}
+
+/// Draws a single glyph on the fancy console at the given position
+fn draw_glyph(ctx: &mut BTerm, position: PointF, color: RGBA, glyph: FontCharType) {
+    ctx.set_fancy(
+        position,
+        1,
+        Radians(0.0),
+        (1.0, 1.0).into(),
+        color,
+        RGBA::from_f32(0.0, 0.0, 0.0, 0.0),
+        glyph,
+    );
+}
 
 #[derive(Clone)]
 struct AnimatedWorld {
     world: World,
     unit_locations: HashMap<UnitId, Coord>,
     visible_tiles: HashSet<Coord>,
+
+    /// The effects of the events that lead to this world
+    effects: Vec<Effect>,
 }
 
 impl From<World> for AnimatedWorld {
@@ -31,35 +179,260 @@ impl From<World> for AnimatedWorld {
             world,
             unit_locations,
             visible_tiles,
+            effects: Vec::new(),
         }
     }
 }
 
 struct ApplicationState {
     world_receiver: async_watch::Receiver<World>,
+    info_receiver: async_watch::Receiver<BattleInfo>,
     last_world: AnimatedWorld,
     world: AnimatedWorld,
     animation_time: f32,
+    player_names: Vec<String>,
+    control: UnboundedSender<BattleControl>,
+    paused: bool,
+    tick_duration: Duration,
+    show_distance: bool,
+    show_trails: bool,
+    trails: HashMap<UnitId, VecDeque<Coord>>,
+    follow: Option<PlayerId>,
+    camera: PointF,
+
+    /// The most recent worlds received from the battle, oldest first
+    history: VecDeque<World>,
+
+    /// The turn of the world from `history` that is shown instead of the latest world, if any
+    rewind: Option<usize>,
 }
 
 impl ApplicationState {
     fn do_world_turn(&mut self) {
         let world = self.world_receiver.borrow();
+        let latest_turn = self.history.back().map(|world| world.turn);
 
-        if world.turn != self.world.world.turn {
-            self.animation_time = 0.0;
+        if latest_turn != Some(world.turn) {
+            // A new battle was started
+            if latest_turn.map_or(false, |turn| world.turn < turn) {
+                self.trails.clear();
+                self.history.clear();
+                self.rewind = None;
+            }
+
+            self.history.push_back(world.clone());
+            if self.history.len() > HISTORY_LENGTH {
+                self.history.pop_front();
+            }
+
+            for unit in world.units.iter() {
+                let trail = self.trails.entry(unit.id).or_default();
+                trail.push_back(unit.location);
+                if trail.len() > TRAIL_LENGTH + 1 {
+                    trail.pop_front();
+                }
+            }
 
+            // Keep showing the world that is being inspected
+            if self.rewind.is_some() {
+                return;
+            }
+
+            self.animation_time = 0.0;
             std::mem::swap(&mut self.world, &mut self.last_world);
             self.world = world.clone().into();
+
+            // The battle information is published before the world is sent
+            let info = self.info_receiver.borrow();
+            if info.turn == world.turn {
+                self.world.effects = Effect::from_events(&info.events, &world);
+            }
+        }
+    }
+
+    /// Shows the world of the given turn from the history instead of the latest world, or the
+    /// latest world again if `turn` is `None` or lies beyond the history. The battle is paused
+    /// while rewound.
+    fn rewind_to(&mut self, turn: Option<usize>, messages: &mut Vec<BattleControl>) {
+        let latest = match self.history.back() {
+            Some(world) => world.turn,
+            None => return,
+        };
+        let oldest = self.history.front().map_or(latest, |world| world.turn);
+        let turn = turn
+            .map(|turn| turn.max(oldest))
+            .filter(|&turn| turn < latest);
+        if turn == self.rewind {
+            return;
+        }
+        if turn.is_some() && !self.paused {
+            self.paused = true;
+            messages.push(BattleControl::Pause);
+        }
+        self.rewind = turn;
+
+        let world = match self
+            .history
+            .iter()
+            .find(|world| world.turn == turn.unwrap_or(latest))
+        {
+            Some(world) => world.clone(),
+            None => return,
+        };
+        std::mem::swap(&mut self.world, &mut self.last_world);
+        self.world = world.into();
+        self.animation_time = 0.0;
+    }
+
+    /// Returns the interpolated position of a unit on the map
+    fn unit_position(&self, unit: &Unit) -> PointF {
+        let current_position = PointF::new(unit.location.x as f32, unit.location.y as f32);
+        match self.last_world.unit_locations.get(&unit.id) {
+            Some(previous_location) => {
+                let previous_position =
+                    PointF::new(previous_location.x as f32, previous_location.y as f32);
+                previous_position + (current_position - previous_position) * self.animation_time
+            }
+            None => current_position,
+        }
+    }
+
+    /// Moves the camera towards the center of the units of the followed player, or the center of
+    /// the map if no player is followed.
+    fn update_camera(&mut self, frame_time_ms: f32) {
+        let map = &self.world.world.map;
+        let map_center = PointF::new(map.width as f32 / 2.0, map.height as f32 / 2.0);
+        let target = match self.follow {
+            Some(player) => {
+                let positions = self
+                    .world
+                    .world
+                    .units
+                    .iter()
+                    .filter(|unit| unit.player == player)
+                    .map(|unit| self.unit_position(unit))
+                    .collect::<Vec<_>>();
+                if positions.is_empty() {
+                    map_center
+                } else {
+                    let sum = positions
+                        .iter()
+                        .fold(PointF::new(0.0, 0.0), |sum, position| sum + *position);
+                    sum / positions.len() as f32
+                }
+            }
+            None => map_center,
+        };
+
+        let t = (CAMERA_SPEED * frame_time_ms / 1000.0).min(1.0);
+        self.camera = self.camera + (target - self.camera) * t;
+    }
+
+    /// Returns the map tile that is drawn in the top left corner of the viewport
+    fn camera_offset(&self) -> Point {
+        let map = &self.world.world.map;
+        Point::new(
+            (self.camera.x - map.width as f32 / 2.0).round() as i32,
+            (self.camera.y - map.height as f32 / 2.0).round() as i32,
+        )
+    }
+
+    /// Draws the trails of previous positions behind all units, older positions fade out
+    fn draw_trails(&self, ctx: &mut BTerm) {
+        let offset = self.camera_offset();
+        for unit in self.world.world.units.iter() {
+            let trail = match self.trails.get(&unit.id) {
+                Some(trail) => trail,
+                None => continue,
+            };
+
+            // The last position in the trail is the current position of the unit
+            for (age, location) in trail.iter().rev().skip(1).enumerate() {
+                let screen = Point::new(location.x, location.y) - offset;
+                if !in_viewport(screen, &self.world.world.map) {
+                    continue;
+                }
+                let mut color: RGBA = player_color(unit.player).into();
+                color.a = 0.6 * (1.0 - age as f32 / TRAIL_LENGTH as f32);
+                draw_glyph(
+                    ctx,
+                    PointF::new(screen.x as f32, screen.y as f32 + 1.0),
+                    color,
+                    to_cp437('∙'),
+                );
+            }
+        }
+    }
+
+    /// Handles the keys that control the progress of the battle and the overlays
+    fn handle_input(&mut self, ctx: &BTerm) {
+        let mut messages = Vec::new();
+        match ctx.key {
+            Some(VirtualKeyCode::Space) => {
+                self.rewind_to(None, &mut messages);
+                self.paused = !self.paused;
+                messages.push(if self.paused {
+                    BattleControl::Pause
+                } else {
+                    BattleControl::Resume
+                });
+            }
+            Some(VirtualKeyCode::Period) => {
+                self.rewind_to(None, &mut messages);
+                if !self.paused {
+                    self.paused = true;
+                    messages.push(BattleControl::Pause);
+                }
+                messages.push(BattleControl::Step);
+            }
+            Some(VirtualKeyCode::Equals) => {
+                self.tick_duration = (self.tick_duration / 2).max(MIN_TICK_DURATION);
+                messages.push(BattleControl::SetTickDuration(Some(self.tick_duration)));
+            }
+            Some(VirtualKeyCode::Left) => {
+                let turn = self.rewind.unwrap_or(self.world.world.turn);
+                self.rewind_to(Some(turn.saturating_sub(1)), &mut messages);
+            }
+            Some(VirtualKeyCode::Right) => {
+                if let Some(turn) = self.rewind {
+                    self.rewind_to(Some(turn + 1), &mut messages);
+                }
+            }
+            Some(VirtualKeyCode::H) => self.show_distance = !self.show_distance,
+            Some(VirtualKeyCode::T) => self.show_trails = !self.show_trails,
+            Some(VirtualKeyCode::F) => {
+                // Cycle through all players and back to the whole map
+                self.follow = match self.follow {
+                    None if !self.player_names.is_empty() => Some(PlayerId(0)),
+                    Some(player) if player.0 + 1 < self.player_names.len() => {
+                        Some(PlayerId(player.0 + 1))
+                    }
+                    _ => None,
+                };
+            }
+            Some(VirtualKeyCode::Minus) => {
+                self.tick_duration = (self.tick_duration * 2).min(MAX_TICK_DURATION);
+                messages.push(BattleControl::SetTickDuration(Some(self.tick_duration)));
+            }
+            _ => {}
+        }
+
+        for message in messages {
+            // The battle might have ended, in which case there is nothing left to control
+            let _ = self.control.unbounded_send(message);
         }
     }
 }
 
 impl GameState for ApplicationState {
     fn tick(&mut self, ctx: &mut BTerm) {
+        self.handle_input(ctx);
+
         // Try to receive a new world
         self.do_world_turn();
 
+        self.update_camera(ctx.frame_time_ms);
+
         // Clear the screen
         ctx.cls();
 
@@ -79,25 +452,33 @@ impl GameState for ApplicationState {
         };
 
         // Draw map
+        let offset = self.camera_offset();
         ctx.set_active_console(0);
-        draw_map(&self.world.world.map, is_visible, ctx);
+        draw_map(&self.world.world.map, is_visible, offset, ctx);
+        if self.show_distance {
+            draw_distance_overlay(&self.world.world.map, offset, ctx);
+        }
+        draw_items(&self.world.world.items, &self.world.world.map, offset, ctx);
+        draw_obstacles(
+            &self.world.world.obstacles,
+            &self.world.world.map,
+            offset,
+            ctx,
+        );
 
         // Draw units
         ctx.set_active_console(1);
+        // The trails follow the latest world
+        if self.show_trails && self.rewind.is_none() {
+            self.draw_trails(ctx);
+        }
+        let screen_offset = PointF::new(offset.x as f32, offset.y as f32 - 1.0);
         for unit in self.world.world.units.iter() {
-            let current_position =
-                PointF::new(unit.location.x as f32 - 0.0, unit.location.y as f32 + 1.0);
-            let position =
-                if let Some(previous_location) = self.last_world.unit_locations.get(&unit.id) {
-                    let previous_position = PointF::new(
-                        previous_location.x as f32 - 0.0,
-                        previous_location.y as f32 + 1.0,
-                    );
-                    previous_position + (current_position - previous_position) * self.animation_time
-                } else {
-                    current_position
-                };
-
+            let position = self.unit_position(unit) - screen_offset;
+            let tile = Point::new(position.x.round() as i32, position.y.round() as i32 - 1);
+            if !in_viewport(tile, &self.world.world.map) {
+                continue;
+            }
             ctx.set_fancy(
                 position,
                 1,
@@ -108,28 +489,149 @@ impl GameState for ApplicationState {
                 unit_glyph(unit),
             )
         }
+        for effect in self.world.effects.iter() {
+            effect.draw(self.animation_time, offset, &self.world.world.map, ctx);
+        }
 
-        draw_ui(&self.world.world, &self.world.unit_locations, ctx);
+        draw_ui(
+            &self.world.world,
+            &self.world.unit_locations,
+            &self.player_names,
+            ctx,
+        );
 
-        let frame_animation_time = 100.0;
+        let status = if let Some(turn) = self.rewind {
+            let latest = self.history.back().map_or(turn, |world| world.turn);
+            format!("REWOUND {}/{}", turn, latest)
+        } else if self.paused {
+            "PAUSED".to_string()
+        } else {
+            format!("{}ms/turn", self.tick_duration.as_millis())
+        };
+        ctx.print(1, self.world.world.map.height - 1, status);
+
+        {
+            let info = self.info_receiver.borrow();
+            draw_sidebar(&info.scoreboard, self.world.world.map.width, ctx);
+            draw_ticker(&info.ticker, self.world.world.map.height - 2, ctx);
+        }
+
+        let frame_animation_time = self.tick_duration.as_millis() as f32;
         self.animation_time =
             (self.animation_time + ctx.frame_time_ms / frame_animation_time).min(1.0);
     }
 }
 
-pub fn run(world_receiver: async_watch::Receiver<World>) -> BError {
-    let context = BTermBuilder::simple80x50()
-        .with_fancy_console(80, 50, "terminal8x8.png".to_string())
+pub fn run(
+    world_receiver: async_watch::Receiver<World>,
+    info_receiver: async_watch::Receiver<BattleInfo>,
+    player_names: Vec<String>,
+    control: UnboundedSender<BattleControl>,
+    tick_duration: Duration,
+) -> BError {
+    let world: AnimatedWorld = world_receiver.borrow().deref().clone().into();
+
+    // Size the console to fit the map with the sidebar next to it
+    let width = world.world.map.width + SIDEBAR_WIDTH;
+    let height = world.world.map.height;
+    let context = BTermBuilder::simple(width, height)?
+        .with_fancy_console(width, height, "terminal8x8.png".to_string())
         .with_title("My Little Robots")
+        .with_automatic_console_resize(true)
         .build()?;
-    let world: AnimatedWorld = world_receiver.borrow().deref().clone().into();
+    let camera = PointF::new(
+        world.world.map.width as f32 / 2.0,
+        world.world.map.height as f32 / 2.0,
+    );
     let application_state = ApplicationState {
         world_receiver,
+        info_receiver,
         last_world: world.clone(),
         world,
         animation_time: 1.0,
+        player_names,
+        control,
+        paused: false,
+        tick_duration,
+        show_distance: false,
+        show_trails: true,
+        trails: HashMap::new(),
+        follow: None,
+        camera,
+        history: VecDeque::new(),
+        rewind: None,
     };
 
     // Run the main loop
     main_loop(context, application_state)
 }
+
+/// The number of events shown in the ticker
+const TICKER_LENGTH: usize = 6;
+
+/// Information about a battle that is shown next to the world
+#[derive(Clone, Default)]
+pub struct BattleInfo {
+    pub scoreboard: Scoreboard,
+
+    /// Descriptions of the most recent noteworthy events, oldest first
+    pub ticker: VecDeque<String>,
+
+    /// The turn that was last played and the events that happened during it
+    pub turn: usize,
+    pub events: Vec<GameEvent>,
+}
+
+/// A `BattleObserver` that keeps the battle information shown in the viewer up to date
+pub struct BattleInfoPublisher {
+    info: BattleInfo,
+    players: Vec<String>,
+    sender: Arc<async_watch::Sender<BattleInfo>>,
+}
+
+impl BattleInfoPublisher {
+    pub fn new(sender: Arc<async_watch::Sender<BattleInfo>>) -> Self {
+        BattleInfoPublisher {
+            info: BattleInfo::default(),
+            players: Vec::new(),
+            sender,
+        }
+    }
+
+    fn publish(&self) {
+        // The viewer might have been closed
+        let _ = self.sender.send(self.info.clone());
+    }
+}
+
+impl BattleObserver for BattleInfoPublisher {
+    fn on_start(&mut self, world: &World, players: &[String], rules: &GameRules) {
+        self.players = players.to_vec();
+        self.info.ticker.clear();
+        self.info.turn = world.turn;
+        self.info.events.clear();
+        self.info.scoreboard.on_start(world, players, rules);
+        self.publish();
+    }
+
+    fn on_turn(&mut self, world: &World, events: &[GameEvent]) {
+        self.info.scoreboard.on_turn(world, events);
+        self.info.turn = world.turn;
+        self.info.events = events.to_vec();
+        let players = &self.players;
+        for description in events.iter().filter_map(|e| e.describe(players)) {
+            self.info
+                .ticker
+                .push_back(format!("{:>4} {}", world.turn, description));
+        }
+        while self.info.ticker.len() > TICKER_LENGTH {
+            self.info.ticker.pop_front();
+        }
+        self.publish();
+    }
+
+    fn on_end(&mut self, world: &World, result: &MatchResult) {
+        self.info.scoreboard.on_end(world, result);
+        self.publish();
+    }
+}
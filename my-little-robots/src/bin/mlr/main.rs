@@ -1,12 +1,29 @@
 mod application;
+mod watch;
 
 use anyhow::Context;
 use anyhow::{anyhow, bail};
+use application::{BattleInfo, BattleInfoPublisher};
+use futures::channel::mpsc::UnboundedSender;
+use futures::StreamExt;
 use itertools::Itertools;
-use mlr::Battle;
+use mlr::map_builder::{BuilderKind, LibraryMap, LIBRARY};
+use mlr::BuiltinRunner;
+use mlr::CommandOptions;
 use mlr::Runner;
+use mlr::RunnerScheduler;
+use mlr::{Battle, BattleControl, Campaign, PlayerRunner};
+use mlr::{
+    GameLog, GameRules, GifRecorder, Map, MapAnalysis, MatchResult, PenaltyRules, PngRenderer,
+    Replay, ReplayRecorder, Scenario, Verification, WorldUpdate,
+};
+use mlr_api::Coord;
+use serde_json::json;
 use std::ffi::{OsStr, OsString};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 use std::time::Duration;
 use structopt::StructOpt;
 
@@ -15,6 +32,111 @@ use structopt::StructOpt;
 enum MyLittleRobots {
     /// Command for running a local match
     Run(Run),
+
+    /// Lists the available map builders, built-in bots and runner types
+    List,
+
+    /// Commands for working with maps
+    Map(MapCommand),
+
+    /// Re-simulates a recorded replay and checks that every turn results in the recorded world
+    Verify(Verify),
+
+    /// Plays the levels of a campaign one after the other with a single player
+    Campaign(CampaignOptions),
+}
+
+#[derive(StructOpt)]
+struct CampaignOptions {
+    /// The TOML file that describes the campaign
+    #[structopt(parse(from_os_str))]
+    campaign: PathBuf,
+
+    /// The player that plays the campaign, see `mlr run --help` for the accepted formats
+    #[structopt(parse(from_os_str))]
+    player: OsString,
+}
+
+#[derive(StructOpt)]
+struct Verify {
+    /// The replay to verify
+    #[structopt(parse(from_os_str))]
+    replay: PathBuf,
+}
+
+#[derive(StructOpt)]
+enum MapCommand {
+    /// Generates a map and exports it as an image
+    Export(ExportMap),
+
+    /// Generates a map and prints it as text, in the format used by scenarios
+    Generate(GenerateMap),
+}
+
+#[derive(StructOpt)]
+#[structopt(setting = clap::AppSettings::DeriveDisplayOrder)]
+struct GenerateMap {
+    /// The map builder used to generate the map, run `mlr list` to see all map builders
+    #[structopt(long, default_value = "prim")]
+    builder: BuilderKind,
+
+    /// The width of the map in tiles
+    #[structopt(long, default_value = "80")]
+    width: usize,
+
+    /// The height of the map in tiles
+    #[structopt(long, default_value = "50")]
+    height: usize,
+
+    /// Mirrors the map so that it looks the same from every corner, see `mlr run --symmetric`
+    #[structopt(long)]
+    symmetric: bool,
+
+    /// Prints measures of the quality of the map below it, like the number of dead ends and
+    /// choke points
+    #[structopt(long)]
+    analyze: bool,
+}
+
+#[derive(StructOpt)]
+#[structopt(setting = clap::AppSettings::DeriveDisplayOrder)]
+struct ExportMap {
+    /// The map builder used to generate the map, run `mlr list` to see all map builders
+    #[structopt(long, default_value = "prim")]
+    builder: BuilderKind,
+
+    /// The width of the map in tiles
+    #[structopt(long, default_value = "80")]
+    width: usize,
+
+    /// The height of the map in tiles
+    #[structopt(long, default_value = "50")]
+    height: usize,
+
+    /// The format of the exported image, only `svg` is currently supported
+    #[structopt(long, default_value = "svg")]
+    format: MapFormat,
+
+    /// The file to write the image to, if omitted the image is written to stdout
+    #[structopt(long, parse(from_os_str))]
+    out: Option<PathBuf>,
+}
+
+/// The image formats a map can be exported to
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum MapFormat {
+    Svg,
+}
+
+impl FromStr for MapFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "svg" => Ok(MapFormat::Svg),
+            _ => bail!("unknown map format '{}', expected 'svg'", s),
+        }
+    }
 }
 
 #[derive(StructOpt)]
@@ -23,16 +145,190 @@ struct Run {
     /// The runners that should be placed in the match.
     ///
     /// A runner is specified in one of the following ways:
-    /// 1. `command:$PATH` or `localrunner:$PATH`. The path to a binary file.
+    /// 1. `command:$COMMAND`. A command that is executed every turn.
+    /// 2. `file:$PATH`, `local:$PATH` or `$PATH`. The path to a WASI module.
+    /// 3. `builtin:$NAME`. One of the bots built into the engine.
+    ///
+    /// Run `mlr list` to see all runner types and built-in bots.
+    ///
+    /// A runner can be prefixed with `$NAME=` to give the player a display name, e.g.
+    /// `Alice=command:./alice`.
     #[structopt(
         parse(from_os_str),
-        required = true,
+        required_unless = "mirror",
         min_values = 2,
         verbatim_doc_comment
     )]
     runners: Vec<OsString>,
+
+    /// Plays a mirror match between copies of the specified runner on a symmetric map, to measure
+    /// whether a change to a bot is an improvement. Every copy runs in its own instance with its
+    /// own memory.
+    #[structopt(long, parse(from_os_str), conflicts_with = "runners")]
+    mirror: Option<OsString>,
+
+    /// The number of copies that play a mirror match, 2 by default and at most 4
+    #[structopt(long, requires = "mirror")]
+    count: Option<usize>,
+
+    /// Watch the files of the runners and restart the match when they change. Runners that were
+    /// built by cargo are rebuilt before the match is restarted.
+    #[structopt(long)]
+    watch: bool,
+
+    /// Run the match without a viewer.
+    ///
+    /// When the match ends a single line of JSON describing the result is printed. The exit code
+    /// of the process reflects the outcome of the match:
+    ///   1       an error occurred
+    ///   2       the match ended in a draw
+    ///   3       a player after the first 110 players won the match, see the JSON for which one
+    ///   10 + N  player N won the match, for N below 110
+    ///   130     the match was aborted because the process was interrupted
+    #[structopt(long, conflicts_with = "watch", verbatim_doc_comment)]
+    headless: bool,
+
+    /// How the match is rendered: `window`, the default, opens a viewer, `png` writes an image of
+    /// every turn to the directory specified with `--out` without opening a window.
+    #[structopt(long, conflicts_with = "watch")]
+    render: Option<RenderMode>,
+
+    /// The directory the frames are written to when rendering to PNG
+    #[structopt(long, parse(from_os_str), required_if("render", "png"))]
+    out: Option<PathBuf>,
+
+    #[structopt(flatten)]
+    battle: BattleOptions,
+}
+
+/// Options that configure how a battle is played
+#[derive(StructOpt, Clone)]
+struct BattleOptions {
+    /// The maximum number of turns after which the match ends, the player that made the most
+    /// progress towards the objective then wins
+    #[structopt(long)]
+    max_turns: Option<usize>,
+
+    /// The number of invalid actions, failed runs and timeouts after which a player has to sit out
+    /// a turn
+    #[structopt(long)]
+    skip_after: Option<usize>,
+
+    /// The number of invalid actions, failed runs and timeouts after which a player is eliminated
+    #[structopt(long)]
+    eliminate_after: Option<usize>,
+
+    /// Moves the exit to a random reachable tile every this many turns
+    #[structopt(long)]
+    moving_exit: Option<usize>,
+
+    /// Sends every player a description of the match before the first turn and gives it this many
+    /// milliseconds to prepare, like precomputing paths
+    #[structopt(long)]
+    warm_up: Option<u64>,
+
+    /// The maximum size in bytes of the memory a player can keep between turns, measured as the
+    /// JSON that is sent to the player. Players that return more memory fail their turn. Defaults
+    /// to 65536, raise it for bots that keep more memory.
+    #[structopt(long)]
+    max_memory_size: Option<usize>,
+
+    /// The number of seconds after which the match is cut short. The player that made the most
+    /// progress towards the objective, like the player closest to an exit, then wins.
+    #[structopt(long)]
+    deadline: Option<u64>,
+
+    /// The seed of the random choices the engine makes during the match
+    #[structopt(long)]
+    seed: Option<u64>,
+
+    /// The map builder used to generate the map, run `mlr list` to see all map builders
+    #[structopt(long, default_value = "prim")]
+    builder: BuilderKind,
+
+    /// Plays on a map from the library of curated maps instead of a generated map, specified as
+    /// `library:$NAME`. Run `mlr list` to see all maps in the library.
+    #[structopt(long, parse(try_from_str = parse_library_map))]
+    map: Option<&'static LibraryMap>,
+
+    /// The largest difference in walking distance to the closest exit between the spawn points of
+    /// the players. Maps are generated again until they have such spawn points.
+    #[structopt(long)]
+    spawn_tolerance: Option<usize>,
+
+    /// Mirrors the generated map so that every player starts in the same situation, in one of the
+    /// corners of the map. Supports at most 4 players. Maps from the library cannot be mirrored,
+    /// only the symmetric ones are accepted.
+    #[structopt(long)]
+    symmetric: bool,
+
+    /// Plays the scenario described by the specified TOML file. The scenario determines the map,
+    /// the spawn points and the rules, options given on the command line take precedence.
+    #[structopt(long, parse(from_os_str))]
+    scenario: Option<PathBuf>,
+
+    /// Records a replay of the match to the specified file. Replays are stored as JSON unless the
+    /// file has a `.msgpack` extension and mlr was built with the `msgpack` feature.
+    #[structopt(long, parse(from_os_str))]
+    record: Option<PathBuf>,
+
+    /// Records an animated GIF of the match to the specified file
+    #[structopt(long, parse(from_os_str))]
+    record_gif: Option<PathBuf>,
+
+    /// Writes every event of the match as a JSON object per line to the specified file
+    #[structopt(long, parse(from_os_str))]
+    game_log: Option<PathBuf>,
+
+    /// Writes the exact bytes exchanged with every player each turn to a file per player in the
+    /// specified directory, to find out why the output of a player is not understood
+    #[structopt(long, parse(from_os_str))]
+    debug_io: Option<PathBuf>,
+
+    /// The maximum number of players whose programs run at the same time, the others wait for
+    /// their turn. The time spent waiting does not count towards timeouts. Use `cpus` to allow as
+    /// many programs as there are CPUs. Unlimited if not specified.
+    #[structopt(long, parse(try_from_str = parse_concurrency_limit))]
+    max_concurrent_runners: Option<RunnerScheduler>,
+}
+
+/// Describes how a match is rendered
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum RenderMode {
+    Window,
+    Png,
+}
+
+impl FromStr for RenderMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "window" => Ok(RenderMode::Window),
+            "png" => Ok(RenderMode::Png),
+            _ => bail!("unknown render mode '{}', expected 'window' or 'png'", s),
+        }
+    }
 }
 
+/// The exit code used when a headless match ends in a draw
+const EXIT_CODE_DRAW: i32 = 2;
+
+/// The exit code used when a player wins a headless match whose index is too large to be offset
+/// from `EXIT_CODE_WINNER_BASE`
+const EXIT_CODE_WINNER_OTHER: i32 = 3;
+
+/// The exit code used when the first player wins a headless match. Other players are offset from
+/// this code by their index, up to `EXIT_CODE_WINNER_MAX`.
+const EXIT_CODE_WINNER_BASE: i32 = 10;
+
+/// The largest exit code used for a winner, this keeps the codes clear of the codes shells use for
+/// processes killed by a signal (128 and up)
+const EXIT_CODE_WINNER_MAX: i32 = 119;
+
+/// The exit code used when a match was aborted because the process was interrupted
+const EXIT_CODE_ABORTED: i32 = 130;
+
 fn main() {
     if let Err(err) = try_main() {
         eprintln!("ERROR: {}", err);
@@ -49,59 +345,668 @@ fn try_main() -> anyhow::Result<()> {
     let opt: MyLittleRobots = MyLittleRobots::from_args();
 
     match opt {
-        MyLittleRobots::Run(run_opt) => {
-            let mut battle = Battle::default();
-
-            // Parse all runner descriptions into actual runners
-            let runners = run_opt
-                .runners
-                .iter()
-                .map(|runner_desc| -> anyhow::Result<_> {
-                    let runner = RunnerDesc::parse(runner_desc)?;
-                    Ok(Box::new(runner.into_runner()?))
-                })
-                .collect::<Result<Vec<_>, _>>()?;
+        MyLittleRobots::Run(mut run_opt) => {
+            // Parse all player descriptions
+            let players = match &run_opt.mirror {
+                Some(mirror) => {
+                    run_opt.battle.symmetric = true;
+                    mirror_players(mirror, run_opt.count.unwrap_or(2))?
+                }
+                None => run_opt
+                    .runners
+                    .iter()
+                    .enumerate()
+                    .map(|(i, player_desc)| PlayerDesc::parse(player_desc, i))
+                    .collect::<Result<Vec<_>, _>>()?,
+            };
+            let player_names = players.iter().map(|p| p.name.clone()).collect_vec();
+
+            if run_opt.headless || run_opt.render == Some(RenderMode::Png) {
+                let mut battle = create_battle(players.iter().cloned(), &run_opt.battle)?;
+                if let (Some(RenderMode::Png), Some(out)) = (run_opt.render, &run_opt.out) {
+                    battle.add_observer(Box::new(PngRenderer::new(out)));
+                }
+                let (control_sender, control_receiver) = futures::channel::mpsc::unbounded();
+                battle.set_control(control_receiver);
+                abort_on_interrupt(control_sender)?;
+                let result = async_std::task::block_on(battle.run(None, None));
+                if !run_opt.headless {
+                    print_result(&result, &players, run_opt.battle.record.as_deref());
+                    return Ok(());
+                }
 
-            // Add all runners as players to the battle
-            for runner in runners {
-                battle.add_player(runner);
+                let winner_name = result.winner.map(|winner| &player_names[winner.0]);
+                println!(
+                    "{}",
+                    json!({
+                        "winner": result.winner,
+                        "winner_name": winner_name,
+                        "turns": result.turns,
+                        "penalties": result.penalties,
+                        "aborted": result.aborted,
+                        "tied": result.tied,
+                        "tie_break": result.tie_break,
+                        "adjudicated": result.adjudicated,
+                        "ranking": result.ranking,
+                        "replay": run_opt.battle.record,
+                    })
+                );
+                std::process::exit(exit_code(&result));
             }
 
             // Construct the future for the battle
-            let (sender, receiver) = async_std::sync::channel(1);
-            std::thread::spawn(|| {
-                async_std::task::block_on(
-                    battle.run(Some(Duration::from_millis(100)), Some(sender)),
-                )
-            });
+            let tick_duration = Duration::from_millis(100);
+            let (sender, mut receiver) = futures::channel::mpsc::channel(1);
+            let (control_sender, control_receiver) = futures::channel::mpsc::unbounded();
+            let (info_sender, info_receiver) = async_watch::channel(BattleInfo::default());
+            let info_sender = Arc::new(info_sender);
+            abort_on_interrupt(control_sender.clone())?;
+            if run_opt.watch {
+                let options = run_opt.battle.clone();
+                let viewer = watch::ViewerLink {
+                    worlds: sender,
+                    control: control_receiver,
+                    info: info_sender,
+                };
+                std::thread::spawn(move || {
+                    let battles = watch::run(players, options, tick_duration, viewer);
+                    async_std::task::block_on(battles);
+                    std::process::exit(EXIT_CODE_ABORTED);
+                });
+            } else {
+                let mut battle = create_battle(players.iter().cloned(), &run_opt.battle)?;
+                battle.set_control(control_receiver);
+                battle.add_observer(Box::new(BattleInfoPublisher::new(info_sender)));
+                let record = run_opt.battle.record.clone();
+                std::thread::spawn(move || {
+                    let result =
+                        async_std::task::block_on(battle.run(Some(tick_duration), Some(sender)));
+                    print_result(&result, &players, record.as_deref());
+                    if result.aborted {
+                        std::process::exit(EXIT_CODE_ABORTED);
+                    }
+                });
+            }
 
-            // Await the first world send by the battle
-            let world = async_std::task::block_on(receiver.recv())?;
+            // Await the snapshot of the initial world send by the battle
+            let mut world = match async_std::task::block_on(receiver.next()) {
+                Some(WorldUpdate::Snapshot(world)) => world,
+                Some(WorldUpdate::Diff(_)) => bail!("the battle did not start with a snapshot"),
+                None => bail!("the battle ended before it started"),
+            };
 
-            // Spawn a task that continuously updates the latest world received by the battle.
-            let (world_sender, world_receiver) = async_watch::channel(world);
+            // Spawn a task that applies the changes send by the battle to keep track of the latest
+            // world.
+            let (world_sender, world_receiver) = async_watch::channel(world.clone());
             async_std::task::spawn(async move {
-                while let Ok(world) = receiver.recv().await {
-                    if world_sender.send(world).is_err() {
+                while let Some(update) = receiver.next().await {
+                    world.apply_update(update);
+                    if world_sender.send(world.clone()).is_err() {
                         break;
                     }
                 }
             });
 
             // Render our world
-            application::run(world_receiver).expect("failed to render");
+            application::run(
+                world_receiver,
+                info_receiver,
+                player_names,
+                control_sender,
+                tick_duration,
+            )
+            .expect("failed to render");
+        }
+        MyLittleRobots::List => {
+            println!("Map builders:");
+            for builder in BuilderKind::all() {
+                println!("  {:<20} {}", builder.name(), builder.description());
+            }
+            println!();
+            println!("Built-in bots:");
+            for builtin in BuiltinRunner::all() {
+                println!(
+                    "  {:<20} {}",
+                    format!("builtin:{}", builtin.name()),
+                    builtin.description()
+                );
+            }
+            println!();
+            println!("Library maps:");
+            for map in LIBRARY {
+                println!(
+                    "  {:<20} {}",
+                    format!("library:{}", map.name),
+                    map.description
+                );
+            }
+            println!();
+            println!("Runner types:");
+            for (scheme, description) in RunnerDesc::SCHEMES {
+                println!("  {:<20} {}", scheme, description);
+            }
+        }
+        MyLittleRobots::Map(MapCommand::Export(export)) => {
+            let map = export.builder.new_map(export.width, export.height);
+            let image = match export.format {
+                MapFormat::Svg => map.to_svg(),
+            };
+            match &export.out {
+                Some(path) => std::fs::write(path, image)
+                    .with_context(|| format!("could not write map to {}", path.display()))?,
+                None => print!("{}", image),
+            }
+        }
+        MyLittleRobots::Map(MapCommand::Generate(generate)) => {
+            let mut map = generate.builder.new_map(generate.width, generate.height);
+            if generate.symmetric {
+                map = map.symmetric();
+            }
+            print!("{}", map.to_text());
+            if generate.analyze {
+                print_analysis(&MapAnalysis::new(&map));
+            }
+        }
+        MyLittleRobots::Verify(verify) => {
+            let replay = Replay::load(&verify.replay)
+                .with_context(|| format!("could not read replay {}", verify.replay.display()))?;
+            match async_std::task::block_on(replay.verify())? {
+                Verification::Identical { turns } => {
+                    println!("All {} turns match the replay", turns)
+                }
+                Verification::Diverged {
+                    turn,
+                    expected,
+                    actual,
+                } => bail!(
+                    "turn {} diverged from the replay: expected world hash {:016x}, got {:016x}",
+                    turn,
+                    expected,
+                    actual
+                ),
+            }
+        }
+        MyLittleRobots::Campaign(options) => {
+            let campaign = Campaign::load(&options.campaign).with_context(|| {
+                format!("could not read campaign {}", options.campaign.display())
+            })?;
+            let player = PlayerDesc::parse(&options.player, 0)?;
+            let result = async_std::task::block_on(campaign.run(
+                || {
+                    let runner: Box<dyn PlayerRunner> =
+                        Box::new(player.runner.clone().into_runner()?);
+                    Ok(runner)
+                },
+                |_| {},
+            ))?;
+            for (index, level) in result.levels.iter().enumerate() {
+                println!(
+                    "Level {}: {} - {} in {} turns",
+                    index + 1,
+                    level.name,
+                    if level.completed {
+                        "completed"
+                    } else {
+                        "failed"
+                    },
+                    level.turns
+                );
+            }
+            println!(
+                "{}: {} of {} levels completed in {} turns",
+                player.name,
+                result.completed_levels(),
+                campaign.levels.len(),
+                result.total_turns()
+            );
         }
     }
 
     Ok(())
 }
 
+/// Returns the exit code of a headless match that ended with `result`
+fn exit_code(result: &MatchResult) -> i32 {
+    match result.winner {
+        _ if result.aborted => EXIT_CODE_ABORTED,
+        Some(winner) => {
+            let code = EXIT_CODE_WINNER_BASE as usize + winner.0;
+            if code <= EXIT_CODE_WINNER_MAX as usize {
+                code as i32
+            } else {
+                EXIT_CODE_WINNER_OTHER
+            }
+        }
+        None => EXIT_CODE_DRAW,
+    }
+}
+
+/// Aborts the battle that listens to `control` when the process is interrupted (Ctrl-C) or
+/// terminated. The battle finishes its current turn and writes its recordings before it returns.
+/// Interrupting the process a second time exits immediately.
+fn abort_on_interrupt(control: UnboundedSender<BattleControl>) -> anyhow::Result<()> {
+    let interrupted = AtomicBool::new(false);
+    ctrlc::set_handler(move || {
+        if interrupted.swap(true, Ordering::SeqCst) {
+            std::process::exit(EXIT_CODE_ABORTED);
+        }
+        log::info!("Interrupted, aborting the match");
+        let _ = control.unbounded_send(BattleControl::Abort);
+    })
+    .context("could not install the interrupt handler")
+}
+
+/// Constructs a battle configured with `options` with a player for each of the given descriptions
+fn create_battle(
+    players: impl IntoIterator<Item = PlayerDesc>,
+    options: &BattleOptions,
+) -> anyhow::Result<Battle> {
+    let players = players.into_iter().collect_vec();
+    let mut battle = Battle::default();
+    let mut rules = GameRules::default();
+    match &options.scenario {
+        Some(_) if options.symmetric => bail!("scenarios cannot be mirrored"),
+        Some(path) => {
+            let scenario = Scenario::load(path)
+                .with_context(|| format!("could not read scenario {}", path.display()))?;
+            scenario.apply(&mut battle)?;
+            rules = scenario.rules;
+        }
+        None if options.map.is_some() => {
+            let library = options.map.expect("checked that there is a map");
+            let map = library.build();
+            let spawns = map.symmetric_spawns();
+            if library.symmetric && players.len() <= spawns.len() {
+                battle.set_spawns(spawns);
+            } else if options.symmetric {
+                bail!(
+                    "map {} cannot be mirrored for {} players",
+                    library.name,
+                    players.len()
+                );
+            }
+            battle.set_map(map);
+        }
+        None if options.symmetric => {
+            let (map, spawns) = symmetric_map(options.builder, players.len())?;
+            battle.set_spawns(spawns);
+            battle.set_map(map);
+        }
+        None => battle.set_map(fair_map(
+            options.builder,
+            players.len(),
+            options.spawn_tolerance,
+        )?),
+    }
+    if options.max_turns.is_some() {
+        rules.max_turns = options.max_turns;
+    }
+    if let Some(size) = options.max_memory_size {
+        rules.max_memory_size = size;
+    }
+    if options.skip_after.is_some() {
+        rules.penalties = PenaltyRules {
+            skip_after: options.skip_after,
+            skip_turns: 1,
+            ..rules.penalties
+        };
+    }
+    if options.eliminate_after.is_some() {
+        rules.penalties.eliminate_after = options.eliminate_after;
+    }
+    if options.moving_exit.is_some() {
+        rules.moving_exit = options.moving_exit;
+    }
+    if options.warm_up.is_some() {
+        rules.warm_up = options.warm_up;
+    }
+    if let Some(seed) = options.seed {
+        rules.seed = seed;
+    }
+    if options.spawn_tolerance.is_some() {
+        rules.spawn_tolerance = options.spawn_tolerance;
+    }
+    battle.set_rules(rules);
+    if let Some(seconds) = options.deadline {
+        battle.set_deadline(Duration::from_secs(seconds));
+    }
+    if let Some(path) = &options.record {
+        battle.add_observer(Box::new(ReplayRecorder::new(path)));
+    }
+    if let Some(path) = &options.record_gif {
+        battle.add_observer(Box::new(GifRecorder::new(path)));
+    }
+    if let Some(path) = &options.game_log {
+        battle.add_observer(Box::new(GameLog::new(path)));
+    }
+    if let Some(dir) = &options.debug_io {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("could not create directory {}", dir.display()))?;
+    }
+    for (index, player) in players.into_iter().enumerate() {
+        let mut runner = player.runner.into_runner()?;
+        if let Some(scheduler) = &options.max_concurrent_runners {
+            runner = runner.with_scheduler(scheduler.clone());
+        }
+        if let Some(dir) = &options.debug_io {
+            let path = dir.join(transcript_file_name(index, &player.name));
+            runner = runner
+                .with_transcript(&path)
+                .with_context(|| format!("could not create transcript {}", path.display()))?;
+        }
+        battle.add_named_player(player.name, Box::new(runner));
+    }
+    battle.check()?;
+    Ok(battle)
+}
+
+/// Returns the name of the file to which the I/O of a player is written with `--debug-io`, made up
+/// of the index of the player and its name with everything but letters and digits replaced
+fn transcript_file_name(index: usize, name: &str) -> String {
+    let name: String = name
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '_' })
+        .collect();
+    format!("{}-{}.txt", index, name)
+}
+
+/// Parses the limit of `--max-concurrent-runners`, either a number or `cpus`
+fn parse_concurrency_limit(s: &str) -> anyhow::Result<RunnerScheduler> {
+    if s == "cpus" {
+        return Ok(RunnerScheduler::with_cpu_limit());
+    }
+    match s.parse::<usize>() {
+        Ok(limit) if limit > 0 => Ok(RunnerScheduler::new(limit)),
+        _ => bail!("expected a positive number or 'cpus', got '{}'", s),
+    }
+}
+
+/// Parses a map from the library specified as `library:$NAME`
+fn parse_library_map(s: &str) -> anyhow::Result<&'static LibraryMap> {
+    let name = match s.strip_prefix("library:") {
+        Some(name) => name,
+        None => bail!("expected a map of the form library:$NAME, got '{}'", s),
+    };
+    LibraryMap::find(name).ok_or_else(|| anyhow!("there is no map named '{}' in the library", name))
+}
+
+/// The number of times a symmetric map is generated before giving up on finding a playable one
+const SYMMETRIC_MAP_ATTEMPTS: usize = 10;
+
+/// Generates a symmetric map with the given builder that has room for the given number of players
+/// and returns it together with the spawn points of the players. Maps that are not playable
+/// according to their `MapAnalysis` are rejected.
+fn symmetric_map(builder: BuilderKind, players: usize) -> anyhow::Result<(Map, Vec<Coord>)> {
+    for _ in 0..SYMMETRIC_MAP_ATTEMPTS {
+        let map = builder.new_map(80, 50).symmetric();
+        let spawns = map.symmetric_spawns();
+        if players > spawns.len() {
+            bail!(
+                "a symmetric map has room for {} players, not {}",
+                spawns.len(),
+                players
+            );
+        }
+        if MapAnalysis::new(&map).is_playable() {
+            return Ok((map, spawns));
+        }
+    }
+    bail!(
+        "the {} builder did not generate a playable symmetric map in {} attempts",
+        builder.name(),
+        SYMMETRIC_MAP_ATTEMPTS
+    )
+}
+
+/// The number of times a map is generated before giving up on finding one with fair spawn points
+const FAIR_MAP_ATTEMPTS: usize = 10;
+
+/// Generates a map with the given builder. With a spawn tolerance maps are generated until one has
+/// spawn points for all players within the tolerance, see `Map::fair_spawn_points`.
+fn fair_map(
+    builder: BuilderKind,
+    players: usize,
+    spawn_tolerance: Option<usize>,
+) -> anyhow::Result<Map> {
+    let tolerance = match spawn_tolerance {
+        Some(tolerance) => tolerance,
+        None => return Ok(builder.new_map(80, 50)),
+    };
+    for _ in 0..FAIR_MAP_ATTEMPTS {
+        let map = builder.new_map(80, 50);
+        if map.fair_spawn_points(players, tolerance).is_some() {
+            return Ok(map);
+        }
+    }
+    bail!(
+        "the {} builder did not generate a map with spawn points within {} tiles of each other in {} attempts",
+        builder.name(),
+        tolerance,
+        FAIR_MAP_ATTEMPTS
+    )
+}
+
+/// Prints the measures of a map, one per line
+fn print_analysis(analysis: &MapAnalysis) {
+    let exit_distance = analysis
+        .average_exit_distance
+        .map(|distance| format!("{:.1}", distance))
+        .unwrap_or_else(|| "-".to_string());
+    println!();
+    println!("  {:<24} {}", "floor tiles", analysis.floor_tiles);
+    println!("  {:<24} {:.0}%", "openness", analysis.openness * 100.0);
+    println!(
+        "  {:<24} {:.0}%",
+        "corridors",
+        analysis.corridor_ratio * 100.0
+    );
+    println!("  {:<24} {}", "dead ends", analysis.dead_ends);
+    println!("  {:<24} {}", "choke points", analysis.choke_points);
+    println!("  {:<24} {}", "average exit distance", exit_distance);
+    println!(
+        "  {:<24} {}",
+        "unreachable tiles", analysis.unreachable_tiles
+    );
+}
+
+/// Describes `count` copies of the same player for a mirror match. Copies that share an explicit
+/// name are numbered to tell them apart.
+fn mirror_players(desc: &OsStr, count: usize) -> anyhow::Result<Vec<PlayerDesc>> {
+    if count < 2 {
+        bail!("a mirror match needs at least 2 copies");
+    }
+    let mut players = (0..count)
+        .map(|index| PlayerDesc::parse(desc, index))
+        .collect::<Result<Vec<_>, _>>()?;
+    if players.iter().map(|player| &player.name).unique().count() < count {
+        for (index, player) in players.iter_mut().enumerate() {
+            player.name = format!("{} #{}", player.name, index + 1);
+        }
+    }
+    Ok(players)
+}
+
+/// Prints a human readable description of the outcome of a battle
+fn print_result(result: &MatchResult, players: &[PlayerDesc], replay: Option<&Path>) {
+    match result.winner {
+        _ if result.aborted => println!("The match was aborted after {} turns", result.turns),
+        Some(winner) => println!("{} won", players[winner.0].name),
+        None => println!("The match ended in a draw"),
+    }
+    if result.adjudicated {
+        let ranking = result
+            .ranking
+            .iter()
+            .map(|player| players[player.0].name.as_str())
+            .collect::<Vec<_>>();
+        println!(
+            "The match was cut short after {} turns, ranked by progress: {}",
+            result.turns,
+            ranking.join(", ")
+        );
+    }
+    if let Some(tie_break) = result.tie_break {
+        let tied = result
+            .tied
+            .iter()
+            .map(|player| players[player.0].name.as_str())
+            .collect::<Vec<_>>();
+        println!(
+            "{} reached an exit in the same turn, decided by tie break {:?}",
+            tied.join(", "),
+            tie_break
+        );
+    }
+    for (player, penalties) in players.iter().zip(result.penalties.iter()) {
+        if penalties.strikes() > 0 {
+            println!(
+                "{}: {} invalid actions, {} errors, {} turns skipped{}",
+                player.name,
+                penalties.invalid_actions,
+                penalties.runner_errors,
+                penalties.skipped_turns,
+                if penalties.eliminated {
+                    ", eliminated"
+                } else {
+                    ""
+                }
+            );
+        }
+    }
+    if let Some(path) = replay {
+        println!("Replay written to {}", path.display());
+    }
+}
+
+/// Splits the text at every `separator` that is not quoted or escaped the way shell words are, so
+/// a command like `sh -c 'a; b'` stays in one piece. Fails if a quote is never closed.
+fn split_outside_quotes(text: &str, separator: char) -> anyhow::Result<Vec<&str>> {
+    let mut parts = Vec::new();
+    let mut start = 0;
+    let mut quote = None;
+    let mut escaped = false;
+    for (index, c) in text.char_indices() {
+        match (quote, c) {
+            _ if escaped => escaped = false,
+            (Some('\''), '\'') => quote = None,
+            (Some('\''), _) => {}
+            (_, '\\') => escaped = true,
+            (Some('"'), '"') => quote = None,
+            (Some(_), _) => {}
+            (None, '\'') | (None, '"') => quote = Some(c),
+            (None, c) if c == separator => {
+                parts.push(&text[start..index]);
+                start = index + c.len_utf8();
+            }
+            (None, _) => {}
+        }
+    }
+    if let Some(quote) = quote {
+        bail!("unterminated {} in {:?}", quote, text);
+    }
+    if escaped {
+        bail!("nothing to escape at the end of {:?}", text);
+    }
+    parts.push(&text[start..]);
+    Ok(parts)
+}
+
+/// Parses the options that follow the command of a command runner, e.g. `cwd=./bots/alice`,
+/// `env=DEBUG=1` and `max_line=65536`. Every option is separated from the previous one by a `;`.
+fn parse_command_options<'a>(
+    options: impl Iterator<Item = &'a str>,
+) -> anyhow::Result<CommandOptions> {
+    let mut result = CommandOptions::default();
+    for option in options.map(str::trim).filter(|option| !option.is_empty()) {
+        match option.splitn(2, '=').collect_tuple() {
+            Some(("cwd", dir)) => result.current_dir = Some(PathBuf::from(dir)),
+            Some(("env", variable)) => match variable.splitn(2, '=').collect_tuple() {
+                Some((name, value)) if !name.is_empty() => {
+                    result.env.push((name.to_string(), value.to_string()))
+                }
+                _ => bail!("expected env=$NAME=$VALUE, found {:?}", option),
+            },
+            Some(("max_line", bytes)) => {
+                result.limits.max_line_length = bytes
+                    .parse::<usize>()
+                    .with_context(|| format!("invalid line length {:?}", bytes))?
+            }
+            Some(("max_message", bytes)) => {
+                result.limits.max_message_size = bytes
+                    .parse::<usize>()
+                    .with_context(|| format!("invalid message size {:?}", bytes))?
+            }
+            _ => bail!(
+                "unknown command option {:?}, expected cwd, env, max_line or max_message",
+                option
+            ),
+        }
+    }
+    Ok(result)
+}
+
+/// Describes a player in a match: the runner that plays it and the name to display for it.
+#[derive(Clone)]
+struct PlayerDesc {
+    name: String,
+    runner: RunnerDesc,
+}
+
+impl PlayerDesc {
+    /// Parses a player description of the form `[$NAME=]$RUNNER`. Players without an explicit
+    /// name are named after their `index`.
+    pub fn parse(s: &OsStr, index: usize) -> anyhow::Result<Self> {
+        if let Some((name, runner)) = s.to_str().and_then(|s| s.splitn(2, '=').collect_tuple()) {
+            let is_name =
+                !name.is_empty() && !name.contains(|c: char| matches!(c, ':' | '/' | '\\'));
+            if is_name {
+                return Ok(PlayerDesc {
+                    name: name.to_string(),
+                    runner: RunnerDesc::parse(OsStr::new(runner))?,
+                });
+            }
+        }
+
+        Ok(PlayerDesc {
+            name: format!("Player {}", index),
+            runner: RunnerDesc::parse(s)?,
+        })
+    }
+}
+
+#[derive(Clone)]
 enum RunnerDesc {
-    Command { command: String, args: Vec<String> },
-    Source { source: PathBuf },
+    Command {
+        command: String,
+        args: Vec<String>,
+        options: CommandOptions,
+    },
+    Source {
+        source: PathBuf,
+    },
+    Builtin {
+        builtin: BuiltinRunner,
+    },
 }
 
 impl RunnerDesc {
+    /// The supported runner description schemes and what they do
+    pub const SCHEMES: &'static [(&'static str, &'static str)] = &[
+        (
+            "command:$COMMAND",
+            "runs a command every turn, append `; cwd=$DIR` or `; env=$NAME=$VALUE` to set \
+             its working directory or environment variables and `; max_line=$BYTES` or \
+             `; max_message=$BYTES` to limit the size of its input",
+        ),
+        ("file:$PATH", "runs a WASI module every turn"),
+        ("local:$PATH", "same as file:$PATH"),
+        ("$PATH", "same as file:$PATH"),
+        (
+            "builtin:$NAME",
+            "plays with a bot that is built into the engine",
+        ),
+    ];
+
     pub fn parse(s: &OsStr) -> anyhow::Result<Self> {
         let s = match s.to_str() {
             Some(s) => s,
@@ -122,9 +1027,18 @@ impl RunnerDesc {
             match typ {
                 "file" | "local" => Self::from_path(PathBuf::from(content)),
                 "command" => {
-                    let (command, args) = parse_command(content)?;
-                    Ok(Self::Command { command, args })
+                    let mut parts = split_outside_quotes(content, ';')?.into_iter();
+                    let (command, args) = parse_command(parts.next().unwrap_or_default())?;
+                    let options = parse_command_options(parts)?;
+                    Ok(Self::Command {
+                        command,
+                        args,
+                        options,
+                    })
                 }
+                "builtin" => Ok(Self::Builtin {
+                    builtin: content.parse()?,
+                }),
                 _ => bail!("unknown runner type {:?}", typ),
             }
         } else {
@@ -136,11 +1050,197 @@ impl RunnerDesc {
         Ok(RunnerDesc::Source { source })
     }
 
+    /// Returns the files on disk that make up this runner
+    pub fn files(&self) -> Vec<PathBuf> {
+        match self {
+            RunnerDesc::Command {
+                command,
+                args,
+                options,
+            } => std::iter::once(command)
+                .chain(args.iter())
+                .map(|path| match &options.current_dir {
+                    Some(dir) => dir.join(path),
+                    None => PathBuf::from(path),
+                })
+                .filter(|path| path.is_file())
+                .collect(),
+            RunnerDesc::Source { source } => vec![source.clone()],
+            RunnerDesc::Builtin { .. } => Vec::new(),
+        }
+    }
+
     /// Construct a runner from this description
     pub fn into_runner(self) -> anyhow::Result<Runner> {
         match self {
-            RunnerDesc::Command { command, args } => Ok(Runner::new_cmd(command, args)),
+            RunnerDesc::Command {
+                command,
+                args,
+                options,
+            } => Ok(Runner::new_cmd_with_options(command, args, options)),
             RunnerDesc::Source { source } => Runner::new_wasm(source),
+            RunnerDesc::Builtin { builtin } => Ok(Runner::new_builtin(builtin)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_outside_quotes() {
+        assert_eq!(
+            split_outside_quotes("bot; cwd=bots", ';').unwrap(),
+            vec!["bot", " cwd=bots"]
+        );
+        assert_eq!(split_outside_quotes("", ';').unwrap(), vec![""]);
+        assert_eq!(split_outside_quotes("a;", ';').unwrap(), vec!["a", ""]);
+    }
+
+    #[test]
+    fn does_not_split_at_quoted_separators() {
+        assert_eq!(
+            split_outside_quotes("sh -c 'a; b'; env=X=1", ';').unwrap(),
+            vec!["sh -c 'a; b'", " env=X=1"]
+        );
+        assert_eq!(
+            split_outside_quotes(r#"sh -c "a; b"; cwd=c"#, ';').unwrap(),
+            vec![r#"sh -c "a; b""#, " cwd=c"]
+        );
+        assert_eq!(
+            split_outside_quotes(r#"echo "it's; fine""#, ';').unwrap(),
+            vec![r#"echo "it's; fine""#]
+        );
+    }
+
+    #[test]
+    fn does_not_split_at_escaped_separators() {
+        assert_eq!(
+            split_outside_quotes(r"echo a\; b; cwd=c", ';').unwrap(),
+            vec![r"echo a\; b", " cwd=c"]
+        );
+        assert_eq!(
+            split_outside_quotes(r#"echo "a\"; b"; cwd=c"#, ';').unwrap(),
+            vec![r#"echo "a\"; b""#, " cwd=c"]
+        );
+        assert_eq!(
+            split_outside_quotes(r"echo 'a\'; cwd=c", ';').unwrap(),
+            vec![r"echo 'a\'", " cwd=c"]
+        );
+    }
+
+    #[test]
+    fn rejects_unterminated_quotes() {
+        assert!(split_outside_quotes("sh -c 'a; b", ';').is_err());
+        assert!(split_outside_quotes(r#"sh -c "a\"; b"#, ';').is_err());
+        assert!(split_outside_quotes(r"echo a\", ';').is_err());
+    }
+
+    #[test]
+    fn parses_command_options() {
+        let options = parse_command_options(
+            vec![
+                " cwd=bots/alice",
+                "env=DEBUG=1",
+                "",
+                "env=A=b=c ",
+                "max_line=10",
+            ]
+            .into_iter(),
+        )
+        .unwrap();
+        assert_eq!(options.current_dir, Some(PathBuf::from("bots/alice")));
+        assert_eq!(
+            options.env,
+            vec![
+                ("DEBUG".to_string(), "1".to_string()),
+                ("A".to_string(), "b=c".to_string())
+            ]
+        );
+        assert_eq!(options.limits.max_line_length, 10);
+        assert!(parse_command_options(vec!["env==1"].into_iter()).is_err());
+        assert!(parse_command_options(vec!["max_message=big"].into_iter()).is_err());
+        assert!(parse_command_options(vec!["cwd"].into_iter()).is_err());
+        assert!(parse_command_options(vec!["nice=1"].into_iter()).is_err());
+    }
+
+    #[test]
+    fn parses_commands_with_quoted_separators() {
+        let desc = RunnerDesc::parse(OsStr::new("command:sh -c 'a; b'; env=X=1")).unwrap();
+        match desc {
+            RunnerDesc::Command {
+                command,
+                args,
+                options,
+            } => {
+                assert_eq!(command, "sh");
+                assert_eq!(args, vec!["-c", "a; b"]);
+                assert_eq!(options.env, vec![("X".to_string(), "1".to_string())]);
+            }
+            _ => panic!("expected a command runner"),
+        }
+        assert!(RunnerDesc::parse(OsStr::new("command:sh -c 'a; b")).is_err());
+    }
+
+    fn result(winner: Option<usize>) -> MatchResult {
+        MatchResult {
+            winner: winner.map(mlr_api::PlayerId),
+            turns: 1,
+            penalties: Vec::new(),
+            aborted: false,
+            tied: Vec::new(),
+            tie_break: None,
+            adjudicated: false,
+            ranking: Vec::new(),
+            memories: Vec::new(),
         }
     }
+
+    #[test]
+    fn exit_codes_stay_below_signal_codes() {
+        assert_eq!(exit_code(&result(None)), EXIT_CODE_DRAW);
+        assert_eq!(exit_code(&result(Some(0))), EXIT_CODE_WINNER_BASE);
+        assert_eq!(exit_code(&result(Some(109))), EXIT_CODE_WINNER_MAX);
+        assert_eq!(exit_code(&result(Some(110))), EXIT_CODE_WINNER_OTHER);
+        assert_eq!(exit_code(&result(Some(300))), EXIT_CODE_WINNER_OTHER);
+        let aborted = MatchResult {
+            aborted: true,
+            ..result(Some(0))
+        };
+        assert_eq!(exit_code(&aborted), EXIT_CODE_ABORTED);
+    }
+
+    #[test]
+    fn copies_are_only_counted_for_mirror_matches() {
+        let parse = |args: &[&str]| MyLittleRobots::from_iter_safe(args.iter());
+        assert!(parse(&["mlr", "run", "builtin:easy", "builtin:hard"]).is_ok());
+        assert!(parse(&["mlr", "run", "--mirror", "builtin:easy", "--count", "3"]).is_ok());
+        assert!(parse(&["mlr", "run", "builtin:easy", "builtin:hard", "--count", "3"]).is_err());
+    }
+
+    #[test]
+    fn watching_conflicts_only_with_other_outputs() {
+        let parse = |args: &[&str]| MyLittleRobots::from_iter_safe(args.iter());
+        assert!(parse(&["mlr", "run", "--watch", "builtin:easy", "builtin:hard"]).is_ok());
+        assert!(parse(&[
+            "mlr",
+            "run",
+            "--watch",
+            "--headless",
+            "builtin:easy",
+            "builtin:hard"
+        ])
+        .is_err());
+        assert!(parse(&[
+            "mlr",
+            "run",
+            "--watch",
+            "--render",
+            "window",
+            "builtin:easy",
+            "builtin:hard"
+        ])
+        .is_err());
+    }
 }
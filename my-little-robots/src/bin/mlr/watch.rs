@@ -0,0 +1,273 @@
+use crate::application::{BattleInfo, BattleInfoPublisher};
+use crate::{BattleOptions, PlayerDesc, RunnerDesc};
+use futures::channel::mpsc::{unbounded, Sender, UnboundedReceiver, UnboundedSender};
+use futures::future::{select, Either};
+use futures::StreamExt;
+use mlr::{BattleControl, WorldUpdate};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+/// How often the watched files are checked for modifications
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Describes how a bot that was built from a cargo project can be rebuilt.
+#[derive(Debug, Clone, Eq, PartialEq)]
+struct CargoProject {
+    root: PathBuf,
+    target: Option<String>,
+    release: bool,
+}
+
+impl CargoProject {
+    /// If `path` points to a build artifact of a cargo project (somewhere in its `target`
+    /// directory) returns the project that produces it.
+    fn from_artifact(path: &Path) -> Option<CargoProject> {
+        let path = path.canonicalize().ok()?;
+        let target_dir = path
+            .ancestors()
+            .find(|dir| dir.file_name().map_or(false, |name| name == "target"))?;
+        let root = target_dir.parent()?;
+        if !root.join("Cargo.toml").is_file() {
+            return None;
+        }
+
+        // The artifact is either stored in `target/$PROFILE/` or `target/$TRIPLE/$PROFILE/`
+        let components = path
+            .strip_prefix(target_dir)
+            .ok()?
+            .iter()
+            .map(|c| c.to_string_lossy().into_owned())
+            .collect::<Vec<_>>();
+        let (target, profile) = match components.as_slice() {
+            [profile, _] => (None, profile),
+            [target, profile, _] => (Some(target.clone()), profile),
+            _ => return None,
+        };
+
+        Some(CargoProject {
+            root: root.to_path_buf(),
+            target,
+            release: profile == "release",
+        })
+    }
+
+    /// Builds the project, blocks until the build has finished.
+    fn build(&self) -> anyhow::Result<()> {
+        let mut command = Command::new("cargo");
+        command
+            .arg("build")
+            .arg("--manifest-path")
+            .arg(self.root.join("Cargo.toml"));
+        if let Some(target) = &self.target {
+            command.arg("--target").arg(target);
+        }
+        if self.release {
+            command.arg("--release");
+        }
+
+        log::info!("Building {}", self.root.display());
+        let status = command.status()?;
+        if !status.success() {
+            anyhow::bail!("building {} failed: {}", self.root.display(), status);
+        }
+        Ok(())
+    }
+}
+
+/// Keeps track of the files used by a set of runners and rebuilds them when they change.
+struct Watcher {
+    paths: Vec<PathBuf>,
+    projects: Vec<CargoProject>,
+    last_modified: Option<SystemTime>,
+}
+
+impl Watcher {
+    /// Constructs a watcher for all the files that make up the runners of the given players.
+    fn new(players: &[PlayerDesc]) -> Watcher {
+        let mut paths = Vec::new();
+        let mut projects = Vec::new();
+        for path in players
+            .iter()
+            .map(|p| &p.runner)
+            .flat_map(RunnerDesc::files)
+        {
+            match CargoProject::from_artifact(&path) {
+                Some(project) => {
+                    paths.push(project.root.join("Cargo.toml"));
+                    paths.push(project.root.join("src"));
+                    if !projects.contains(&project) {
+                        projects.push(project);
+                    }
+                }
+                None => paths.push(path),
+            }
+        }
+
+        let mut watcher = Watcher {
+            paths,
+            projects,
+            last_modified: None,
+        };
+        watcher.last_modified = watcher.modified();
+        watcher
+    }
+
+    /// Rebuilds all the cargo projects that are watched.
+    fn rebuild(&self) -> anyhow::Result<()> {
+        for project in self.projects.iter() {
+            project.build()?;
+        }
+        Ok(())
+    }
+
+    /// Returns the most recent modification time of all watched files.
+    fn modified(&self) -> Option<SystemTime> {
+        self.paths
+            .iter()
+            .filter_map(|path| last_modified(path))
+            .max()
+    }
+
+    /// Waits until any of the watched files has been modified.
+    async fn changed(&mut self) {
+        loop {
+            async_std::task::sleep(POLL_INTERVAL).await;
+            let modified = self.modified();
+            if modified != self.last_modified {
+                self.last_modified = modified;
+                return;
+            }
+        }
+    }
+}
+
+/// Returns the most recent modification time of the file or directory at `path`. Directories are
+/// searched recursively.
+fn last_modified(path: &Path) -> Option<SystemTime> {
+    let metadata = std::fs::metadata(path).ok()?;
+    if !metadata.is_dir() {
+        return metadata.modified().ok();
+    }
+
+    std::fs::read_dir(path)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| last_modified(&entry.path()))
+        .chain(metadata.modified().ok())
+        .max()
+}
+
+/// Forwards control messages to the battle that is currently running. The pause state and speed
+/// are remembered so they carry over when a battle is restarted.
+struct ControlForwarder {
+    receiver: UnboundedReceiver<BattleControl>,
+    paused: bool,
+    tick_duration: Option<Duration>,
+}
+
+impl ControlForwarder {
+    /// Constructs a control channel for a new battle that starts in the current state.
+    fn channel(
+        &self,
+    ) -> (
+        UnboundedSender<BattleControl>,
+        UnboundedReceiver<BattleControl>,
+    ) {
+        let (sender, receiver) = unbounded();
+        if self.paused {
+            let _ = sender.unbounded_send(BattleControl::Pause);
+        }
+        let _ = sender.unbounded_send(BattleControl::SetTickDuration(self.tick_duration));
+        (sender, receiver)
+    }
+
+    /// Forwards all received messages to `sender`. Only completes once an `Abort` message was
+    /// forwarded.
+    async fn forward(&mut self, sender: UnboundedSender<BattleControl>) {
+        while let Some(message) = self.receiver.next().await {
+            match message {
+                BattleControl::Pause => self.paused = true,
+                BattleControl::Resume => self.paused = false,
+                BattleControl::SetTickDuration(duration) => self.tick_duration = duration,
+                BattleControl::Step => {}
+                BattleControl::Abort => {
+                    let _ = sender.unbounded_send(message);
+                    return;
+                }
+            }
+
+            // The battle might have already ended
+            let _ = sender.unbounded_send(message);
+        }
+        futures::future::pending().await
+    }
+}
+
+/// The channels over which battles communicate with the viewer
+pub struct ViewerLink {
+    pub worlds: Sender<WorldUpdate>,
+    pub control: UnboundedReceiver<BattleControl>,
+    pub info: Arc<async_watch::Sender<BattleInfo>>,
+}
+
+/// Runs battles between the given players until aborted. Whenever one of the files that make up
+/// the runners changes, the runners are rebuilt and a new battle is started.
+pub async fn run(
+    players: Vec<PlayerDesc>,
+    options: BattleOptions,
+    tick_duration: Duration,
+    viewer: ViewerLink,
+) {
+    let mut watcher = Watcher::new(&players);
+    let mut controls = ControlForwarder {
+        receiver: viewer.control,
+        paused: false,
+        tick_duration: Some(tick_duration),
+    };
+    loop {
+        let battle = watcher
+            .rebuild()
+            .and_then(|_| crate::create_battle(players.iter().cloned(), &options));
+        match battle {
+            Ok(mut battle) => {
+                let (control_sender, control_receiver) = controls.channel();
+                battle.set_control(control_receiver);
+                battle.add_observer(Box::new(BattleInfoPublisher::new(viewer.info.clone())));
+
+                let battle = Box::pin(battle.run(Some(tick_duration), Some(viewer.worlds.clone())));
+                let changed = Box::pin(watcher.changed());
+                let forward = Box::pin(controls.forward(control_sender));
+                match select(battle, select(changed, forward)).await {
+                    Either::Left((result, waiting)) => {
+                        crate::print_result(&result, &players, options.record.as_deref());
+                        if result.aborted {
+                            return;
+                        }
+                        log::info!("Waiting for changes");
+                        if let Either::Right(_) = waiting.await {
+                            return;
+                        }
+                    }
+                    Either::Right((Either::Right(_), battle)) => {
+                        // Let the battle finish its turn so it can write its recordings
+                        let result = battle.await;
+                        crate::print_result(&result, &players, options.record.as_deref());
+                        return;
+                    }
+                    Either::Right((Either::Left(_), _)) => {}
+                }
+            }
+            Err(err) => {
+                log::error!("{:#}, waiting for changes", err);
+                let changed = Box::pin(watcher.changed());
+                let forward = Box::pin(controls.forward(unbounded().0));
+                if let Either::Right(_) = select(changed, forward).await {
+                    return;
+                }
+            }
+        }
+        log::info!("Change detected, restarting the match");
+    }
+}
@@ -1,8 +1,9 @@
+use crate::palette::{player_rgb, player_symbol};
 use crate::Map;
-use crate::World;
+use crate::{Scoreboard, World};
 use bracket_lib::prelude::*;
-use mlr_api::{Coord, PlayerId, TileType, Unit, UnitId};
-use std::collections::{HashMap, HashSet};
+use mlr_api::{Coord, Item, Obstacle, ObstacleKind, PlayerId, TileType, Unit, UnitId};
+use std::collections::{HashMap, HashSet, VecDeque};
 
 /// Returns the correct glyph for the TileType
 pub fn glyph_for(coord: Coord, map: &Map) -> (impl Into<RGBA>, FontCharType) {
@@ -11,27 +12,14 @@ pub fn glyph_for(coord: Coord, map: &Map) -> (impl Into<RGBA>, FontCharType) {
         TileType::Wall => (WHITE, wall_glyph(map, coord.x, coord.y)),
         TileType::Floor => (GRAY, to_cp437('.')),
         TileType::Exit => (CYAN, to_cp437('>')),
+        TileType::Goal => (GOLD, to_cp437('%')),
+        TileType::Custom { .. } => (MAGENTA, to_cp437('?')),
     }
 }
 
 pub fn player_color(player: PlayerId) -> impl Into<RGBA> {
-    match player.0 {
-        0 => LIGHTGREEN,
-        1 => BLUE_VIOLET,
-        2 => ORANGERED,
-        3 => GOLD,
-        _ => GRAY,
-    }
-}
-
-fn player_symbol(player: PlayerId) -> char {
-    match player.0 {
-        0 => '♦',
-        1 => '♣',
-        2 => '¶',
-        3 => '♣',
-        _ => '♥',
-    }
+    let [r, g, b] = player_rgb(player);
+    RGBA::from_u8(r, g, b, 255)
 }
 
 pub fn player_glyph(player: PlayerId) -> FontCharType {
@@ -88,24 +76,95 @@ pub fn wall_glyph(map: &Map, x: isize, y: isize) -> FontCharType {
 }
 
 /// Draws the specified map
-pub fn draw_map<F: Fn(Coord) -> f32>(map: &Map, is_visible: F, ctx: &mut BTerm) {
+/// Draws the map with the tile at `offset` in the top left corner of the console. Tiles are clipped
+/// to a viewport the size of the map.
+pub fn draw_map<F: Fn(Coord) -> f32>(map: &Map, is_visible: F, offset: Point, ctx: &mut BTerm) {
     let height = map.height as isize;
     let width = map.width as isize;
 
     for y in 0..height {
         for x in 0..width {
-            let pos: Coord = (x, y).into();
+            let screen = Point::new(x, y) - offset;
+            if !in_viewport(screen, map) {
+                continue;
+            }
 
+            let pos: Coord = (x, y).into();
             let (color, glyph) = glyph_for((x, y).into(), map);
             let mut color = color.into();
             color.a = 0.1 + (is_visible(pos) * 0.9);
-            ctx.set(x, y, color, BLACK, glyph);
+            ctx.set(screen.x, screen.y, color, BLACK, glyph);
+        }
+    }
+}
+
+/// Draws the items that lie on the ground, items that are carried move along with their carrier
+/// and are not drawn. The map is positioned the same way as in [`draw_map`].
+pub fn draw_items(items: &[Item], map: &Map, offset: Point, ctx: &mut BTerm) {
+    for item in items.iter().filter(|item| item.carrier.is_none()) {
+        let screen = Point::new(item.location.x, item.location.y) - offset;
+        if in_viewport(screen, map) {
+            ctx.set(screen.x, screen.y, GOLD, BLACK, to_cp437('*'));
+        }
+    }
+}
+
+/// Draws the obstacles that stand on the map. The map is positioned the same way as in
+/// [`draw_map`].
+pub fn draw_obstacles(obstacles: &[Obstacle], map: &Map, offset: Point, ctx: &mut BTerm) {
+    for obstacle in obstacles.iter() {
+        let screen = Point::new(obstacle.location.x, obstacle.location.y) - offset;
+        if in_viewport(screen, map) {
+            let glyph = match obstacle.kind {
+                ObstacleKind::Crate => to_cp437('■'),
+                ObstacleKind::Rubble => to_cp437('▒'),
+            };
+            ctx.set(screen.x, screen.y, BURLYWOOD, BLACK, glyph);
+        }
+    }
+}
+
+/// Returns true if the given console position lies within the viewport in which `map` is drawn
+pub fn in_viewport(position: Point, map: &Map) -> bool {
+    position.x >= 0
+        && position.y >= 0
+        && position.x < map.width as i32
+        && position.y < map.height as i32
+}
+
+/// Tints the background of every tile from which an exit can be reached with a color that reflects
+/// the walking distance to the closest exit, ranging from green (close) to red (far). The map is
+/// positioned the same way as in [`draw_map`].
+pub fn draw_distance_overlay(map: &Map, offset: Point, ctx: &mut BTerm) {
+    let max_distance = map
+        .distance_to_exit
+        .iter()
+        .filter_map(|distance| *distance)
+        .max()
+        .unwrap_or(0)
+        .max(1);
+
+    for y in 0..map.height {
+        for x in 0..map.width {
+            let screen = Point::new(x, y) - offset;
+            if !in_viewport(screen, map) {
+                continue;
+            }
+            if let Some(distance) = map.get_distance_to_exit((x, y)) {
+                let t = distance as f32 / max_distance as f32;
+                ctx.set_bg(screen.x, screen.y, RGBA::from_f32(t, 1.0 - t, 0.0, 0.6));
+            }
         }
     }
 }
 
-/// Draw the UI
-pub fn draw_ui(world: &World, _units: &HashMap<UnitId, Coord>, ctx: &mut BTerm) {
+/// Draw the UI, `player_names` holds the display name of every player indexed by `PlayerId`.
+pub fn draw_ui(
+    world: &World,
+    _units: &HashMap<UnitId, Coord>,
+    player_names: &[String],
+    ctx: &mut BTerm,
+) {
     let map = &world.map;
     let mut ui_string = format!("Turn {}", world.turn);
 
@@ -122,7 +181,97 @@ pub fn draw_ui(world: &World, _units: &HashMap<UnitId, Coord>, ctx: &mut BTerm)
     player_vector.sort_by(|a, b| a.0.cmp(&b.0));
 
     ui_string += &player_vector.iter().fold(String::new(), |acc, p| {
-        acc + &format!(" Player {}: {}", p.0, player_symbol(**p))
+        let name = player_names
+            .get(p.0)
+            .cloned()
+            .unwrap_or_else(|| format!("Player {}", p.0));
+        acc + &format!(" {}: {}", name, player_symbol(**p))
     });
     ctx.print_centered(map.height - 1, ui_string);
 }
+
+/// The width of the sidebar drawn by `draw_sidebar` in tiles
+pub const SIDEBAR_WIDTH: usize = 20;
+
+/// Draws a sidebar with the statistics of all players at horizontal position `x`
+pub fn draw_sidebar(scoreboard: &Scoreboard, x: usize, ctx: &mut BTerm) {
+    let mut y = 1;
+    ctx.print(x + 1, y, format!("Turn {}", scoreboard.turn));
+    y += 2;
+
+    // Turn times are drawn relative to the slowest player of the last turn
+    let slowest = scoreboard
+        .players
+        .iter()
+        .filter_map(|stats| stats.turn_time)
+        .max()
+        .unwrap_or(0);
+
+    for (index, stats) in scoreboard.players.iter().enumerate() {
+        let player = PlayerId(index);
+        ctx.set(x + 1, y, player_color(player), BLACK, player_glyph(player));
+        ctx.print_color(x + 3, y, player_color(player), BLACK, &stats.name);
+        y += 1;
+
+        let distance = stats
+            .distance_to_exit
+            .map(|distance| distance.to_string())
+            .unwrap_or_else(|| "-".to_string());
+        let rows = [
+            ("units", stats.units.to_string()),
+            ("errors", stats.errors.to_string()),
+            ("invalid", stats.invalid_actions.to_string()),
+            ("timeouts", stats.timeouts.to_string()),
+            ("exit dist", distance),
+            ("explored", format!("{}%", stats.explored)),
+            ("time", format_turn_time(stats.turn_time)),
+        ];
+        for (label, value) in rows.iter() {
+            ctx.print_color(x + 3, y, GRAY, BLACK, label);
+            ctx.print(x + SIDEBAR_WIDTH - 1 - value.len(), y, value);
+            y += 1;
+        }
+        draw_latency_bar(stats.turn_time, slowest, x + 3, y, ctx);
+        y += 1;
+        y += 1;
+    }
+}
+
+/// Formats the time a runner took for a turn in milliseconds
+fn format_turn_time(micros: Option<u64>) -> String {
+    match micros {
+        Some(micros) => format!("{:.1}ms", micros as f64 / 1000.0),
+        None => "-".to_string(),
+    }
+}
+
+/// Draws a bar at position `x`, `y` whose length is the turn time of a player relative to the
+/// `slowest` turn time. The slowest player is drawn in red.
+fn draw_latency_bar(micros: Option<u64>, slowest: u64, x: usize, y: usize, ctx: &mut BTerm) {
+    let width = SIDEBAR_WIDTH - 4;
+    let micros = match micros {
+        Some(micros) if slowest > 0 => micros,
+        _ => return,
+    };
+    let length = ((micros as f64 / slowest as f64) * width as f64).ceil() as usize;
+    let color = if micros == slowest { RED } else { GREEN };
+    for i in 0..width {
+        if i < length {
+            ctx.set(x + i, y, color, BLACK, to_cp437('█'));
+        } else {
+            ctx.set(x + i, y, DARK_GRAY, BLACK, to_cp437('░'));
+        }
+    }
+}
+
+/// Draws the descriptions of recent events with the newest event on row `bottom` and older events
+/// above it.
+pub fn draw_ticker(entries: &VecDeque<String>, bottom: usize, ctx: &mut BTerm) {
+    for (age, entry) in entries.iter().rev().enumerate() {
+        if age > bottom {
+            break;
+        }
+        let color = if age == 0 { WHITE } else { GRAY };
+        ctx.print_color(1, bottom - age, color, BLACK, entry);
+    }
+}
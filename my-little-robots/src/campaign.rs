@@ -0,0 +1,160 @@
+use crate::{Battle, PlayerRunner, Scenario};
+use anyhow::Context;
+use mlr_api::{PlayerId, PlayerMemory};
+use serde_derive::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// The player that plays the campaign
+const PLAYER: PlayerId = PlayerId(0);
+
+/// A `Campaign` is a sequence of scenarios that a single player plays one after the other. A level
+/// is completed by winning its scenario, the campaign ends at the first level that is not
+/// completed. Campaigns are stored as TOML files:
+///
+/// ```toml
+/// name = "Tutorial"
+/// memory_limit = 1024
+///
+/// [[levels]]
+/// scenario = "levels/first_steps.toml"
+///
+/// [[levels]]
+/// scenario = "levels/the_maze.toml"
+/// ```
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Campaign {
+    /// The human readable name of the campaign
+    #[serde(default)]
+    pub name: Option<String>,
+
+    /// The maximum size in bytes of the memory that is carried over from one level to the next.
+    /// Larger memory is discarded and the player starts the next level without any memory. If
+    /// not specified memory is never carried over.
+    #[serde(default)]
+    pub memory_limit: Option<usize>,
+
+    /// The levels of the campaign in the order in which they are played
+    pub levels: Vec<Level>,
+}
+
+/// A single level of a `Campaign`
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Level {
+    /// The path to the scenario of the level, relative to the campaign file
+    pub scenario: PathBuf,
+}
+
+/// The outcome of a single level of a campaign
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct LevelResult {
+    /// The name of the scenario of the level
+    pub name: String,
+
+    /// True if the player won the level
+    pub completed: bool,
+
+    /// The number of turns that were played
+    pub turns: usize,
+}
+
+/// The outcome of a campaign
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct CampaignResult {
+    /// The results of the levels that were played, in order
+    pub levels: Vec<LevelResult>,
+
+    /// True if every level of the campaign was completed
+    pub completed: bool,
+}
+
+impl CampaignResult {
+    /// Returns the number of levels that were completed
+    pub fn completed_levels(&self) -> usize {
+        self.levels.iter().filter(|level| level.completed).count()
+    }
+
+    /// Returns the number of turns that were played over all levels
+    pub fn total_turns(&self) -> usize {
+        self.levels.iter().map(|level| level.turns).sum()
+    }
+}
+
+impl Campaign {
+    /// Reads a campaign from the TOML file at the specified path. The paths of the scenarios are
+    /// resolved relative to the directory of the file.
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Campaign> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path)?;
+        let mut campaign: Campaign = toml::from_str(&contents)?;
+        let dir = path.parent().unwrap_or_else(|| Path::new(""));
+        for level in campaign.levels.iter_mut() {
+            level.scenario = dir.join(&level.scenario);
+        }
+        Ok(campaign)
+    }
+
+    /// Plays the levels of the campaign in order until a level is not completed. `new_runner` is
+    /// called to construct the runner of the player for every level, `configure` can be used to
+    /// add observers to the battle of every level.
+    pub async fn run(
+        &self,
+        mut new_runner: impl FnMut() -> anyhow::Result<Box<dyn PlayerRunner>>,
+        mut configure: impl FnMut(&mut Battle),
+    ) -> anyhow::Result<CampaignResult> {
+        let mut result = CampaignResult::default();
+        let mut memory: Option<PlayerMemory> = None;
+        for level in self.levels.iter() {
+            let scenario = Scenario::load(&level.scenario)
+                .with_context(|| format!("could not read scenario {}", level.scenario.display()))?;
+            let name = scenario.name.clone().unwrap_or_else(|| {
+                level
+                    .scenario
+                    .file_stem()
+                    .map_or_else(String::new, |stem| stem.to_string_lossy().into_owned())
+            });
+
+            let mut battle = Battle::default();
+            scenario.apply(&mut battle)?;
+            battle.add_player(new_runner()?);
+            if let Some(memory) = memory.take() {
+                battle.set_memory(PLAYER, memory);
+            }
+            configure(&mut battle);
+            battle.check()?;
+
+            let outcome = battle.run(None, None).await;
+            let completed = outcome.winner == Some(PLAYER);
+            result.levels.push(LevelResult {
+                name,
+                completed,
+                turns: outcome.turns,
+            });
+            if !completed {
+                return Ok(result);
+            }
+
+            memory = self.carried_memory(outcome.memories.into_iter().next());
+        }
+
+        result.completed = true;
+        Ok(result)
+    }
+
+    /// Returns the memory that is carried over to the next level, if any
+    fn carried_memory(&self, memory: Option<PlayerMemory>) -> Option<PlayerMemory> {
+        let limit = self.memory_limit?;
+        memory.filter(|memory| {
+            let size = memory.size();
+            if size > limit {
+                log::warn!(
+                    "memory of {} bytes exceeds the limit of {} bytes, it is not carried over",
+                    size,
+                    limit
+                );
+            }
+            size <= limit
+        })
+    }
+}
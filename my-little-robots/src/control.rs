@@ -0,0 +1,148 @@
+use futures::channel::mpsc::UnboundedReceiver;
+use futures::StreamExt;
+use std::time::Duration;
+
+/// Messages that can be sent to a running `Battle` to control its progress.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum BattleControl {
+    /// Stops playing turns until `Resume` is received
+    Pause,
+
+    /// Continues playing turns after the battle was paused
+    Resume,
+
+    /// Plays a single turn while the battle is paused
+    Step,
+
+    /// Changes the time to wait between turns
+    SetTickDuration(Option<Duration>),
+
+    /// Stops the battle after the current turn. The battle ends in a draw and is marked as
+    /// aborted.
+    Abort,
+}
+
+/// Keeps track of the state of a battle as controlled by `BattleControl` messages.
+pub(crate) struct Controller {
+    receiver: Option<UnboundedReceiver<BattleControl>>,
+    paused: bool,
+    aborted: bool,
+    tick_duration: Option<Duration>,
+}
+
+impl Controller {
+    pub fn new(
+        receiver: Option<UnboundedReceiver<BattleControl>>,
+        tick_duration: Option<Duration>,
+    ) -> Self {
+        Controller {
+            receiver,
+            paused: false,
+            aborted: false,
+            tick_duration,
+        }
+    }
+
+    /// Returns the time to wait between turns
+    pub fn tick_duration(&self) -> Option<Duration> {
+        self.tick_duration
+    }
+
+    /// Processes all pending control messages and returns when the next turn can be played. While
+    /// the battle is paused this waits until the battle is resumed or a single step is requested.
+    /// Returns false if the battle was aborted and no more turns should be played.
+    pub async fn wait_for_turn(&mut self) -> bool {
+        while let Some(receiver) = &mut self.receiver {
+            let message = if self.paused {
+                match receiver.next().await {
+                    Some(message) => message,
+                    None => {
+                        // Nobody can resume the battle anymore
+                        self.receiver = None;
+                        self.paused = false;
+                        break;
+                    }
+                }
+            } else {
+                match receiver.try_next() {
+                    Ok(Some(message)) => message,
+                    Ok(None) => {
+                        self.receiver = None;
+                        break;
+                    }
+                    Err(_) => break,
+                }
+            };
+
+            match message {
+                BattleControl::Pause => self.paused = true,
+                BattleControl::Resume => self.paused = false,
+                BattleControl::Step if self.paused => break,
+                BattleControl::Step => {}
+                BattleControl::SetTickDuration(duration) => self.tick_duration = duration,
+                BattleControl::Abort => {
+                    self.aborted = true;
+                    break;
+                }
+            }
+        }
+        !self.aborted
+    }
+}
+
+/// The moment after which a battle is cut short, see `Battle::set_deadline`
+#[cfg(feature = "native")]
+pub(crate) struct Deadline(Option<std::time::Instant>);
+
+#[cfg(feature = "native")]
+impl Deadline {
+    /// Starts counting down from now, a battle without a duration never passes its deadline
+    pub fn after(duration: Option<Duration>) -> Self {
+        Deadline(duration.map(|duration| std::time::Instant::now() + duration))
+    }
+
+    pub fn passed(&self) -> bool {
+        self.0
+            .map_or(false, |deadline| std::time::Instant::now() >= deadline)
+    }
+}
+
+/// Without the `native` feature there is no clock, the deadline never passes.
+#[cfg(not(feature = "native"))]
+pub(crate) struct Deadline;
+
+#[cfg(not(feature = "native"))]
+impl Deadline {
+    pub fn after(_duration: Option<Duration>) -> Self {
+        Deadline
+    }
+
+    pub fn passed(&self) -> bool {
+        false
+    }
+}
+
+/// Waits for the specified duration. Relies only on `async-io` timers so it works regardless of
+/// the executor the engine is running on.
+#[cfg(feature = "native")]
+pub(crate) async fn sleep(duration: Duration) {
+    async_io::Timer::after(duration).await;
+}
+
+/// Without the `native` feature there are no timers, returns immediately.
+#[cfg(not(feature = "native"))]
+pub(crate) async fn sleep(_duration: Duration) {}
+
+/// Runs the future to completion and returns its output together with the time it took.
+#[cfg(feature = "native")]
+pub(crate) async fn timed<F: std::future::Future>(future: F) -> (F::Output, Duration) {
+    let start = std::time::Instant::now();
+    let output = future.await;
+    (output, start.elapsed())
+}
+
+/// Without the `native` feature there is no clock, the reported time is always zero.
+#[cfg(not(feature = "native"))]
+pub(crate) async fn timed<F: std::future::Future>(future: F) -> (F::Output, Duration) {
+    (future.await, Duration::default())
+}
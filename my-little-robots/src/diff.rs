@@ -0,0 +1,137 @@
+use crate::{GameEvent, World};
+use mlr_api::{Item, ItemId, Marker, Obstacle, PlayerId, PlayerTile, Unit, UnitId};
+use serde_derive::{Deserialize, Serialize};
+use std::sync::Arc;
+
+/// The changes to a `World` caused by a single turn. Applying the diff to the world before the
+/// turn results in the world after the turn, without having to send the entire world.
+#[derive(Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub struct WorldDiff {
+    /// The turn of the world after the diff has been applied
+    pub turn: usize,
+
+    /// The units that were added or changed during the turn
+    pub units: Vec<Unit>,
+
+    /// The units that were removed during the turn
+    pub removed_units: Vec<UnitId>,
+
+    /// The markers that were placed during the turn
+    #[serde(default)]
+    pub markers: Vec<(PlayerId, Marker)>,
+
+    /// The tiles of the map that changed during the turn
+    #[serde(default)]
+    pub tiles: Vec<PlayerTile>,
+
+    /// All the items that are left if any item changed during the turn
+    #[serde(default)]
+    pub items: Option<Vec<Item>>,
+
+    /// The items that were delivered during the turn
+    #[serde(default)]
+    pub delivered: Vec<(PlayerId, ItemId)>,
+
+    /// All the obstacles that are left if any obstacle changed during the turn
+    #[serde(default)]
+    pub obstacles: Option<Vec<Obstacle>>,
+
+    /// The events that occurred during the turn
+    pub events: Vec<GameEvent>,
+}
+
+impl WorldDiff {
+    /// Computes the changes between two versions of a world
+    pub fn between(old: &World, new: &World, events: &[GameEvent]) -> WorldDiff {
+        let units = new
+            .units
+            .iter()
+            .filter(|unit| !old.units.contains(unit))
+            .cloned()
+            .collect();
+        let removed_units = old
+            .units
+            .iter()
+            .filter(|unit| new.units.iter().all(|u| u.id != unit.id))
+            .map(|unit| unit.id)
+            .collect();
+        let markers = new
+            .markers
+            .iter()
+            .filter(|marker| !old.markers.contains(marker))
+            .copied()
+            .collect();
+        let items = if old.items == new.items {
+            None
+        } else {
+            Some(new.items.clone())
+        };
+        let obstacles = if old.obstacles == new.obstacles {
+            None
+        } else {
+            Some(new.obstacles.clone())
+        };
+
+        WorldDiff {
+            turn: new.turn,
+            units,
+            removed_units,
+            markers,
+            tiles: new.changed_tiles.clone(),
+            items,
+            delivered: new.delivered[old.delivered.len().min(new.delivered.len())..].to_vec(),
+            obstacles,
+            events: events.to_vec(),
+        }
+    }
+}
+
+/// An update of the world that is streamed to viewers. A stream starts with a snapshot of the
+/// entire world followed by a diff for every turn.
+#[derive(Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[serde(tag = "update", rename_all = "snake_case")]
+pub enum WorldUpdate {
+    Snapshot(World),
+    Diff(WorldDiff),
+}
+
+impl World {
+    /// Applies the changes of a single turn to this world
+    pub fn apply_diff(&mut self, diff: &WorldDiff) {
+        self.units
+            .retain(|unit| !diff.removed_units.contains(&unit.id));
+        for unit in diff.units.iter() {
+            match self.units.iter_mut().find(|u| u.id == unit.id) {
+                Some(existing) => *existing = unit.clone(),
+                None => self.units.push(unit.clone()),
+            }
+        }
+        for &(player, marker) in diff.markers.iter() {
+            self.place_marker(player, marker);
+        }
+        if !diff.tiles.is_empty() {
+            let map = Arc::make_mut(&mut self.map);
+            for tile in diff.tiles.iter() {
+                map[tile.coord] = tile.tile_type;
+            }
+            map.compute_distance_to_exit();
+        }
+        self.changed_tiles = diff.tiles.clone();
+        if let Some(items) = &diff.items {
+            self.items = items.clone();
+        }
+        self.delivered.extend(diff.delivered.iter().copied());
+        if let Some(obstacles) = &diff.obstacles {
+            self.obstacles = obstacles.clone();
+        }
+        self.turn = diff.turn;
+    }
+
+    /// Applies a streamed update to this world
+    pub fn apply_update(&mut self, update: WorldUpdate) {
+        match update {
+            WorldUpdate::Snapshot(world) => *self = world,
+            WorldUpdate::Diff(diff) => self.apply_diff(&diff),
+        }
+    }
+}
@@ -0,0 +1,191 @@
+use crate::{GameEvent, GameRules, Map, MapDiff, TileChange, World};
+use mlr_api::{Coord, PlayerTile, TileType};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use serde_derive::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+/// A scripted change of the map during a battle, like a bridge that collapses or a door that opens.
+/// Changes are part of the rules of the game and are usually defined by a scenario:
+///
+/// ```toml
+/// # The door opens at the end of turn 10 and every 20 turns after that
+/// [[rules.map_changes]]
+/// turn = 10
+/// every = 20
+/// tiles = [[5, 3]]
+/// tile = "floor"
+///
+/// # ... and closes again 10 turns later
+/// [[rules.map_changes]]
+/// turn = 20
+/// every = 20
+/// tiles = [[5, 3]]
+/// tile = "wall"
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MapChange {
+    /// The turn at the end of which the change is applied for the first time
+    pub turn: usize,
+
+    /// If specified the change is applied again every `every` turns
+    #[serde(default)]
+    pub every: Option<usize>,
+
+    /// The locations of the tiles that change
+    pub tiles: Vec<Coord>,
+
+    /// The type the tiles change into
+    pub tile: TileType,
+}
+
+impl MapChange {
+    /// Returns true if the change is applied at the end of the given turn
+    fn applies_at(&self, turn: usize) -> bool {
+        match self.every {
+            _ if turn < self.turn => false,
+            Some(every) if every > 0 => (turn - self.turn) % every == 0,
+            _ => turn == self.turn,
+        }
+    }
+}
+
+impl World {
+    /// Changes the type of a single tile. Tiles that units stand on are never changed into tiles
+    /// that cannot be entered, returns false if the tile was not changed. Distances to the exits
+    /// are updated at the end of the turn.
+    pub fn set_tile(&mut self, coord: Coord, tile: TileType) -> bool {
+        if !self.map.in_bounds(coord) || self.map[coord] == tile {
+            return false;
+        }
+        if !tile.can_enter() && self.units.iter().any(|unit| unit.location == coord) {
+            log::debug!(
+                "tile {:?} is occupied, it does not change into {:?}",
+                coord,
+                tile
+            );
+            return false;
+        }
+        Arc::make_mut(&mut self.map)[coord] = tile;
+        self.changed_tiles.push(PlayerTile {
+            coord,
+            tile_type: tile,
+        });
+        true
+    }
+
+    /// Changes the tiles of the map as described by the diff, every tile with `set_tile`. Returns
+    /// the diff of the tiles that actually changed, its inverse undoes the change.
+    pub fn apply_map_diff(&mut self, diff: &MapDiff) -> MapDiff {
+        let mut changes = Vec::with_capacity(diff.changes.len());
+        for change in diff.changes.iter() {
+            if !self.map.in_bounds(change.coord) {
+                continue;
+            }
+            let from = self.map[change.coord];
+            if self.set_tile(change.coord, change.to) {
+                changes.push(TileChange {
+                    coord: change.coord,
+                    from,
+                    to: change.to,
+                });
+            }
+        }
+        MapDiff { changes }
+    }
+
+    /// The environment phase of a turn, applies the scripted changes of the map that are due and
+    /// moves the exits if the rules say so
+    pub(crate) fn apply_environment(&mut self, rules: &GameRules, events: &mut Vec<GameEvent>) {
+        self.apply_map_changes(&rules.map_changes, events);
+        if let Some(every) = rules.moving_exit {
+            if every > 0 && (self.turn + 1) % every == 0 {
+                self.move_exits(rules.seed, events);
+            }
+        }
+    }
+
+    /// Applies the scripted changes of the map that are due this turn
+    fn apply_map_changes(&mut self, changes: &[MapChange], events: &mut Vec<GameEvent>) {
+        let first_change = self.changed_tiles.len();
+        let turn = self.turn;
+        for change in changes.iter().filter(|change| change.applies_at(turn)) {
+            for &coord in change.tiles.iter() {
+                self.set_tile(coord, change.tile);
+            }
+        }
+        if self.changed_tiles.len() > first_change {
+            events.push(GameEvent::TilesChanged {
+                tiles: self.changed_tiles[first_change..].to_vec(),
+            });
+        }
+    }
+
+    /// Moves every exit to a random floor tile that can be reached by at least one unit. Exits
+    /// that a unit stands on stay where they are. The random choice only depends on `seed` and the
+    /// current turn so replays can be verified.
+    fn move_exits(&mut self, seed: u64, events: &mut Vec<GameEvent>) {
+        let mut rng = StdRng::seed_from_u64(seed ^ (self.turn as u64).wrapping_mul(EXIT_SEED_MIX));
+        let exits = (0..self.map.height)
+            .flat_map(|y| (0..self.map.width).map(move |x| Coord::new(x, y)))
+            .filter(|&coord| self.map[coord] == TileType::Exit)
+            .filter(|&coord| self.units.iter().all(|unit| unit.location != coord))
+            .collect::<Vec<_>>();
+        if exits.is_empty() {
+            return;
+        }
+
+        let starts = self.units.iter().map(|unit| unit.location);
+        let candidates = reachable_tiles(&self.map, starts)
+            .into_iter()
+            .filter(|&coord| self.map[coord] == TileType::Floor)
+            .filter(|&coord| self.obstacle_at(coord).is_none())
+            .filter(|&coord| self.units.iter().all(|unit| unit.location != coord))
+            .collect::<Vec<_>>();
+
+        for from in exits {
+            let to = match candidates.choose(&mut rng) {
+                Some(&to) if self.map[to] == TileType::Floor => to,
+                _ => continue,
+            };
+            self.set_tile(from, TileType::Floor);
+            self.set_tile(to, TileType::Exit);
+            events.push(GameEvent::ExitMoved { from, to });
+        }
+    }
+}
+
+/// Mixes the turn into the seed so every relocation of the exits is different
+const EXIT_SEED_MIX: u64 = 0x9e37_79b9_7f4a_7c15;
+
+/// Returns all tiles that can be reached by walking from any of the `starts`, in the order in
+/// which they are found
+fn reachable_tiles(map: &Map, starts: impl Iterator<Item = Coord>) -> Vec<Coord> {
+    let mut visited = vec![false; map.width * map.height];
+    let mut queue = VecDeque::new();
+    let mut reachable = Vec::new();
+    for start in starts.filter(|&start| map.in_bounds(start)) {
+        let index = start.x as usize + start.y as usize * map.width;
+        if !visited[index] {
+            visited[index] = true;
+            queue.push_back(start);
+        }
+    }
+    while let Some(coord) = queue.pop_front() {
+        reachable.push(coord);
+        for neighbor in coord.neighbors() {
+            if !map.can_enter_tile(neighbor) {
+                continue;
+            }
+            let index = neighbor.x as usize + neighbor.y as usize * map.width;
+            if !visited[index] {
+                visited[index] = true;
+                queue.push_back(neighbor);
+            }
+        }
+    }
+    reachable
+}
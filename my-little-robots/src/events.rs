@@ -0,0 +1,238 @@
+use crate::ActionValidationError;
+use mlr_api::{Coord, Direction, ItemId, PlayerAction, PlayerId, PlayerTile, UnitId};
+use serde_derive::{Deserialize, Serialize};
+
+/// Describes something that happened during a turn
+#[derive(Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum GameEvent {
+    /// A player returned a set of actions
+    ActionsSubmitted {
+        player: PlayerId,
+        actions: Vec<PlayerAction>,
+    },
+
+    /// The runner of a player failed to produce any actions. `code` identifies the kind of error,
+    /// see `RunnerError::code`.
+    RunnerError {
+        player: PlayerId,
+        code: u16,
+        error: String,
+    },
+
+    /// The runner of a player finished its turn, successfully or not, after `micros` microseconds
+    RunnerTimed { player: PlayerId, micros: u64 },
+
+    /// The runner of a player did not produce any actions within the time limit
+    Timeout { player: PlayerId },
+
+    /// An action of a player was rejected. `index` is the position of the action in the output of
+    /// the player and `code` identifies the reason, see `ActionValidationError::code`.
+    InvalidAction {
+        player: PlayerId,
+        index: usize,
+        action: PlayerAction,
+        code: u16,
+        error: ActionValidationError,
+    },
+
+    /// A player misbehaved too often and has to sit out the next `turns` turns
+    PlayerSuspended { player: PlayerId, turns: usize },
+
+    /// A player misbehaved too often and was eliminated from the battle
+    PlayerEliminated { player: PlayerId },
+
+    /// A unit moved to a new location
+    UnitMoved {
+        player: PlayerId,
+        unit: UnitId,
+        from: Coord,
+        to: Coord,
+    },
+
+    /// A unit tried to move onto a tile that cannot be entered
+    MoveBlocked {
+        player: PlayerId,
+        unit: UnitId,
+        location: Coord,
+        direction: Direction,
+    },
+
+    /// A unit died and was removed from the world
+    UnitDied {
+        player: PlayerId,
+        unit: UnitId,
+        location: Coord,
+    },
+
+    /// A unit of a player that died before came back, see `GameRules::respawn`
+    UnitRespawned {
+        player: PlayerId,
+        unit: UnitId,
+        location: Coord,
+    },
+
+    /// A unit placed a marker on the tile it stands on
+    MarkerPlaced {
+        player: PlayerId,
+        unit: UnitId,
+        location: Coord,
+        tag: u8,
+    },
+
+    /// A player made a noise that can be heard `loudness` tiles far
+    NoiseMade {
+        player: PlayerId,
+        location: Coord,
+        loudness: usize,
+    },
+
+    /// Tiles of the map changed, for instance because of a scripted change of the scenario
+    TilesChanged { tiles: Vec<PlayerTile> },
+
+    /// An exit moved to a different location, see `GameRules::moving_exit`
+    ExitMoved { from: Coord, to: Coord },
+
+    /// A unit picked up the item that lay on the tile it stands on
+    ItemPickedUp {
+        player: PlayerId,
+        unit: UnitId,
+        item: ItemId,
+        location: Coord,
+    },
+
+    /// A unit dropped the item it carried, either on purpose or because it died
+    ItemDropped {
+        player: PlayerId,
+        unit: UnitId,
+        item: ItemId,
+        location: Coord,
+    },
+
+    /// A unit carried an item onto a goal tile
+    ItemDelivered {
+        player: PlayerId,
+        unit: UnitId,
+        item: ItemId,
+        location: Coord,
+    },
+
+    /// A unit set up a sensor link to another unit of the same player
+    SensorsLinked {
+        player: PlayerId,
+        unit: UnitId,
+        target: UnitId,
+    },
+
+    /// A unit broke down an obstacle
+    ObstacleDestroyed {
+        player: PlayerId,
+        unit: UnitId,
+        location: Coord,
+    },
+
+    /// A unit stepped onto an exit
+    ExitReached {
+        player: PlayerId,
+        unit: UnitId,
+        location: Coord,
+    },
+
+    /// Something happened that was caused by a plugin. `name` identifies the kind of event,
+    /// `data` is up to the plugin.
+    Custom {
+        name: String,
+        player: Option<PlayerId>,
+        unit: Option<UnitId>,
+        data: String,
+    },
+}
+
+impl GameEvent {
+    /// Returns a short human readable description of the event, `players` contains the names of
+    /// the players indexed by `PlayerId`. Returns `None` for routine events that are not worth
+    /// reporting, like units that moved.
+    pub fn describe(&self, players: &[String]) -> Option<String> {
+        let name = |player: &PlayerId| {
+            players
+                .get(player.0)
+                .cloned()
+                .unwrap_or_else(|| format!("Player {}", player.0))
+        };
+        match self {
+            GameEvent::ActionsSubmitted { .. }
+            | GameEvent::RunnerTimed { .. }
+            | GameEvent::UnitMoved { .. }
+            | GameEvent::MarkerPlaced { .. }
+            | GameEvent::SensorsLinked { .. }
+            | GameEvent::NoiseMade { .. } => None,
+            GameEvent::UnitDied { player, unit, .. } => {
+                Some(format!("{}: unit {} died", name(player), unit.0))
+            }
+            GameEvent::UnitRespawned { player, unit, .. } => {
+                Some(format!("{}: unit {} respawned", name(player), unit.0))
+            }
+            GameEvent::ExitMoved { to, .. } => {
+                Some(format!("The exit moved to ({}, {})", to.x, to.y))
+            }
+            GameEvent::TilesChanged { tiles } => Some(format!("{} tiles changed", tiles.len())),
+            GameEvent::RunnerError { player, error, .. } => {
+                Some(format!("{}: error: {}", name(player), error))
+            }
+            GameEvent::Timeout { player } => Some(format!("{}: timed out", name(player))),
+            GameEvent::InvalidAction { player, error, .. } => {
+                Some(format!("{}: {}", name(player), error))
+            }
+            GameEvent::PlayerSuspended { player, turns } => {
+                Some(format!("{}: suspended for {} turns", name(player), turns))
+            }
+            GameEvent::PlayerEliminated { player } => Some(format!("{}: eliminated", name(player))),
+            GameEvent::MoveBlocked {
+                player,
+                unit,
+                direction,
+                ..
+            } => Some(format!(
+                "{}: unit {} blocked moving {:?}",
+                name(player),
+                unit.0,
+                direction
+            )),
+            GameEvent::ItemPickedUp {
+                player, unit, item, ..
+            } => Some(format!(
+                "{}: unit {} picked up item {}",
+                name(player),
+                unit.0,
+                item.0
+            )),
+            GameEvent::ItemDropped {
+                player, unit, item, ..
+            } => Some(format!(
+                "{}: unit {} dropped item {}",
+                name(player),
+                unit.0,
+                item.0
+            )),
+            GameEvent::ItemDelivered {
+                player, unit, item, ..
+            } => Some(format!(
+                "{}: unit {} delivered item {}",
+                name(player),
+                unit.0,
+                item.0
+            )),
+            GameEvent::ObstacleDestroyed { player, unit, .. } => Some(format!(
+                "{}: unit {} destroyed an obstacle",
+                name(player),
+                unit.0
+            )),
+            GameEvent::ExitReached { player, unit, .. } => Some(format!(
+                "{}: unit {} reached the exit",
+                name(player),
+                unit.0
+            )),
+            GameEvent::Custom { .. } => None,
+        }
+    }
+}
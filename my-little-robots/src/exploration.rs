@@ -0,0 +1,75 @@
+use crate::World;
+use mlr_api::{PlayerId, PlayerWorld, TileType};
+use serde_derive::{Deserialize, Serialize};
+
+/// The tiles of the map a player has seen with its own units during the battle
+#[derive(Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub struct Exploration {
+    pub player: PlayerId,
+
+    /// One bit for every tile of the map, set once the player has seen the tile
+    seen: Vec<u64>,
+}
+
+impl Exploration {
+    /// Returns true if the player has seen the tile with the given index
+    fn has_seen(&self, index: usize) -> bool {
+        self.seen
+            .get(index / 64)
+            .map_or(false, |bits| bits & (1 << (index % 64)) != 0)
+    }
+}
+
+impl World {
+    /// Returns what `player` has explored after seeing `player_world`
+    pub(crate) fn update_exploration(
+        &self,
+        player: PlayerId,
+        player_world: &PlayerWorld,
+    ) -> Exploration {
+        let mut exploration = self
+            .explored
+            .iter()
+            .find(|exploration| exploration.player == player)
+            .cloned()
+            .unwrap_or_else(|| Exploration {
+                player,
+                seen: Vec::new(),
+            });
+        exploration
+            .seen
+            .resize((self.map.width * self.map.height + 63) / 64, 0);
+        for tile in player_world.tiles.iter() {
+            let index = tile.coord.x as usize + tile.coord.y as usize * self.map.width;
+            exploration.seen[index / 64] |= 1 << (index % 64);
+        }
+        exploration
+    }
+
+    /// Returns the percentage of the floor tiles of the map that the given player has seen with
+    /// its own units, rounded down
+    pub fn explored_percentage(&self, player: PlayerId) -> usize {
+        let exploration = match self
+            .explored
+            .iter()
+            .find(|exploration| exploration.player == player)
+        {
+            Some(exploration) => exploration,
+            None => return 0,
+        };
+        let floor = self
+            .map
+            .tiles
+            .iter()
+            .enumerate()
+            .filter(|(_, tile)| **tile == TileType::Floor);
+        let (total, seen) = floor.fold((0, 0), |(total, seen), (index, _)| {
+            (total + 1, seen + exploration.has_seen(index) as usize)
+        });
+        if total == 0 {
+            0
+        } else {
+            seen * 100 / total
+        }
+    }
+}
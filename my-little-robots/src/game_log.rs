@@ -0,0 +1,102 @@
+use crate::{BattleObserver, GameEvent, GameRules, MatchResult, World};
+use serde_derive::Serialize;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+
+/// A single line in the log. Every line records the turn it belongs to together with what
+/// happened, both game events and the records below are tagged with an `event` field.
+#[derive(Serialize)]
+struct LogLine<T> {
+    turn: usize,
+    #[serde(flatten)]
+    record: T,
+}
+
+/// Records written by the log itself, in addition to the events of the game
+#[derive(Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+enum LogRecord<'a> {
+    /// The battle started
+    Start { players: &'a [String], hash: u64 },
+
+    /// The state of the world after a turn
+    WorldHash { hash: u64 },
+
+    /// The battle ended
+    End { result: &'a MatchResult },
+}
+
+/// An observer that writes every event of a battle as a single JSON object per line to a file.
+/// Unlike a replay the log is written while the battle progresses and is meant for grepping and
+/// external analysis. After every turn a hash of the world is logged to make it easy to spot where
+/// two battles diverge.
+pub struct GameLog {
+    path: PathBuf,
+    writer: Option<BufWriter<File>>,
+}
+
+impl GameLog {
+    /// Constructs a log that writes to the specified path
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        GameLog {
+            path: path.into(),
+            writer: None,
+        }
+    }
+
+    /// Writes a single line to the log. If writing fails the log is closed.
+    fn write<T: serde::Serialize>(&mut self, turn: usize, record: T) {
+        if let Some(writer) = &mut self.writer {
+            let result = serde_json::to_writer(&mut *writer, &LogLine { turn, record })
+                .map_err(anyhow::Error::from)
+                .and_then(|_| writer.write_all(b"\n").map_err(anyhow::Error::from));
+            if let Err(err) = result {
+                log::error!("could not write to {}: {}", self.path.display(), err);
+                self.writer = None;
+            }
+        }
+    }
+
+    /// Writes all buffered lines to the file
+    fn flush(&mut self) {
+        if let Some(writer) = &mut self.writer {
+            if let Err(err) = writer.flush() {
+                log::error!("could not write to {}: {}", self.path.display(), err);
+                self.writer = None;
+            }
+        }
+    }
+}
+
+impl BattleObserver for GameLog {
+    fn on_start(&mut self, world: &World, players: &[String], _rules: &GameRules) {
+        match File::create(&self.path) {
+            Ok(file) => self.writer = Some(BufWriter::new(file)),
+            Err(err) => {
+                log::error!("could not create {}: {}", self.path.display(), err);
+                return;
+            }
+        }
+        let hash = world.state_hash();
+        self.write(world.turn, LogRecord::Start { players, hash });
+        self.flush();
+    }
+
+    fn on_turn(&mut self, world: &World, events: &[GameEvent]) {
+        // The world has already advanced to the next turn
+        let turn = world.turn.saturating_sub(1);
+        for event in events {
+            self.write(turn, event);
+        }
+        let hash = world.state_hash();
+        self.write(turn, LogRecord::WorldHash { hash });
+        self.flush();
+    }
+
+    fn on_end(&mut self, world: &World, result: &MatchResult) {
+        self.write(world.turn, LogRecord::End { result });
+        self.flush();
+        self.writer = None;
+    }
+}
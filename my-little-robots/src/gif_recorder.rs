@@ -0,0 +1,77 @@
+use crate::raster::rasterize;
+use crate::{BattleObserver, GameEvent, GameRules, MatchResult, World};
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+
+/// The time every turn is shown in the animation, in hundredths of a second
+const FRAME_DELAY: u16 = 10;
+
+/// The quality/speed trade-off used to quantize frames, 1 is the slowest and 30 the fastest
+const QUANTIZE_SPEED: i32 = 10;
+
+type Encoder = gif::Encoder<BufWriter<File>>;
+
+/// An observer that records a battle to a single animated GIF. Frames are encoded as the battle
+/// progresses so the whole match never has to be kept in memory.
+pub struct GifRecorder {
+    path: PathBuf,
+    encoder: Option<Encoder>,
+}
+
+impl GifRecorder {
+    /// Constructs a recorder that writes the animation to the specified path
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        GifRecorder {
+            path: path.into(),
+            encoder: None,
+        }
+    }
+
+    /// Adds a frame of the world to the animation. If writing fails the recording is stopped.
+    fn add_frame(&mut self, world: &World) {
+        let (width, height, data) = rasterize(&world.spectator_world());
+        if self.encoder.is_none() {
+            match create_encoder(&self.path, width, height) {
+                Ok(encoder) => self.encoder = Some(encoder),
+                Err(err) => {
+                    log::error!("could not create {}: {}", self.path.display(), err);
+                    return;
+                }
+            }
+        }
+
+        if let Some(encoder) = &mut self.encoder {
+            let mut frame =
+                gif::Frame::from_rgb_speed(width as u16, height as u16, &data, QUANTIZE_SPEED);
+            frame.delay = FRAME_DELAY;
+            if let Err(err) = encoder.write_frame(&frame) {
+                log::error!("could not write to {}: {}", self.path.display(), err);
+                self.encoder = None;
+            }
+        }
+    }
+}
+
+/// Creates a looping GIF encoder that writes to `path`
+fn create_encoder(path: &Path, width: usize, height: usize) -> anyhow::Result<Encoder> {
+    let writer = BufWriter::new(File::create(path)?);
+    let mut encoder = gif::Encoder::new(writer, width as u16, height as u16, &[])?;
+    encoder.set_repeat(gif::Repeat::Infinite)?;
+    Ok(encoder)
+}
+
+impl BattleObserver for GifRecorder {
+    fn on_start(&mut self, world: &World, _players: &[String], _rules: &GameRules) {
+        self.add_frame(world);
+    }
+
+    fn on_turn(&mut self, world: &World, _events: &[GameEvent]) {
+        self.add_frame(world);
+    }
+
+    fn on_end(&mut self, _world: &World, _result: &MatchResult) {
+        // Dropping the encoder writes the trailer of the file
+        self.encoder = None;
+    }
+}
@@ -0,0 +1,64 @@
+use crate::World;
+use mlr_api::{Coord, PlayerId, PlayerWorld, Sighting, UnitId};
+use serde_derive::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// The last time a player saw a unit of another player
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub struct LastSeen {
+    /// The player that saw the unit
+    pub observer: PlayerId,
+    pub unit: UnitId,
+    pub player: PlayerId,
+    pub location: Coord,
+
+    /// The turn in which the unit was seen
+    pub turn: usize,
+}
+
+impl World {
+    /// Returns what `observer` knows about the units of other players after seeing `player_world`.
+    /// The units of other players in sight are recorded, earlier sightings at locations that are in
+    /// sight again are forgotten.
+    pub(crate) fn update_sightings(
+        &self,
+        observer: PlayerId,
+        player_world: &PlayerWorld,
+    ) -> Vec<LastSeen> {
+        let visible = player_world
+            .tiles
+            .iter()
+            .map(|tile| tile.coord)
+            .collect::<HashSet<_>>();
+        let mut sightings = self
+            .last_seen
+            .iter()
+            .filter(|seen| seen.observer == observer && !visible.contains(&seen.location))
+            .filter(|seen| player_world.units.iter().all(|unit| unit.id != seen.unit))
+            .copied()
+            .collect::<Vec<_>>();
+        sightings.extend(player_world.visible_enemies(observer).map(|unit| LastSeen {
+            observer,
+            unit: unit.id,
+            player: unit.player,
+            location: unit.location,
+            turn: self.turn,
+        }));
+        sightings.sort_by_key(|seen| seen.unit.0);
+        sightings
+    }
+}
+
+/// Returns the sightings of units that are out of sight during `turn`, as told to the observer
+pub(crate) fn stale_sightings(sightings: &[LastSeen], turn: usize) -> Vec<Sighting> {
+    sightings
+        .iter()
+        .filter(|seen| seen.turn < turn)
+        .map(|seen| Sighting {
+            unit: seen.unit,
+            player: seen.player,
+            location: seen.location,
+            turns_ago: turn - seen.turn,
+        })
+        .collect()
+}
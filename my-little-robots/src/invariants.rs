@@ -0,0 +1,46 @@
+use crate::World;
+
+impl World {
+    /// Asserts that the world is in a consistent state. Panics with a description of the world if
+    /// it is not. Only available with the `debug-invariants` feature, it is meant to catch bugs in
+    /// the engine during development.
+    pub fn check_invariants(&self) {
+        for pair in self.units.windows(2) {
+            assert!(
+                pair[0].id.0 < pair[1].id.0,
+                "unit {:?} is stored before unit {:?} in turn {}: {:#?}",
+                pair[0].id,
+                pair[1].id,
+                self.turn,
+                self.units
+            );
+        }
+        for unit in self.units.iter() {
+            assert!(
+                self.map.in_bounds(unit.location),
+                "unit {:?} of {:?} is out of bounds in turn {}: {:#?}",
+                unit.id,
+                unit.player,
+                self.turn,
+                unit
+            );
+            assert!(
+                self.map.can_enter_tile(unit.location),
+                "unit {:?} of {:?} is standing on a {:?} tile in turn {}: {:#?}",
+                unit.id,
+                unit.player,
+                self.map[unit.location],
+                self.turn,
+                unit
+            );
+            assert!(
+                self.obstacle_at(unit.location).is_none(),
+                "unit {:?} of {:?} is standing on an obstacle in turn {}: {:#?}",
+                unit.id,
+                unit.player,
+                self.turn,
+                unit
+            );
+        }
+    }
+}
@@ -1,98 +1,506 @@
 mod battle;
+#[cfg(feature = "render")]
 pub mod bracket_lib;
+mod campaign;
+mod control;
+mod diff;
+mod environment;
+mod events;
+mod exploration;
+mod game_log;
+mod gif_recorder;
+mod intel;
+#[cfg(feature = "debug-invariants")]
+mod invariants;
 mod map;
+mod map_analysis;
 pub mod map_builder;
+mod map_diff;
+mod noise;
+mod objective;
+mod obstacles;
+mod orders;
+mod palette;
+mod parallel;
+mod penalty;
+mod plugin;
+mod png_renderer;
+mod raster;
+mod replay;
+mod respawn;
+mod rules;
 mod runner;
+mod scenario;
+mod scoreboard;
+mod sensors;
+mod views;
+mod weather;
 
 use async_trait::async_trait;
 use serde_derive::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+use std::time::Duration;
 use thiserror::Error;
 
-pub use self::battle::Battle;
-pub use self::{map::Map, runner::Runner};
+pub use self::battle::{Battle, BattleError, BattleObserver, MatchResult};
+#[cfg(feature = "native")]
+pub use self::runner::{Acquire, CommandOptions, MessageLimits, RunnerPermit, RunnerScheduler};
+pub use self::{
+    campaign::{Campaign, CampaignResult, Level, LevelResult},
+    control::BattleControl,
+    diff::{WorldDiff, WorldUpdate},
+    environment::MapChange,
+    events::GameEvent,
+    exploration::Exploration,
+    game_log::GameLog,
+    gif_recorder::GifRecorder,
+    intel::LastSeen,
+    map::{Axis, Map, TileProperties},
+    map_analysis::MapAnalysis,
+    map_diff::{MapDiff, TileChange},
+    penalty::{PenaltyRules, PlayerPenalties},
+    plugin::{ActionHandler, CustomTile, Plugin, Plugins, ResolutionPhase},
+    png_renderer::PngRenderer,
+    replay::{Replay, ReplayFormat, ReplayRecorder, Verification, REPLAY_VERSION},
+    respawn::{PlayerLives, RespawnRules, SpawnSelection},
+    rules::{GameRules, TieBreak, WinCondition},
+    runner::{BuiltinRunner, Runner},
+    scenario::{MapSource, Scenario, ScenarioObstacle},
+    scoreboard::{PlayerStats, Scoreboard},
+    weather::Weather,
+};
 
-use futures::channel::mpsc::unbounded;
-use futures::{SinkExt, StreamExt};
+use self::control::timed;
+use self::intel::stale_sightings;
+use self::noise::NoiseSource;
+use self::orders::TurnOrders;
+use self::parallel::map_parallel;
+use self::penalty::Penalty;
+use self::views::{UnitView, ViewCache};
 use itertools::Itertools;
 use mlr_api::{
-    Coord, Direction, PlayerAction, PlayerId, PlayerInput, PlayerMemory, PlayerOutput, PlayerTile,
-    PlayerWorld, RunnerError, TileType, Unit, UnitId, API_VERSION,
+    ActionKind, Coord, Direction, Item, ItemId, Marker, MatchStart, Noise, Obstacle, PlayerId,
+    PlayerInput, PlayerMemory, PlayerOutput, PlayerTile, PlayerWorld, RunnerError, SpectatorWorld,
+    TileType, Unit, UnitId, WorldInfo, API_VERSION,
 };
 
 /// A `World` defines the state of the world.
 #[derive(Clone, Eq, Debug, PartialEq, Hash, Serialize, Deserialize)]
 pub struct World {
-    pub map: Map,
+    /// The map is shared between all copies of the world, it is only copied when it changes
+    pub map: Arc<Map>,
     pub units: Vec<Unit>,
     pub turn: usize,
+
+    /// The markers placed by the players, at most one per player per tile
+    #[serde(default)]
+    pub markers: Vec<(PlayerId, Marker)>,
+
+    /// The noises every player heard during the last turn
+    #[serde(default)]
+    pub noises: Vec<(PlayerId, Noise)>,
+
+    /// The tiles that changed during the last turn
+    #[serde(default)]
+    pub changed_tiles: Vec<PlayerTile>,
+
+    /// The objective items that have not been delivered yet
+    #[serde(default)]
+    pub items: Vec<Item>,
+
+    /// The items that were delivered to a goal tile and the players that delivered them
+    #[serde(default)]
+    pub delivered: Vec<(PlayerId, ItemId)>,
+
+    /// Where every player last saw the units of other players
+    #[serde(default)]
+    pub last_seen: Vec<LastSeen>,
+
+    /// The tiles every player has seen during the battle
+    #[serde(default)]
+    pub explored: Vec<Exploration>,
+
+    /// The obstacles that stand on floor tiles, they block movement and vision until they are
+    /// destroyed
+    #[serde(default)]
+    pub obstacles: Vec<Obstacle>,
+
+    /// The sensor links that were set up during the last turn, from the linking unit to the unit
+    /// that sees further
+    #[serde(default)]
+    pub links: Vec<(UnitId, UnitId)>,
+
+    /// The id of the next unit that is spawned. Units are stored ordered by their id.
+    #[serde(default)]
+    next_unit_id: usize,
+
+    /// The noises made during the current turn, see `World::make_noise`
+    #[serde(skip)]
+    pub(crate) noise_sources: Vec<NoiseSource>,
 }
 
 impl Default for World {
     fn default() -> World {
         World {
             //map: map_builder::new_map(80, 50, &mut map_builder::SimpleMapBuilder),
-            map: map_builder::new_map(80, 50, &mut map_builder::PrimMazeBuilder),
+            map: Arc::new(map_builder::new_map(
+                80,
+                50,
+                &mut map_builder::PrimMazeBuilder,
+            )),
             //map: map_builder::new_map(80, 50, &mut map_builder::CellularAutomata),
             units: Vec::new(),
             turn: 0,
+            markers: Vec::new(),
+            noises: Vec::new(),
+            changed_tiles: Vec::new(),
+            items: Vec::new(),
+            delivered: Vec::new(),
+            last_seen: Vec::new(),
+            explored: Vec::new(),
+            obstacles: Vec::new(),
+            links: Vec::new(),
+            next_unit_id: 0,
+            noise_sources: Vec::new(),
         }
     }
 }
 
 impl World {
-    /// Applies the specified `actions` to an instance and returns a modified instance where these
-    /// actions have been applied.
-    fn apply(mut self, actions: impl IntoIterator<Item = Action>) -> Self {
-        for action in actions {
-            match action {
-                Action::Move(unit_id, direction) => {
-                    let unit = &mut self.units[unit_id.0];
+    /// Constructs a world without any units on the given map
+    pub fn new(map: Map) -> World {
+        World {
+            map: Arc::new(map),
+            units: Vec::new(),
+            turn: 0,
+            markers: Vec::new(),
+            noises: Vec::new(),
+            changed_tiles: Vec::new(),
+            items: Vec::new(),
+            delivered: Vec::new(),
+            last_seen: Vec::new(),
+            explored: Vec::new(),
+            obstacles: Vec::new(),
+            links: Vec::new(),
+            next_unit_id: 0,
+            noise_sources: Vec::new(),
+        }
+    }
+
+    /// Applies the validated `orders` of all players to the world in place, in a single pass.
+    /// Afterwards the carried items are delivered, the scripted map changes and the resolution
+    /// phases of the plugins are run and the noises made during the turn are propagated. The
+    /// consequences of the actions are added to `events`.
+    fn apply(
+        &mut self,
+        orders: &[TurnOrders],
+        rules: &GameRules,
+        plugins: &Plugins,
+        events: &mut Vec<GameEvent>,
+    ) {
+        self.changed_tiles.clear();
+        self.links.clear();
+        for action in orders.iter().flat_map(|orders| orders.actions.iter()) {
+            match *action {
+                Action::Move { unit, direction } => {
+                    // The unit may have died earlier during the turn
+                    let unit = match self.unit_index(unit) {
+                        Some(index) => &mut self.units[index],
+                        None => continue,
+                    };
+                    // A unit that carries an item may have moved already this turn
+                    let turn = self.turn;
+                    if self
+                        .items
+                        .iter()
+                        .any(|item| item.carrier == Some(unit.id) && item.next_move > turn)
+                    {
+                        continue;
+                    }
                     let new_location = unit.location + direction;
-                    if self.map.can_enter_tile(new_location) {
+                    if self.map.can_enter_tile(new_location)
+                        && self
+                            .obstacles
+                            .iter()
+                            .all(|obstacle| obstacle.location != new_location)
+                    {
+                        events.push(GameEvent::UnitMoved {
+                            player: unit.player,
+                            unit: unit.id,
+                            from: unit.location,
+                            to: new_location,
+                        });
                         unit.location = new_location;
+                        let (player, id) = (unit.player, unit.id);
+                        if self.map[new_location] == TileType::Exit {
+                            events.push(GameEvent::ExitReached {
+                                player: unit.player,
+                                unit: unit.id,
+                                location: new_location,
+                            });
+                        }
+                        self.carry_item(id, new_location, rules.carry_slowdown);
+                        self.make_noise(player, new_location, rules.move_noise);
+                    } else {
+                        events.push(GameEvent::MoveBlocked {
+                            player: unit.player,
+                            unit: unit.id,
+                            location: unit.location,
+                            direction,
+                        });
+                    }
+                }
+                Action::Mark { unit, tag } => {
+                    let unit = match self.unit(unit) {
+                        Some(unit) => unit,
+                        None => continue,
+                    };
+                    let (player, unit, location) = (unit.player, unit.id, unit.location);
+                    self.place_marker(
+                        player,
+                        Marker {
+                            coord: location,
+                            tag,
+                        },
+                    );
+                    events.push(GameEvent::MarkerPlaced {
+                        player,
+                        unit,
+                        location,
+                        tag,
+                    });
+                }
+                Action::PickUp { unit } => self.pick_up(unit, events),
+                Action::Drop { unit } => self.drop_item(unit, events),
+                Action::Link { unit, target } => self.link_sensors(unit, target, rules, events),
+                Action::Break { unit, direction } => self.break_obstacle(unit, direction, events),
+                Action::Custom {
+                    unit,
+                    ref name,
+                    ref data,
+                } => {
+                    // Validation made sure the handler exists
+                    if self.unit(unit).is_none() {
+                        continue;
+                    }
+                    if let Some(handler) = plugins.action(name) {
+                        handler.apply(unit, data, self, events);
                     }
                 }
             }
         }
-        self
+        self.deliver_items(events);
+        self.apply_environment(rules, events);
+        plugins.resolve(self, events);
+        if !self.changed_tiles.is_empty() {
+            Arc::make_mut(&mut self.map).compute_distance_to_exit();
+        }
+        self.propagate_noise(events);
+
+        #[cfg(feature = "debug-invariants")]
+        self.check_invariants();
     }
 
-    /// Creates a snapshot of the world as seen by the given Player.
-    fn player_world(&self, player_id: PlayerId) -> PlayerWorld {
-        let player_units = self
+    /// Creates a snapshot of the world as seen by the given Player whose units can see as far as
+    /// the rules allow this turn, further if other units linked to them. The snapshot contains all
+    /// the units of the player and the units of other players and the items that are on a visible
+    /// tile. The field of view of every unit is computed in parallel, from its field of view in
+    /// `previous` when that still applies. The fields of view are returned for the next turn.
+    fn player_world(
+        &self,
+        player_id: PlayerId,
+        rules: &GameRules,
+        previous: &ViewCache,
+    ) -> (PlayerWorld, Vec<UnitView>) {
+        let vision_range = rules.vision_range_at(self.turn);
+        let obstacles = self
+            .obstacles
+            .iter()
+            .map(|obstacle| obstacle.location)
+            .collect::<HashSet<_>>();
+        let own_units = self
             .units
             .iter()
             .filter(|unit| unit.player == player_id)
+            .collect::<Vec<_>>();
+        let previous = if previous.applies_to(self) {
+            Some(previous)
+        } else {
+            None
+        };
+        let views = map_parallel(own_units, |unit| {
+            let range = self.linked_vision_range(unit.id, vision_range, rules);
+            let is_opaque = |coord: Coord| obstacles.contains(&coord);
+            let visible = match previous.and_then(|previous| previous.view(unit.id, range)) {
+                Some(view) => self.map.field_of_view_from_previous(
+                    view.location,
+                    &view.visible,
+                    unit.location,
+                    range as isize,
+                    is_opaque,
+                ),
+                None => self
+                    .map
+                    .field_of_view_with(unit.location, range as isize, is_opaque),
+            };
+            UnitView {
+                unit: unit.id,
+                location: unit.location,
+                range,
+                visible,
+            }
+        });
+        let visible_tiles: HashSet<Coord> = views
+            .iter()
+            .flat_map(|view| view.visible.iter().copied())
+            .collect();
+
+        let units = self
+            .units
+            .iter()
+            .filter(|unit| unit.player == player_id || visible_tiles.contains(&unit.location))
             .cloned()
             .collect_vec();
 
-        let tiles = player_units
+        let items = self
+            .items
             .iter()
-            .map(|unit| self.map.field_of_view(unit.location, 7))
-            .flatten()
+            .filter(|item| visible_tiles.contains(&item.location))
+            .cloned()
+            .collect();
+
+        let obstacles = self
+            .obstacles
+            .iter()
+            .filter(|obstacle| visible_tiles.contains(&obstacle.location))
+            .copied()
+            .collect();
+
+        // The tiles are sorted so that players get the same input every time the same turn is
+        // played, regardless of the order in which the set happens to iterate them
+        let tiles = visible_tiles
+            .into_iter()
+            .sorted_by_key(|coord| (coord.y, coord.x))
             .map(|coord| PlayerTile {
                 coord,
                 tile_type: self.map[coord],
             })
             .collect();
 
-        PlayerWorld {
-            units: player_units,
+        let markers = self.markers_of(player_id).copied().collect();
+
+        let player_world = PlayerWorld {
+            units,
             tiles,
+            markers,
+            items,
+            sightings: Vec::new(),
+            obstacles,
+        };
+        (player_world, views)
+    }
+
+    /// Creates a snapshot of the complete world for spectators, everything is visible. Unlike the
+    /// `World` itself the snapshot only consists of api types.
+    pub fn spectator_world(&self) -> SpectatorWorld {
+        SpectatorWorld {
+            turn: self.turn,
+            width: self.map.width,
+            height: self.map.height,
+            tiles: self.map.tiles.clone(),
+            units: self.units.clone(),
+            items: self.items.clone(),
+            obstacles: self.obstacles.clone(),
         }
     }
 
-    /// Spawns a unit in the world
+    /// Places a marker of `player`, replacing the marker the player had on the same tile
+    pub fn place_marker(&mut self, player: PlayerId, marker: Marker) {
+        match self
+            .markers
+            .iter_mut()
+            .find(|(p, m)| *p == player && m.coord == marker.coord)
+        {
+            Some((_, existing)) => *existing = marker,
+            None => self.markers.push((player, marker)),
+        }
+    }
+
+    /// Returns all markers placed by the given player
+    pub fn markers_of(&self, player: PlayerId) -> impl Iterator<Item = &Marker> {
+        self.markers
+            .iter()
+            .filter(move |(p, _)| *p == player)
+            .map(|(_, marker)| marker)
+    }
+
+    /// Spawns a unit in the world. Every unit gets a new id, ids of units that died are never
+    /// reused.
     pub fn spawn_unit(&mut self, player: PlayerId, location: Coord) -> UnitId {
-        let id = UnitId(self.units.len());
-        self.units.push(Unit {
-            id,
-            player,
-            location,
-        });
+        let next_id = self.units.last().map_or(0, |unit| unit.id.0 + 1);
+        let id = UnitId(self.next_unit_id.max(next_id));
+        self.next_unit_id = id.0 + 1;
+        self.units.push(Unit::new(id, player, location));
         id
     }
 
+    /// Returns the index in `units` of the unit with the given id, if it is alive
+    fn unit_index(&self, id: UnitId) -> Option<usize> {
+        self.units
+            .binary_search_by_key(&id.0, |unit| unit.id.0)
+            .ok()
+    }
+
+    /// Returns the unit with the given id, if it is alive
+    pub fn unit(&self, id: UnitId) -> Option<&Unit> {
+        self.unit_index(id).map(|index| &self.units[index])
+    }
+
+    /// Returns the unit with the given id for modification, if it is alive
+    pub fn unit_mut(&mut self, id: UnitId) -> Option<&mut Unit> {
+        let index = self.unit_index(id)?;
+        Some(&mut self.units[index])
+    }
+
+    /// Removes a unit from the world, the item it carried is dropped where it died. Returns the unit
+    /// if it was still alive.
+    pub fn kill_unit(&mut self, id: UnitId, events: &mut Vec<GameEvent>) -> Option<Unit> {
+        self.drop_item(id, events);
+        let index = self.unit_index(id)?;
+        let unit = self.units.remove(index);
+        events.push(GameEvent::UnitDied {
+            player: unit.player,
+            unit: unit.id,
+            location: unit.location,
+        });
+        Some(unit)
+    }
+
+    /// Returns the information about the world and the rules that is shared with every player
+    fn world_info(&self, rules: &GameRules) -> WorldInfo {
+        WorldInfo {
+            width: self.map.width,
+            height: self.map.height,
+            vision_range: rules.vision_range_at(self.turn),
+            weather: rules.weather_at(self.turn),
+            max_turns: rules.max_turns,
+            actions: rules.actions.clone(),
+            action_points: rules.action_points,
+            action_costs: rules.action_costs,
+        }
+    }
+
+    /// Returns a hash of the complete state of the world, including the map. Two worlds with the
+    /// same hash can be considered identical.
+    pub fn state_hash(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.hash(&mut hasher);
+        hasher.finish()
+    }
+
     /// Returns the units that are currently standing on an exit
     pub fn units_on_exits(&self) -> impl Iterator<Item = &Unit> {
         self.units
@@ -104,7 +512,33 @@ impl World {
 /// Describes an action in the world which may have been undertaken by any player
 #[derive(Clone, Eq, PartialEq, Debug, Hash)]
 enum Action {
-    Move(UnitId, Direction),
+    Move {
+        unit: UnitId,
+        direction: Direction,
+    },
+    Mark {
+        unit: UnitId,
+        tag: u8,
+    },
+    PickUp {
+        unit: UnitId,
+    },
+    Drop {
+        unit: UnitId,
+    },
+    Link {
+        unit: UnitId,
+        target: UnitId,
+    },
+    Break {
+        unit: UnitId,
+        direction: Direction,
+    },
+    Custom {
+        unit: UnitId,
+        name: String,
+        data: String,
+    },
 }
 
 /// The PlayerRunner can be implemented to produce actions for a current snapshot of the world.
@@ -112,6 +546,26 @@ enum Action {
 pub trait PlayerRunner: Send {
     /// Given the current state of the world, returns the actions that should be executed.
     async fn run(&mut self, input: PlayerInput) -> Result<PlayerOutput, RunnerError>;
+
+    /// Waits until the runner may run, called before every `run`. The time spent waiting is not
+    /// counted as time the runner took. Runners that share a limited resource, like the processes
+    /// that may run at the same time, wait for their share here.
+    async fn ready(&mut self) {}
+
+    /// Lets the runner prepare for the battle before the first turn, within `timeout`. Only called
+    /// when the rules give players time to warm up, see `GameRules::warm_up`. Only the memory of
+    /// the output is used. Runners that have nothing to prepare keep the memory they are given.
+    async fn start_match(
+        &mut self,
+        start: MatchStart,
+        _timeout: Duration,
+    ) -> Result<PlayerOutput, RunnerError> {
+        Ok(PlayerOutput {
+            actions: Vec::new(),
+            memory: start.memory,
+            version: None,
+        })
+    }
 }
 
 // Implement `PlayerRunner` for a functions
@@ -130,103 +584,437 @@ pub struct Player {
     /// The unique id of this player
     pub id: PlayerId,
 
+    /// The human readable name of this player
+    pub name: String,
+
     /// The function to generate actions from the current state of the world
     pub runner: Box<dyn PlayerRunner>,
 
     /// The current player memory
     pub memory: PlayerMemory,
+
+    /// The misbehavior of the player so far and the penalties that apply
+    pub penalties: PlayerPenalties,
+
+    /// The units of the player that died and respawned so far
+    pub lives: PlayerLives,
 }
 
 /// Represents the current game state
 pub struct GameState {
     pub players: Vec<Player>,
     pub world: World,
+    pub rules: GameRules,
+    pub plugins: Plugins,
+
+    /// The locations where the players started the battle, indexed by `PlayerId`. Units respawn
+    /// at these locations.
+    pub spawns: Vec<Coord>,
+
+    /// The fields of view of the units during the previous turn
+    pub(crate) views: ViewCache,
 }
 
 impl GameState {
-    pub async fn turn(mut self) -> Self {
-        let (action_sender, action_receiver) = unbounded();
+    /// Sends every player a `MatchStart` and waits for all of them to prepare for the battle,
+    /// if the rules give them time to warm up. Players that fail to prepare in time are not
+    /// punished, they start the first turn with the memory they had.
+    pub async fn start_match(&mut self) {
+        let timeout = match self.rules.warm_up {
+            Some(millis) => Duration::from_millis(millis),
+            None => return,
+        };
         let world_ref = &self.world;
+        let rules = &self.rules;
+        let world_info = world_ref.world_info(rules);
+        let world_info = &world_info;
+        let no_views = ViewCache::default();
+        let no_views = &no_views;
+        futures::future::join_all(self.players.iter_mut().map(|player| async move {
+            let (world, _) = world_ref.player_world(player.id, rules, no_views);
+            let start = MatchStart {
+                version: API_VERSION,
+                player_id: player.id,
+                world_info: world_info.clone(),
+                world,
+                memory: player.memory.clone(),
+            };
+            player.runner.ready().await;
+            let result = player
+                .runner
+                .start_match(start, timeout)
+                .await
+                .and_then(check_api_version)
+                .and_then(|output| {
+                    check_memory_size(output, &player.memory, rules.max_memory_size)
+                });
+            match result {
+                Ok(output) => player.memory = output.memory,
+                Err(err) => log::warn!("{} failed to warm up: {}", player.name, err),
+            }
+        }))
+        .await;
+    }
+
+    /// Plays a single turn, updates the game state in place and returns the events that occurred
+    /// during the turn.
+    pub async fn turn(&mut self) -> Vec<GameEvent> {
+        let world_ref = &self.world;
+        let rules = &self.rules;
+        let world_info = self.world.world_info(rules);
+        let world_info = &world_info;
         let turn = self.world.turn;
-        let player_iter_fut = futures::stream::iter(self.players.iter_mut()).for_each_concurrent(
-            None,
-            move |player| {
-                let mut action_sender = action_sender.clone();
-                async move {
-                    // Construct the input for the player
-                    let player_input = PlayerInput {
-                        version: API_VERSION,
-                        player_id: player.id,
-                        turn,
-                        world: world_ref.player_world(player.id),
-                        memory: player.memory.clone(),
-                    };
 
-                    // Run the player runner
-                    let player_result = player.runner.run(player_input).await;
+        // Players that are suspended or eliminated sit out the turn
+        let active = self
+            .players
+            .iter_mut()
+            .map(|player| player.penalties.take_turn())
+            .collect::<Vec<_>>();
 
-                    // Check the output for errors
-                    let output = match player_result {
-                        Err(err) => {
-                            log::error!("Player {:?}: {}", player.id, err);
-                            return;
-                        }
-                        Ok(output) => output,
-                    };
+        // Determine what every player can see up front, in parallel
+        let active_ids = self
+            .players
+            .iter()
+            .filter(|player| active[player.id.0])
+            .map(|player| player.id)
+            .collect::<Vec<_>>();
+        let previous_views = &self.views;
+        let (player_worlds, intel): (Vec<_>, Vec<_>) = map_parallel(active_ids, |player| {
+            let (mut player_world, views) = world_ref.player_world(player, rules, previous_views);
+            let sightings = world_ref.update_sightings(player, &player_world);
+            let exploration = world_ref.update_exploration(player, &player_world);
+            player_world.sightings = stale_sightings(&sightings, turn);
+            (player_world, ((sightings, exploration), views))
+        })
+        .into_iter()
+        .unzip();
+        let (intel, views): (Vec<_>, Vec<_>) = intel.into_iter().unzip();
+        let (sightings, explored): (Vec<_>, Vec<_>) = intel.into_iter().unzip();
+        let views = ViewCache::new(world_ref, views.into_iter().flatten());
 
-                    // Validate all the actions
-                    for player_action in output.actions {
-                        match validate_action(player_action, player.id, world_ref) {
-                            Err(err) => {
-                                log::error!("Player {:?}: invalid action: {}", player.id, err);
-                            }
-                            Ok(action) => {
-                                action_sender
-                                    .send(action)
-                                    .await
-                                    .expect("error sending action");
-                            }
-                        }
-                    }
+        // Run all players concurrently, the outputs are collected per player in the order of the
+        // players regardless of which runner finishes first
+        let active_players = self.players.iter_mut().filter(|player| active[player.id.0]);
+        let outputs = futures::future::join_all(active_players.zip(player_worlds).map(
+            |(player, player_world)| async move {
+                // Construct the input for the player
+                let player_input = PlayerInput {
+                    version: API_VERSION,
+                    player_id: player.id,
+                    turn,
+                    world_info: world_info.clone(),
+                    world: player_world,
+                    memory: player.memory.clone(),
+                    noises: world_ref.noises_heard_by(player.id),
+                    changed_tiles: world_ref.changed_tiles.clone(),
+                };
 
-                    // Store the memory of the player
+                // Run the player runner once it may run
+                player.runner.ready().await;
+                let (player_result, elapsed) = timed(player.runner.run(player_input)).await;
+                let player_result = player_result
+                    .and_then(check_api_version)
+                    .map(|output| reuse_unchanged_memory(output, &player.memory))
+                    .and_then(|output| {
+                        check_memory_size(output, &player.memory, rules.max_memory_size)
+                    });
+
+                // Store the memory of the player
+                let actions = player_result.map(|output| {
                     player.memory = output.memory;
-                }
+                    output.actions
+                });
+                (player.id, actions, elapsed)
             },
-        );
+        ))
+        .await;
+
+        // Remember which units of other players every player saw
+        self.views = views;
+        self.world.last_seen.retain(|seen| !active[seen.observer.0]);
+        self.world.last_seen.extend(sightings.into_iter().flatten());
+        self.world
+            .explored
+            .retain(|exploration| !active[exploration.player.0]);
+        self.world.explored.extend(explored);
+        self.world
+            .explored
+            .sort_by_key(|exploration| exploration.player.0);
+
+        // Check the output for errors
+        let mut events = Vec::new();
+        let mut submitted = Vec::with_capacity(outputs.len());
+        let mut runner_errors = vec![0; self.players.len()];
+        let mut invalid_actions = vec![0; self.players.len()];
+        for (player, result, elapsed) in outputs {
+            events.push(GameEvent::RunnerTimed {
+                player,
+                micros: elapsed.as_micros() as u64,
+            });
+            match result {
+                Err(err) => {
+                    runner_errors[player.0] += 1;
+                    let name = &self.players[player.0].name;
+                    log::error!("{}: E{}: {}", name, err.code(), err);
+                    events.push(match err {
+                        RunnerError::Timeout(_) => GameEvent::Timeout { player },
+                        err => GameEvent::RunnerError {
+                            player,
+                            code: err.code(),
+                            error: err.to_string(),
+                        },
+                    });
+                }
+                Ok(actions) => {
+                    events.push(GameEvent::ActionsSubmitted {
+                        player,
+                        actions: actions.clone(),
+                    });
+                    submitted.push((player, actions));
+                }
+            }
+        }
+
+        // Validate the actions of all players in parallel
+        let world_ref = &self.world;
+        let plugins = &self.plugins;
+        let orders = map_parallel(submitted, |(player, actions)| {
+            TurnOrders::validate(player, actions, world_ref, rules, plugins)
+        });
+        for turn_orders in orders.iter() {
+            let name = &self.players[turn_orders.player.0].name;
+            invalid_actions[turn_orders.player.0] += turn_orders.rejected.len();
+            for rejected in turn_orders.rejected.iter() {
+                log::error!(
+                    "{}: invalid action {} (E{}): {}",
+                    name,
+                    rejected.index,
+                    rejected.error.code(),
+                    rejected.error
+                );
+                events.push(GameEvent::InvalidAction {
+                    player: turn_orders.player,
+                    index: rejected.index,
+                    action: rejected.action.clone(),
+                    code: rejected.error.code(),
+                    error: rejected.error.clone(),
+                });
+            }
+        }
 
-        let gather_actions_fut = action_receiver.collect::<Vec<_>>();
-        let (_, actions) = futures::future::join(player_iter_fut, gather_actions_fut).await;
-        self.world = self.world.apply(actions);
+        // Penalize players that misbehaved
+        for player in self.players.iter_mut() {
+            let penalty = player.penalties.record(
+                invalid_actions[player.id.0],
+                runner_errors[player.id.0],
+                &rules.penalties,
+            );
+            match penalty {
+                Some(Penalty::SkipTurns) => {
+                    log::warn!("{}: suspended", player.name);
+                    events.push(GameEvent::PlayerSuspended {
+                        player: player.id,
+                        turns: rules.penalties.skip_turns,
+                    });
+                }
+                Some(Penalty::Eliminate) => {
+                    log::warn!("{}: eliminated", player.name);
+                    events.push(GameEvent::PlayerEliminated { player: player.id });
+                }
+                None => {}
+            }
+        }
+
+        // Resolve all orders at once, eliminated players no longer have any say
+        let players = &self.players;
+        let orders = orders
+            .into_iter()
+            .filter(|orders| !players[orders.player.0].penalties.eliminated)
+            .collect::<Vec<_>>();
+        self.world
+            .apply(&orders, &self.rules, &self.plugins, &mut events);
+        self.respawn_units(&mut events);
         self.world.turn += 1;
 
-        self
+        events
+    }
+
+    /// Returns the players that have not been eliminated and still have units or have units
+    /// waiting to respawn
+    pub fn remaining_players(&self) -> impl Iterator<Item = PlayerId> + '_ {
+        let units = &self.world.units;
+        self.players
+            .iter()
+            .filter(|player| !player.penalties.eliminated)
+            .filter(move |player| {
+                player.lives.is_respawning() || units.iter().any(|unit| unit.player == player.id)
+            })
+            .map(|player| player.id)
     }
 }
 
 /// An error that might occur when a user sends an action that is not possible.
-#[derive(Error, Clone, Debug)]
+#[derive(Error, Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[serde(tag = "error", rename_all = "snake_case")]
 pub enum ActionValidationError {
-    #[error("Invalid action")]
-    InvalidAction(String),
+    #[error("unit {} does not exist", .unit.0)]
+    UnknownUnit { unit: UnitId },
+
+    #[error("unit {} is owned by another player", .unit.0)]
+    NotOwned { unit: UnitId },
+
+    #[error("{kind:?} actions are not allowed by the rules of the game")]
+    NotAllowed { kind: ActionKind },
+
+    #[error("there is no action named '{name}'")]
+    UnknownAction { name: String },
+
+    #[error("unit {} cannot perform '{name}': {reason}", .unit.0)]
+    Rejected {
+        unit: UnitId,
+        name: String,
+        reason: String,
+    },
+
+    #[error("unit {} needs {cost} action points but only has {remaining} left this turn", .unit.0)]
+    OverBudget {
+        unit: UnitId,
+        cost: usize,
+        remaining: usize,
+    },
+
+    #[error("unit {} carries an item and cannot move again before turn {until}", .unit.0)]
+    Encumbered { unit: UnitId, until: usize },
+
+    #[error("there is no item for unit {} to pick up", .unit.0)]
+    NothingToPickUp { unit: UnitId },
+
+    #[error("unit {} does not carry an item", .unit.0)]
+    NotCarrying { unit: UnitId },
+
+    #[error("unit {} needs {needed} energy but only has {available} left", .unit.0)]
+    NotEnoughEnergy {
+        unit: UnitId,
+        needed: u32,
+        available: u32,
+    },
+
+    #[error("there is no obstacle next to unit {} to break", .unit.0)]
+    NothingToBreak { unit: UnitId },
 }
 
-/// Given an action from a player turn it into an action that can be applied to the world. Returns
-/// an error if the action cannot be performed by the player.
-fn validate_action(
-    action: PlayerAction,
-    player: PlayerId,
-    world: &World,
-) -> Result<Action, ActionValidationError> {
-    match action {
-        PlayerAction::Move { unit, direction } => {
-            if world.units[unit.0].player != player {
-                Err(ActionValidationError::InvalidAction(
-                    "action points to invalid unit".to_string(),
-                ))
-            } else {
-                Ok(Action::Move(unit, direction))
-            }
+impl ActionValidationError {
+    /// Returns a number that identifies the kind of error. Codes are stable, they never change
+    /// meaning and are never reused. They don't overlap with the codes of `RunnerError`.
+    pub fn code(&self) -> u16 {
+        match self {
+            ActionValidationError::UnknownUnit { .. } => 100,
+            ActionValidationError::NotOwned { .. } => 101,
+            ActionValidationError::NotAllowed { .. } => 102,
+            ActionValidationError::UnknownAction { .. } => 103,
+            ActionValidationError::Rejected { .. } => 104,
+            ActionValidationError::OverBudget { .. } => 105,
+            ActionValidationError::Encumbered { .. } => 106,
+            ActionValidationError::NothingToPickUp { .. } => 107,
+            ActionValidationError::NotCarrying { .. } => 108,
+            ActionValidationError::NotEnoughEnergy { .. } => 109,
+            ActionValidationError::NothingToBreak { .. } => 110,
+        }
+    }
+
+    /// Returns the unit the rejected action was ordered to, if any
+    pub fn unit(&self) -> Option<UnitId> {
+        match self {
+            ActionValidationError::UnknownUnit { unit }
+            | ActionValidationError::NotOwned { unit }
+            | ActionValidationError::Rejected { unit, .. }
+            | ActionValidationError::OverBudget { unit, .. }
+            | ActionValidationError::Encumbered { unit, .. }
+            | ActionValidationError::NothingToPickUp { unit }
+            | ActionValidationError::NotCarrying { unit }
+            | ActionValidationError::NotEnoughEnergy { unit, .. }
+            | ActionValidationError::NothingToBreak { unit } => Some(*unit),
+            ActionValidationError::NotAllowed { .. }
+            | ActionValidationError::UnknownAction { .. } => None,
         }
     }
 }
+
+/// Rejects the output of a program that was written against a different version of the api
+fn check_api_version(output: PlayerOutput) -> Result<PlayerOutput, RunnerError> {
+    match output.version {
+        Some(version) if version != API_VERSION => Err(RunnerError::VersionMismatch {
+            expected: API_VERSION,
+            actual: version,
+        }),
+        _ => Ok(output),
+    }
+}
+
+/// Replaces the memory returned by a program with the memory of the previous turn if the contents
+/// did not change. This keeps sharing the previous allocation so unchanged memory is never copied
+/// or measured again.
+fn reuse_unchanged_memory(mut output: PlayerOutput, previous: &PlayerMemory) -> PlayerOutput {
+    if !output.memory.ptr_eq(previous) && output.memory == *previous {
+        output.memory = previous.clone();
+    }
+    output
+}
+
+/// Rejects the output of a program that returned more memory than allowed. Memory that was passed
+/// through unchanged is always accepted.
+fn check_memory_size(
+    output: PlayerOutput,
+    previous: &PlayerMemory,
+    limit: usize,
+) -> Result<PlayerOutput, RunnerError> {
+    if output.memory.ptr_eq(previous) {
+        return Ok(output);
+    }
+    let size = output.memory.size();
+    if size > limit {
+        Err(RunnerError::MemoryLimitExceeded { size, limit })
+    } else {
+        Ok(output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn output(memory: PlayerMemory) -> PlayerOutput {
+        PlayerOutput {
+            actions: Vec::new(),
+            memory,
+            version: None,
+        }
+    }
+
+    #[test]
+    fn memory_up_to_the_limit_is_accepted() {
+        let previous = PlayerMemory::default();
+        let memory = PlayerMemory::from(vec![0; 100]);
+        let limit = memory.size();
+        assert!(check_memory_size(output(memory.clone()), &previous, limit).is_ok());
+        assert!(matches!(
+            check_memory_size(output(memory), &previous, limit - 1),
+            Err(RunnerError::MemoryLimitExceeded { size, limit: l }) if size == limit && l == limit - 1
+        ));
+    }
+
+    #[test]
+    fn bytes_are_measured_as_they_are_sent() {
+        let previous = PlayerMemory::default();
+        let memory = PlayerMemory::from(vec![0; 100]);
+        assert!(check_memory_size(output(memory), &previous, 100).is_err());
+    }
+
+    #[test]
+    fn unchanged_memory_is_accepted_past_the_limit() {
+        let previous = PlayerMemory::from(vec![0; 100]);
+        let output = reuse_unchanged_memory(output(PlayerMemory::from(vec![0; 100])), &previous);
+        assert!(check_memory_size(output, &previous, 10).is_ok());
+    }
+}
@@ -1,33 +1,51 @@
 use super::Coord;
-use bracket_lib::prelude::{field_of_view_set, Algorithm2D, BaseMap, Point};
-use mlr_api::TileType;
+use itertools::Itertools;
+use mlr_api::{Direction, TileType};
 use serde_derive::{Deserialize, Serialize};
-use std::collections::HashSet;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BinaryHeap, HashSet, VecDeque};
 use std::ops::{Index, IndexMut};
 
+/// The axis across which a map is mirrored, see [`Map::mirrored`]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Axis {
+    /// Swaps the left and the right side of the map
+    Horizontal,
+
+    /// Swaps the top and the bottom of the map
+    Vertical,
+}
+
+/// The walking distance fair spawn points try to keep between each other, see
+/// [`Map::fair_spawn_points`]
+const FAIR_SPAWN_SEPARATION: usize = 10;
+
+/// The size of a single tile in images produced by [`Map::to_svg`]
+const SVG_TILE_SIZE: usize = 10;
+
 #[derive(Debug, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
 pub struct Map {
     pub width: usize,
     pub height: usize,
     pub(crate) tiles: Vec<TileType>,
     pub(crate) distance_to_exit: Vec<Option<usize>>,
-}
 
-impl BaseMap for Map {
-    fn is_opaque(&self, idx: usize) -> bool {
-        self.tiles[idx as usize] == TileType::Wall
-    }
+    /// Extra data attached to individual tiles, see `Map::set_property`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) properties: Vec<TileProperties>,
 }
 
-impl Algorithm2D for Map {
-    fn dimensions(&self) -> Point {
-        Point::new(self.width, self.height)
-    }
+/// Key/value pairs attached to a single tile of a map. Scenarios and plugins use them to store
+/// data about a tile that the engine itself does not know about, like the name of a region or
+/// the player a spawn belongs to. Values are strings, it is up to the reader to parse them.
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TileProperties {
+    pub coord: Coord,
+    pub values: BTreeMap<String, String>,
 }
 
-#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
-pub struct MapCoord(usize);
-
 impl Map {
     pub fn new(width: usize, height: usize) -> Map {
         Map {
@@ -35,6 +53,7 @@ impl Map {
             height,
             tiles: vec![TileType::Floor; width * height],
             distance_to_exit: vec![None; width * height],
+            properties: Vec::new(),
         }
     }
 
@@ -44,6 +63,7 @@ impl Map {
             height,
             tiles: vec![TileType::Wall; width * height],
             distance_to_exit: vec![None; width * height],
+            properties: Vec::new(),
         }
     }
 
@@ -60,18 +80,539 @@ impl Map {
         self.in_bounds(position) && self[position].can_enter()
     }
 
+    /// Returns the value of a property of the tile at the given location
+    pub fn property(&self, position: Coord, key: &str) -> Option<&str> {
+        self.properties(position)
+            .and_then(|values| values.get(key))
+            .map(String::as_str)
+    }
+
+    /// Returns all the properties of the tile at the given location
+    pub fn properties(&self, position: Coord) -> Option<&BTreeMap<String, String>> {
+        self.properties
+            .iter()
+            .find(|tile| tile.coord == position)
+            .map(|tile| &tile.values)
+    }
+
+    /// Attaches a property to the tile at the given location, replacing the previous value of the
+    /// property. Properties are serialized with the map and survive changes to the tile type.
+    pub fn set_property(
+        &mut self,
+        position: Coord,
+        key: impl Into<String>,
+        value: impl Into<String>,
+    ) -> Option<String> {
+        let index = match self
+            .properties
+            .iter()
+            .position(|tile| tile.coord == position)
+        {
+            Some(index) => index,
+            None => {
+                self.properties.push(TileProperties {
+                    coord: position,
+                    values: BTreeMap::new(),
+                });
+                self.properties.len() - 1
+            }
+        };
+        self.properties[index]
+            .values
+            .insert(key.into(), value.into())
+    }
+
+    /// Removes a property from the tile at the given location, returns the previous value
+    pub fn remove_property(&mut self, position: Coord, key: &str) -> Option<String> {
+        let index = self
+            .properties
+            .iter()
+            .position(|tile| tile.coord == position)?;
+        let value = self.properties[index].values.remove(key);
+        if self.properties[index].values.is_empty() {
+            self.properties.remove(index);
+        }
+        value
+    }
+
+    /// Returns the locations of all tiles that have the given property, together with its value
+    pub fn tiles_with_property<'a>(
+        &'a self,
+        key: &'a str,
+    ) -> impl Iterator<Item = (Coord, &'a str)> + 'a {
+        self.properties.iter().filter_map(move |tile| {
+            tile.values
+                .get(key)
+                .map(|value| (tile.coord, value.as_str()))
+        })
+    }
+
     pub fn get_distance_to_exit<T: Into<Coord>>(&self, position: T) -> Option<usize> {
         let coord = position.into();
         let index = coord.x as usize + coord.y as usize * self.width;
         self.distance_to_exit[index]
     }
 
+    /// Computes the walking distance from every tile to the closest exit.
+    pub fn compute_distance_to_exit(&mut self) {
+        let mut distances = vec![None; self.width * self.height];
+        let mut queue = VecDeque::new();
+        for (index, tile) in self.tiles.iter().enumerate() {
+            if *tile == TileType::Exit {
+                distances[index] = Some(0);
+                queue.push_back(index);
+            }
+        }
+
+        // Breadth first search from all exits
+        while let Some(index) = queue.pop_front() {
+            let coord = Coord::new(index % self.width, index / self.width);
+            let distance = distances[index].expect("queued tiles have a distance");
+            for neighbor in coord.neighbors() {
+                if !self.can_enter_tile(neighbor) {
+                    continue;
+                }
+                let neighbor_index = neighbor.x as usize + neighbor.y as usize * self.width;
+                if distances[neighbor_index].is_none() {
+                    distances[neighbor_index] = Some(distance + 1);
+                    queue.push_back(neighbor_index);
+                }
+            }
+        }
+
+        self.distance_to_exit = distances;
+    }
+
+    /// Picks up to `count` distinct floor tiles to spawn units on, spread out as far from each other
+    /// as possible. Tiles from which an exit can be reached are preferred. Returns fewer locations
+    /// if the map does not have enough floor tiles.
+    pub fn spawn_points(&self, count: usize) -> Vec<Coord> {
+        let floor = (0..self.width * self.height)
+            .map(|index| Coord::new(index % self.width, index / self.width))
+            .filter(|&coord| self[coord] == TileType::Floor)
+            .collect::<Vec<_>>();
+        let reachable = floor
+            .iter()
+            .copied()
+            .filter(|&coord| self.get_distance_to_exit(coord).is_some())
+            .collect::<Vec<_>>();
+        let candidates = if reachable.len() >= count {
+            reachable
+        } else {
+            floor
+        };
+        self.spread_out(&candidates, count)
+    }
+
+    /// Picks `count` floor tiles to spawn units on like [`Map::spawn_points`], but only tiles
+    /// whose walking distances to the closest exit differ by at most `tolerance`, so no player
+    /// starts closer to an exit than the others. Tiles far from the exits and spawn points that
+    /// lie far apart are preferred. Returns `None` if the map does not have enough such tiles.
+    pub fn fair_spawn_points(&self, count: usize, tolerance: usize) -> Option<Vec<Coord>> {
+        let reachable = (0..self.width * self.height)
+            .map(|index| Coord::new(index % self.width, index / self.width))
+            .filter(|&coord| self[coord] == TileType::Floor)
+            .filter_map(|coord| self.get_distance_to_exit(coord).map(|d| (coord, d)))
+            .collect::<Vec<_>>();
+        let mut lowest_distances = reachable.iter().map(|&(_, d)| d).collect::<Vec<_>>();
+        lowest_distances.sort_unstable();
+        lowest_distances.dedup();
+
+        // Try every window of distances, starting furthest from the exits, until the spawn
+        // points in the window are far enough apart
+        let mut best: Option<(usize, Vec<Coord>)> = None;
+        for &lowest in lowest_distances.iter().rev() {
+            let candidates = reachable
+                .iter()
+                .filter(|&&(_, distance)| distance >= lowest && distance <= lowest + tolerance)
+                .map(|&(coord, _)| coord)
+                .collect::<Vec<_>>();
+            if candidates.len() < count {
+                continue;
+            }
+            let spawns = self.spread_out(&candidates, count);
+            let separation = spawns
+                .iter()
+                .tuple_combinations()
+                .map(|(a, b)| a.manhattan_distance(*b))
+                .min()
+                .unwrap_or(usize::MAX);
+            if separation >= FAIR_SPAWN_SEPARATION {
+                return Some(spawns);
+            }
+            if best.as_ref().map_or(true, |(best, _)| separation > *best) {
+                best = Some((separation, spawns));
+            }
+        }
+        best.map(|(_, spawns)| spawns)
+    }
+
+    /// Picks up to `count` of the candidates, spread out as far from each other as possible. The
+    /// candidate furthest away from the exits is picked first.
+    fn spread_out(&self, candidates: &[Coord], count: usize) -> Vec<Coord> {
+        // Start as far away from the exits as possible, then keep adding the candidate that is
+        // furthest away from all spawn points so far
+        let mut spawns = Vec::with_capacity(count);
+        let mut distances = vec![usize::MAX; candidates.len()];
+        let mut next = candidates
+            .iter()
+            .enumerate()
+            .max_by_key(|(_, &coord)| self.get_distance_to_exit(coord).unwrap_or(0))
+            .map(|(index, _)| index);
+        while let Some(index) = next.filter(|_| spawns.len() < count) {
+            let spawn = candidates[index];
+            spawns.push(spawn);
+            for (distance, candidate) in distances.iter_mut().zip(candidates.iter()) {
+                *distance = (*distance).min(candidate.manhattan_distance(spawn));
+            }
+            next = distances
+                .iter()
+                .enumerate()
+                .filter(|(_, &distance)| distance > 0)
+                .max_by_key(|(_, &distance)| distance)
+                .map(|(index, _)| index);
+        }
+        spawns
+    }
+
+    /// Returns a copy of the map flipped across the given axis. Exits and the properties of tiles
+    /// move along with the tiles.
+    pub fn mirrored(&self, axis: Axis) -> Map {
+        let (width, height) = (self.width as isize, self.height as isize);
+        self.transformed(self.width, self.height, |coord| match axis {
+            Axis::Horizontal => Coord::new(width - 1 - coord.x, coord.y),
+            Axis::Vertical => Coord::new(coord.x, height - 1 - coord.y),
+        })
+    }
+
+    /// Returns a copy of the map rotated clockwise by the given number of quarter turns. The width
+    /// and the height of the map swap places after an odd number of turns.
+    pub fn rotated(&self, quarter_turns: usize) -> Map {
+        let (width, height) = (self.width as isize, self.height as isize);
+        match quarter_turns % 4 {
+            0 => self.clone(),
+            1 => self.transformed(self.height, self.width, |coord| {
+                Coord::new(height - 1 - coord.y, coord.x)
+            }),
+            2 => self.transformed(self.width, self.height, |coord| {
+                Coord::new(width - 1 - coord.x, height - 1 - coord.y)
+            }),
+            _ => self.transformed(self.height, self.width, |coord| {
+                Coord::new(coord.y, width - 1 - coord.x)
+            }),
+        }
+    }
+
+    /// Moves every tile of the map to the location returned by `to` on a new map of the given size
+    fn transformed(&self, width: usize, height: usize, to: impl Fn(Coord) -> Coord) -> Map {
+        let mut map = Map::new_closed(width, height);
+        for (index, tile) in self.tiles.iter().enumerate() {
+            map[to(Coord::new(index % self.width, index / self.width))] = *tile;
+        }
+        map.properties = self
+            .properties
+            .iter()
+            .map(|tile| TileProperties {
+                coord: to(tile.coord),
+                values: tile.values.clone(),
+            })
+            .collect();
+        map.compute_distance_to_exit();
+        map
+    }
+
+    /// Returns a copy of the map in which the top left quarter is repeated in the other quarters,
+    /// mirrored so that the map looks the same from every corner. The exits are replaced by one
+    /// exit per quarter on the floor tile closest to the center of the map. Properties of tiles
+    /// are not copied.
+    pub fn symmetric(&self) -> Map {
+        let mut map = self.clone();
+        map.properties.clear();
+        for tile in map.tiles.iter_mut().filter(|tile| **tile == TileType::Exit) {
+            *tile = TileType::Floor;
+        }
+        let (half_width, half_height) = ((self.width + 1) / 2, (self.height + 1) / 2);
+        let quarters = [
+            map.mirrored(Axis::Horizontal),
+            map.mirrored(Axis::Vertical),
+            map.rotated(2),
+        ];
+        for index in 0..self.width * self.height {
+            let (x, y) = (index % self.width, index / self.width);
+            let quarter = match (x < half_width, y < half_height) {
+                (true, true) => continue,
+                (false, true) => &quarters[0],
+                (true, false) => &quarters[1],
+                (false, false) => &quarters[2],
+            };
+            map.tiles[index] = quarter.tiles[index];
+        }
+
+        let center = Coord::new(self.width / 2, self.height / 2);
+        let exit = (0..half_width * half_height)
+            .map(|index| Coord::new(index % half_width, index / half_width))
+            .filter(|&coord| map[coord] == TileType::Floor)
+            .min_by_key(|coord| coord.manhattan_distance(center));
+        if let Some(exit) = exit {
+            for mirror in map.mirrors(exit).iter() {
+                map[*mirror] = TileType::Exit;
+            }
+        }
+        map.compute_distance_to_exit();
+        map
+    }
+
+    /// Returns a spawn point in every quarter of a map created with `Map::symmetric`, the first
+    /// two lie in opposite corners. The spawn points are the same tile as seen from each corner,
+    /// as far from the exits as possible.
+    pub fn symmetric_spawns(&self) -> Vec<Coord> {
+        let (half_width, half_height) = ((self.width + 1) / 2, (self.height + 1) / 2);
+        let spawn = (0..half_width * half_height)
+            .map(|index| Coord::new(index % half_width, index / half_width))
+            .filter(|&coord| self[coord] == TileType::Floor)
+            .filter_map(|coord| self.get_distance_to_exit(coord).map(|d| (coord, d)))
+            .max_by_key(|&(_, distance)| distance)
+            .map(|(coord, _)| coord);
+        match spawn {
+            Some(spawn) => self.mirrors(spawn).iter().copied().unique().collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Returns the tile mirrored into every quarter of the map: the tile itself, the tile in the
+    /// opposite corner and the tiles mirrored horizontally and vertically
+    fn mirrors(&self, coord: Coord) -> [Coord; 4] {
+        let (x, y) = (
+            self.width as isize - 1 - coord.x,
+            self.height as isize - 1 - coord.y,
+        );
+        [
+            coord,
+            Coord::new(x, y),
+            Coord::new(x, coord.y),
+            Coord::new(coord.x, y),
+        ]
+    }
+
+    /// Finds the shortest walking path from `from` to `to` with A*, using the manhattan distance as
+    /// heuristic. Returns the directions to walk in, `None` if `to` cannot be reached.
+    pub fn find_path(&self, from: Coord, to: Coord) -> Option<Vec<Direction>> {
+        if !self.in_bounds(from) || !self.can_enter_tile(to) {
+            return None;
+        }
+        let index = |coord: Coord| coord.x as usize + coord.y as usize * self.width;
+        let mut costs = vec![usize::MAX; self.width * self.height];
+        let mut came_from: Vec<Option<Direction>> = vec![None; self.width * self.height];
+        let mut open = BinaryHeap::new();
+        costs[index(from)] = 0;
+        open.push(Reverse((from.manhattan_distance(to), 0, from.x, from.y)));
+
+        while let Some(Reverse((_, cost, x, y))) = open.pop() {
+            let current = Coord::new(x, y);
+            if current == to {
+                let mut path = Vec::with_capacity(cost);
+                let mut current = to;
+                while let Some(direction) = came_from[index(current)] {
+                    path.push(direction);
+                    current += direction.opposite();
+                }
+                path.reverse();
+                return Some(path);
+            }
+            if cost > costs[index(current)] {
+                continue;
+            }
+            for direction in Direction::all_directions() {
+                let next = current + direction;
+                if !self.can_enter_tile(next) || cost + 1 >= costs[index(next)] {
+                    continue;
+                }
+                costs[index(next)] = cost + 1;
+                came_from[index(next)] = Some(direction);
+                open.push(Reverse((
+                    cost + 1 + next.manhattan_distance(to),
+                    cost + 1,
+                    next.x,
+                    next.y,
+                )));
+            }
+        }
+        None
+    }
+
     /// Returns all the coordinates that can be seen from the given location and within the given range
     pub fn field_of_view(&self, position: Coord, range: isize) -> HashSet<Coord> {
-        field_of_view_set(Point::new(position.x, position.y), range as i32, self)
-            .into_iter()
-            .map(|p| Coord::new(p.x, p.y))
-            .collect()
+        self.field_of_view_with(position, range, |_| false)
+    }
+
+    /// Returns all the coordinates that can be seen from the given location and within the given
+    /// range, when apart from walls also the tiles for which `is_opaque` returns true block the
+    /// view. Like walls those tiles are visible themselves.
+    pub fn field_of_view_with(
+        &self,
+        position: Coord,
+        range: isize,
+        is_opaque: impl Fn(Coord) -> bool,
+    ) -> HashSet<Coord> {
+        let mut visible = HashSet::new();
+        if !self.in_bounds(position) {
+            return visible;
+        }
+
+        // Cast a ray to every tile on the edge of the square that encloses the range
+        for i in -range..=range {
+            for &(dx, dy) in [(i, -range), (i, range), (-range, i), (range, i)].iter() {
+                let target = Coord::new(position.x + dx, position.y + dy);
+                self.cast_ray(position, target, range, &is_opaque, &mut visible);
+            }
+        }
+
+        visible
+    }
+
+    /// Returns the same coordinates as `field_of_view_with` for `position`, given the field of view
+    /// `previous` that was computed at `previous_position` on this map with the same range and
+    /// opaque tiles. Instead of casting all rays again the previous field of view is returned as is
+    /// when the unit did not move, and shifted along when the unit took a single step while no
+    /// wall or opaque tile is in view before or after the step.
+    pub fn field_of_view_from_previous(
+        &self,
+        previous_position: Coord,
+        previous: &HashSet<Coord>,
+        position: Coord,
+        range: isize,
+        is_opaque: impl Fn(Coord) -> bool,
+    ) -> HashSet<Coord> {
+        if position == previous_position {
+            return previous.clone();
+        }
+
+        // Without anything that blocks the view, every ray runs up to the range so the field of
+        // view looks the same at every position. That only holds if the map does not cut off any
+        // of the rays either.
+        let (dx, dy) = (
+            position.x - previous_position.x,
+            position.y - previous_position.y,
+        );
+        let blocks = |coord: Coord, origin: Coord| {
+            self[coord] == TileType::Wall || (coord != origin && is_opaque(coord))
+        };
+        let single_step = dx.abs() <= 1 && dy.abs() <= 1;
+        let in_range_of_bounds = |coord: Coord| {
+            self.in_bounds(Coord::new(coord.x - range, coord.y - range))
+                && self.in_bounds(Coord::new(coord.x + range, coord.y + range))
+        };
+        if single_step
+            && in_range_of_bounds(previous_position)
+            && in_range_of_bounds(position)
+            && !previous
+                .iter()
+                .any(|&coord| blocks(coord, previous_position))
+        {
+            let shifted = previous
+                .iter()
+                .map(|coord| Coord::new(coord.x + dx, coord.y + dy))
+                .collect::<HashSet<_>>();
+            if !shifted.iter().any(|&coord| blocks(coord, position)) {
+                return shifted;
+            }
+        }
+
+        self.field_of_view_with(position, range, is_opaque)
+    }
+
+    /// Walks the line from `from` to `to` and adds every tile that is within `range` of `from` to
+    /// `visible`. The ray stops at the first wall or opaque tile, which is still visible itself.
+    fn cast_ray(
+        &self,
+        from: Coord,
+        to: Coord,
+        range: isize,
+        is_opaque: &impl Fn(Coord) -> bool,
+        visible: &mut HashSet<Coord>,
+    ) {
+        let (dx, dy) = ((to.x - from.x).abs(), -(to.y - from.y).abs());
+        let (step_x, step_y) = ((to.x - from.x).signum(), (to.y - from.y).signum());
+        let mut error = dx + dy;
+        let mut current = from;
+        loop {
+            let (offset_x, offset_y) = (current.x - from.x, current.y - from.y);
+            if offset_x * offset_x + offset_y * offset_y > range * range || !self.in_bounds(current)
+            {
+                return;
+            }
+            visible.insert(current);
+            if self[current] == TileType::Wall
+                || current == to
+                || (current != from && is_opaque(current))
+            {
+                return;
+            }
+
+            // Take the next step along the line using Bresenham's algorithm
+            let double_error = 2 * error;
+            if double_error >= dy {
+                error += dy;
+                current.x += step_x;
+            }
+            if double_error <= dx {
+                error += dx;
+                current.y += step_y;
+            }
+        }
+    }
+
+    /// Draws the map as text, one line per row, in the format scenarios use. Custom tiles are drawn
+    /// as `?` since their symbols are only known to the plugins that registered them.
+    pub fn to_text(&self) -> String {
+        let mut text = String::with_capacity((self.width + 1) * self.height);
+        for row in self.tiles.chunks(self.width.max(1)) {
+            text.extend(row.iter().map(|tile| match tile {
+                TileType::Wall => '#',
+                TileType::Floor => '.',
+                TileType::Exit => 'E',
+                TileType::Goal => 'G',
+                TileType::Custom { .. } => '?',
+            }));
+            text.push('\n');
+        }
+        text
+    }
+
+    /// Renders the map as an SVG image where every tile is a square of `SVG_TILE_SIZE` units.
+    /// Horizontal runs of equal tiles are merged into a single rectangle to keep the output small.
+    pub fn to_svg(&self) -> String {
+        let mut svg = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\" shape-rendering=\"crispEdges\">\n",
+            w = self.width * SVG_TILE_SIZE,
+            h = self.height * SVG_TILE_SIZE
+        );
+        for y in 0..self.height {
+            let row = &self.tiles[y * self.width..(y + 1) * self.width];
+            let mut x = 0;
+            while x < self.width {
+                let tile = row[x];
+                let run = row[x..].iter().take_while(|t| **t == tile).count();
+                let color = match tile {
+                    TileType::Wall => "#c8c8c8",
+                    TileType::Floor => "#202020",
+                    TileType::Exit => "#00c8c8",
+                    TileType::Goal => "#c8a000",
+                    TileType::Custom { .. } => "#c800c8",
+                };
+                svg.push_str(&format!(
+                    "  <rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\"/>\n",
+                    x * SVG_TILE_SIZE,
+                    y * SVG_TILE_SIZE,
+                    run * SVG_TILE_SIZE,
+                    SVG_TILE_SIZE,
+                    color
+                ));
+                x += run;
+            }
+        }
+        svg.push_str("</svg>\n");
+        svg
     }
 }
 
@@ -92,3 +633,110 @@ impl<T: Into<Coord>> IndexMut<T> for Map {
         &mut self.tiles[index]
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map_builder::BuilderKind;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    /// Returns a map that is not square and looks different from every side
+    fn lopsided_map() -> Map {
+        let mut map = Map::new(5, 3);
+        map[Coord::new(0, 0)] = TileType::Exit;
+        map[Coord::new(3, 1)] = TileType::Wall;
+        map[Coord::new(4, 2)] = TileType::Goal;
+        map.set_property(Coord::new(1, 2), "region", "south");
+        map.compute_distance_to_exit();
+        map
+    }
+
+    #[test]
+    fn rotating_swaps_width_and_height() {
+        let map = lopsided_map();
+        let rotated = map.rotated(1);
+        assert_eq!((rotated.width, rotated.height), (3, 5));
+        assert_eq!(rotated[Coord::new(2, 0)], TileType::Exit);
+        assert_eq!(rotated[Coord::new(1, 3)], TileType::Wall);
+        assert_eq!(rotated[Coord::new(0, 4)], TileType::Goal);
+        assert_eq!(rotated.property(Coord::new(0, 1), "region"), Some("south"));
+        assert_eq!((map.rotated(2).width, map.rotated(2).height), (5, 3));
+        assert_eq!((map.rotated(3).width, map.rotated(3).height), (3, 5));
+    }
+
+    #[test]
+    fn four_quarter_turns_are_the_identity() {
+        let map = lopsided_map();
+        let turned = (0..4).fold(map.clone(), |map, _| map.rotated(1));
+        assert_eq!(turned, map);
+        assert_eq!(map.rotated(4), map);
+        assert_eq!(map.rotated(1).rotated(3), map);
+        assert_eq!(map.rotated(2).rotated(2), map);
+    }
+
+    #[test]
+    fn mirroring_twice_is_the_identity() {
+        let map = lopsided_map();
+        for &axis in [Axis::Horizontal, Axis::Vertical].iter() {
+            let mirrored = map.mirrored(axis);
+            assert_eq!((mirrored.width, mirrored.height), (5, 3));
+            assert_ne!(mirrored, map);
+            assert_eq!(mirrored.mirrored(axis), map);
+        }
+        assert_eq!(
+            map.mirrored(Axis::Horizontal).mirrored(Axis::Vertical),
+            map.rotated(2)
+        );
+    }
+
+    /// Walks a unit across `map` and checks that the field of view derived from the previous one
+    /// is the same as the field of view computed from scratch at every step
+    fn check_field_of_view_walk(map: &Map, range: isize, is_opaque: impl Fn(Coord) -> bool) {
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut position = (0..map.width * map.height)
+            .map(|index| Coord::new(index % map.width, index / map.width))
+            .find(|&coord| map.can_enter_tile(coord) && !is_opaque(coord))
+            .expect("the map has a floor tile");
+        let mut visible = map.field_of_view_with(position, range, &is_opaque);
+        for _ in 0..500 {
+            let previous_position = position;
+            let direction = Direction::all_directions()[rng.gen_range(0, 4)];
+            if rng.gen_range(0, 5) > 0 && map.can_enter_tile(position + direction) {
+                position += direction;
+            }
+            visible = map.field_of_view_from_previous(
+                previous_position,
+                &visible,
+                position,
+                range,
+                &is_opaque,
+            );
+            assert_eq!(
+                visible,
+                map.field_of_view_with(position, range, &is_opaque),
+                "field of view differs after moving from {:?} to {:?}",
+                previous_position,
+                position
+            );
+        }
+    }
+
+    #[test]
+    fn field_of_view_from_previous_matches_a_full_recompute() {
+        for (seed, builder) in BuilderKind::all().into_iter().enumerate() {
+            let map = builder.new_seeded_map(40, 40, seed as u64);
+            check_field_of_view_walk(&map, 2, |_| false);
+        }
+    }
+
+    #[test]
+    fn field_of_view_from_previous_matches_a_full_recompute_near_obstacles() {
+        let mut map = Map::new(30, 30);
+        for &(x, y) in [(12, 12), (13, 12), (20, 5), (5, 22), (25, 25)].iter() {
+            map[Coord::new(x, y)] = TileType::Wall;
+        }
+        let opaque = [Coord::new(16, 16), Coord::new(8, 8), Coord::new(22, 14)];
+        check_field_of_view_walk(&map, 3, |coord| opaque.contains(&coord));
+    }
+}
@@ -0,0 +1,154 @@
+use crate::Map;
+use mlr_api::{Coord, TileType};
+use serde_derive::{Deserialize, Serialize};
+
+/// Measures of the layout of a map, used to judge the quality of generated maps
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MapAnalysis {
+    /// The number of tiles that can be entered
+    pub floor_tiles: usize,
+
+    /// The fraction of all tiles that can be entered
+    pub openness: f32,
+
+    /// The fraction of the tiles that can be entered that have exactly two neighbors that can be
+    /// entered, the tiles of corridors
+    pub corridor_ratio: f32,
+
+    /// The number of tiles that can be entered from a single neighbor only, exits excluded
+    pub dead_ends: usize,
+
+    /// The number of tiles that split the map in two when they are blocked
+    pub choke_points: usize,
+
+    /// The average walking distance to the closest exit over all tiles from which an exit can be
+    /// reached, `None` if no exit can be reached at all
+    pub average_exit_distance: Option<f32>,
+
+    /// The number of tiles that can be entered but from which no exit can be reached
+    pub unreachable_tiles: usize,
+}
+
+impl MapAnalysis {
+    /// Analyzes the given map
+    pub fn new(map: &Map) -> MapAnalysis {
+        let floor = (0..map.width * map.height)
+            .map(|index| Coord::new(index % map.width, index / map.width))
+            .filter(|&coord| map.can_enter_tile(coord))
+            .collect::<Vec<_>>();
+        let neighbor_counts = floor
+            .iter()
+            .map(|coord| {
+                let count = coord
+                    .neighbors()
+                    .filter(|&neighbor| map.can_enter_tile(neighbor))
+                    .count();
+                (coord, count)
+            })
+            .collect::<Vec<_>>();
+        let corridors = neighbor_counts
+            .iter()
+            .filter(|(_, count)| *count == 2)
+            .count();
+        let dead_ends = neighbor_counts
+            .iter()
+            .filter(|(coord, count)| *count == 1 && map[**coord] != TileType::Exit)
+            .count();
+        let distances = floor
+            .iter()
+            .filter_map(|&coord| map.get_distance_to_exit(coord))
+            .collect::<Vec<_>>();
+
+        MapAnalysis {
+            floor_tiles: floor.len(),
+            openness: ratio(floor.len(), map.width * map.height),
+            corridor_ratio: ratio(corridors, floor.len()),
+            dead_ends,
+            choke_points: count_choke_points(map),
+            average_exit_distance: if distances.is_empty() {
+                None
+            } else {
+                Some(distances.iter().sum::<usize>() as f32 / distances.len() as f32)
+            },
+            unreachable_tiles: floor.len() - distances.len(),
+        }
+    }
+
+    /// Returns true if the map is fit to play on: an exit can be reached from most of the tiles
+    /// that can be entered
+    pub fn is_playable(&self) -> bool {
+        self.floor_tiles > 0 && self.unreachable_tiles * 2 < self.floor_tiles
+    }
+}
+
+/// Returns `part / total`, or zero if `total` is zero
+fn ratio(part: usize, total: usize) -> f32 {
+    if total == 0 {
+        0.0
+    } else {
+        part as f32 / total as f32
+    }
+}
+
+/// Counts the articulation points of the graph of tiles that can be entered with an iterative
+/// version of Tarjan's algorithm, recursion would overflow the stack on large open maps
+fn count_choke_points(map: &Map) -> usize {
+    let width = map.width;
+    let neighbors = |index: usize| {
+        Coord::new(index % width, index / width)
+            .neighbors()
+            .filter(|&coord| map.can_enter_tile(coord))
+            .map(|coord| coord.x as usize + coord.y as usize * width)
+            .collect::<Vec<_>>()
+    };
+
+    // The order in which tiles are discovered, starting at 1 so 0 means undiscovered
+    let mut discovered = vec![0; width * map.height];
+    let mut low = vec![0; width * map.height];
+    let mut is_choke_point = vec![false; width * map.height];
+    let mut time = 0;
+    for root in 0..width * map.height {
+        if discovered[root] != 0 || !map.can_enter_tile(Coord::new(root % width, root / width)) {
+            continue;
+        }
+        time += 1;
+        discovered[root] = time;
+        low[root] = time;
+        let mut root_children = 0;
+
+        // Every frame holds a tile, its parent, its neighbors and the next neighbor to visit
+        let mut stack = vec![(root, root, neighbors(root), 0)];
+        while let Some(frame) = stack.last_mut() {
+            let (tile, parent) = (frame.0, frame.1);
+            if let Some(&next) = frame.2.get(frame.3) {
+                frame.3 += 1;
+                if next == parent {
+                    continue;
+                }
+                if discovered[next] == 0 {
+                    time += 1;
+                    discovered[next] = time;
+                    low[next] = time;
+                    if tile == root {
+                        root_children += 1;
+                    }
+                    stack.push((next, tile, neighbors(next), 0));
+                } else {
+                    low[tile] = low[tile].min(discovered[next]);
+                }
+            } else {
+                stack.pop();
+                if tile != root {
+                    low[parent] = low[parent].min(low[tile]);
+                    if parent != root && low[tile] >= discovered[parent] {
+                        is_choke_point[parent] = true;
+                    }
+                }
+            }
+        }
+        if root_children > 1 {
+            is_choke_point[root] = true;
+        }
+    }
+    is_choke_point.into_iter().filter(|&choke| choke).count()
+}
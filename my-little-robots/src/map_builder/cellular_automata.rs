@@ -3,11 +3,9 @@ use rand::Rng;
 
 pub struct CellularAutomata;
 impl MapBuilder for CellularAutomata {
-    fn build<T: SnapshotableMap>(&mut self, map: &mut T) {
-        let mut rng = rand::thread_rng();
-
+    fn build<T: SnapshotableMap, R: Rng>(&mut self, map: &mut T, rng: &mut R) {
         // First we completely randomize the map, setting 55% of it to be floor.
-        map.with_snapshot(|map| {
+        map.with_snapshot("randomized the map", |map| {
             for y in 1..map.height - 1 {
                 for x in 1..map.width - 1 {
                     let coord = (x, y);
@@ -22,8 +20,8 @@ impl MapBuilder for CellularAutomata {
         });
 
         // Now we iteratively apply cellular automata rules
-        for _i in 0..15 {
-            map.with_snapshot(|map| {
+        for i in 0..15 {
+            map.with_snapshot(&format!("applied the rules, pass {}/15", i + 1), |map| {
                 let mut newtiles = map.clone();
 
                 for y in 1..map.height - 1 {
@@ -0,0 +1,90 @@
+use super::BuilderKind;
+use crate::Map;
+
+/// A named map from the library of curated maps. A library map is generated with a fixed builder,
+/// size and seed so it is the same every time, which lets tournaments run on known maps.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct LibraryMap {
+    /// The name by which the map is selected, e.g. `--map library:crossroads`
+    pub name: &'static str,
+
+    /// A short description of the map
+    pub description: &'static str,
+
+    pub builder: BuilderKind,
+    pub width: usize,
+    pub height: usize,
+    pub seed: u64,
+
+    /// True if the generated map is made symmetric, see `Map::symmetric`. Symmetric maps give up
+    /// to 4 players the same start, see `Map::symmetric_spawns`.
+    pub symmetric: bool,
+}
+
+/// All the maps in the library
+pub const LIBRARY: &[LibraryMap] = &[
+    LibraryMap {
+        name: "crossroads",
+        description: "a symmetric maze with the exits in the middle",
+        builder: BuilderKind::PrimMaze,
+        width: 81,
+        height: 51,
+        seed: 0x5eed_0001,
+        symmetric: true,
+    },
+    LibraryMap {
+        name: "caverns",
+        description: "symmetric caves with the exits in the middle",
+        builder: BuilderKind::CellularAutomata,
+        width: 80,
+        height: 50,
+        seed: 0x5eed_0002,
+        symmetric: true,
+    },
+    LibraryMap {
+        name: "catacombs",
+        description: "a symmetric maze with rooms, for battles between up to 4 players",
+        builder: BuilderKind::MazeWithRooms,
+        width: 81,
+        height: 51,
+        seed: 0x5eed_0003,
+        symmetric: true,
+    },
+    LibraryMap {
+        name: "plaza",
+        description: "a symmetric open room scattered with walls",
+        builder: BuilderKind::Simple,
+        width: 60,
+        height: 40,
+        seed: 0x5eed_0004,
+        symmetric: true,
+    },
+    LibraryMap {
+        name: "labyrinth",
+        description: "a large maze with a single exit",
+        builder: BuilderKind::PrimMaze,
+        width: 120,
+        height: 70,
+        seed: 0x5eed_0005,
+        symmetric: false,
+    },
+];
+
+impl LibraryMap {
+    /// Returns the library map with the given name
+    pub fn find(name: &str) -> Option<&'static LibraryMap> {
+        LIBRARY.iter().find(|map| map.name == name)
+    }
+
+    /// Generates the map
+    pub fn build(&self) -> Map {
+        let map = self
+            .builder
+            .new_seeded_map(self.width, self.height, self.seed);
+        if self.symmetric {
+            map.symmetric()
+        } else {
+            map
+        }
+    }
+}
@@ -1,19 +1,46 @@
 use super::Map;
 
 mod cellular_automata;
+mod library;
 mod prim;
+mod rooms;
 mod snapshot;
 
 use mlr_api::{Coord, Direction, TileType};
-use rand::Rng;
-pub use snapshot::{MapWithSnapshots, SnapshotableMap};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+pub use snapshot::{MapWithSnapshots, Snapshot, SnapshotableMap};
+use std::str::FromStr;
+use thiserror::Error;
 
 pub use cellular_automata::CellularAutomata;
+pub use library::{LibraryMap, LIBRARY};
 pub use prim::PrimMazeBuilder;
+pub use rooms::RoomCarver;
 
 pub fn new_map<B: MapBuilder>(width: usize, height: usize, builder: &mut B) -> Map {
+    new_map_with_rng(width, height, builder, &mut rand::thread_rng())
+}
+
+/// Constructs a map with the given builder that is the same every time the same seed is used
+pub fn new_seeded_map<B: MapBuilder>(
+    width: usize,
+    height: usize,
+    builder: &mut B,
+    seed: u64,
+) -> Map {
+    new_map_with_rng(width, height, builder, &mut StdRng::seed_from_u64(seed))
+}
+
+fn new_map_with_rng<B: MapBuilder, R: Rng>(
+    width: usize,
+    height: usize,
+    builder: &mut B,
+    rng: &mut R,
+) -> Map {
     let mut map = Map::new_closed(width, height);
-    builder.build(&mut map);
+    builder.build(&mut map, rng);
+    map.compute_distance_to_exit();
     map
 }
 
@@ -21,24 +48,132 @@ pub fn new_map_with_history<B: MapBuilder>(
     width: usize,
     height: usize,
     builder: &mut B,
-) -> Vec<Map> {
+) -> MapWithSnapshots {
     let mut map: MapWithSnapshots = Map::new_closed(width, height).into();
-    builder.build(&mut map);
-    map.into()
+    builder.build(&mut map, &mut rand::thread_rng());
+    map.with_snapshot("computed the distance to the exits", |map| {
+        map.compute_distance_to_exit()
+    });
+    map
 }
 
 pub trait MapBuilder {
-    /// Constructs a map
-    fn build<T: SnapshotableMap>(&mut self, map: &mut T);
+    /// Constructs a map, all random choices are made with `rng`
+    fn build<T: SnapshotableMap, R: Rng>(&mut self, map: &mut T, rng: &mut R);
+}
+
+/// Builders are combined by running them one after the other on the same map, each builder
+/// continues with the map the previous one left behind
+impl<A: MapBuilder, B: MapBuilder> MapBuilder for (A, B) {
+    fn build<T: SnapshotableMap, R: Rng>(&mut self, map: &mut T, rng: &mut R) {
+        self.0.build(map, rng);
+        self.1.build(map, rng);
+    }
+}
+
+/// A maze with rooms carved around some of its junctions
+pub type MazeWithRoomsBuilder = (PrimMazeBuilder, RoomCarver);
+
+/// Enumerates the available map builders so they can be selected by name
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum BuilderKind {
+    Simple,
+    PrimMaze,
+    CellularAutomata,
+    MazeWithRooms,
+}
+
+impl BuilderKind {
+    /// Returns all available map builders
+    pub fn all() -> Vec<BuilderKind> {
+        vec![
+            BuilderKind::Simple,
+            BuilderKind::PrimMaze,
+            BuilderKind::CellularAutomata,
+            BuilderKind::MazeWithRooms,
+        ]
+    }
+
+    /// Returns the name by which this builder can be selected
+    pub fn name(self) -> &'static str {
+        match self {
+            BuilderKind::Simple => "simple",
+            BuilderKind::PrimMaze => "prim",
+            BuilderKind::CellularAutomata => "cellular",
+            BuilderKind::MazeWithRooms => "rooms",
+        }
+    }
+
+    /// Returns a short description of the maps this builder produces
+    pub fn description(self) -> &'static str {
+        match self {
+            BuilderKind::Simple => "a large open room with random obstacles and an exit in a wall",
+            BuilderKind::PrimMaze => "a maze generated with Prim's algorithm",
+            BuilderKind::CellularAutomata => "organic looking caves generated by cellular automata",
+            BuilderKind::MazeWithRooms => "a maze with rooms carved around some of its junctions",
+        }
+    }
+
+    /// Constructs a map with this builder
+    pub fn new_map(self, width: usize, height: usize) -> Map {
+        self.new_seeded_map(width, height, rand::random())
+    }
+
+    /// Constructs a map with this builder that is the same every time the same seed is used
+    pub fn new_seeded_map(self, width: usize, height: usize, seed: u64) -> Map {
+        match self {
+            BuilderKind::Simple => new_seeded_map(width, height, &mut SimpleMapBuilder, seed),
+            BuilderKind::PrimMaze => new_seeded_map(width, height, &mut PrimMazeBuilder, seed),
+            BuilderKind::CellularAutomata => {
+                new_seeded_map(width, height, &mut CellularAutomata, seed)
+            }
+            BuilderKind::MazeWithRooms => {
+                new_seeded_map(width, height, &mut maze_with_rooms(), seed)
+            }
+        }
+    }
+
+    /// Constructs a map with this builder and returns all intermediate versions of the map
+    pub fn new_map_with_history(self, width: usize, height: usize) -> MapWithSnapshots {
+        match self {
+            BuilderKind::Simple => new_map_with_history(width, height, &mut SimpleMapBuilder),
+            BuilderKind::PrimMaze => new_map_with_history(width, height, &mut PrimMazeBuilder),
+            BuilderKind::CellularAutomata => {
+                new_map_with_history(width, height, &mut CellularAutomata)
+            }
+            BuilderKind::MazeWithRooms => {
+                new_map_with_history(width, height, &mut maze_with_rooms())
+            }
+        }
+    }
+}
+
+/// Constructs a maze builder with the default rooms
+pub fn maze_with_rooms() -> MazeWithRoomsBuilder {
+    (PrimMazeBuilder, RoomCarver::default())
+}
+
+/// An error that is returned when a map builder is requested that doesn't exist
+#[derive(Error, Debug)]
+#[error("unknown map builder {0:?}")]
+pub struct UnknownBuilderError(String);
+
+impl FromStr for BuilderKind {
+    type Err = UnknownBuilderError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        BuilderKind::all()
+            .into_iter()
+            .find(|kind| kind.name() == s)
+            .ok_or_else(|| UnknownBuilderError(s.to_string()))
+    }
 }
 
 pub struct SimpleMapBuilder;
 impl MapBuilder for SimpleMapBuilder {
-    fn build<T: SnapshotableMap>(&mut self, map: &mut T) {
-        let mut rng = rand::thread_rng();
-
+    fn build<T: SnapshotableMap, R: Rng>(&mut self, map: &mut T, rng: &mut R) {
         // Carve out a huge open room
-        map.with_snapshot(|map| {
+        map.with_snapshot("carved out an open room", |map| {
             for y in 1..map.height - 1 {
                 for x in 1..map.width - 1 {
                     map[(x, y)] = TileType::Floor;
@@ -47,7 +182,7 @@ impl MapBuilder for SimpleMapBuilder {
         });
 
         // Spawn 400 random obstacles
-        map.with_snapshot(|map| {
+        map.with_snapshot("placed 400 obstacles", |map| {
             for _i in 0..400 {
                 let x = rng.gen_range(1, map.width - 2);
                 let y = rng.gen_range(1, map.height - 2);
@@ -56,8 +191,8 @@ impl MapBuilder for SimpleMapBuilder {
         });
 
         // Create an exit in one of the outer walls
-        map.with_snapshot(|map| {
-            let exit_direction = Direction::random(&mut rng);
+        map.with_snapshot("created an exit in an outer wall", |map| {
+            let exit_direction = Direction::random(&mut *rng);
             let exit_size = 10;
             let (mut start, dir): (Coord, Direction) = match exit_direction {
                 Direction::Left => (
@@ -56,13 +56,11 @@ fn get_neighbor_tiles(map: &Map, position: Coord) -> Vec<Direction> {
 ///     Let neighbors(frontierCell) = All cells in distance 2 in state Passage. Pick a random neighbor and connect the frontier cell with the neighbor by setting the cell in-between to state Passage. Compute the frontier cells of the chosen frontier cell and add them to the frontier list. Remove the chosen frontier cell from the list of frontier cells.
 pub struct PrimMazeBuilder;
 impl MapBuilder for PrimMazeBuilder {
-    fn build<T: SnapshotableMap>(&mut self, map: &mut T) {
-        let mut rng = rand::thread_rng();
-
+    fn build<T: SnapshotableMap, R: Rng>(&mut self, map: &mut T, rng: &mut R) {
         let mut visited = HashSet::new();
 
         // Add the start
-        let mut frontier_cells = map.with_snapshot(|map| {
+        let mut frontier_cells = map.with_snapshot("opened the start of the maze", |map| {
             let start = Coord::new(map.width as isize / 2, map.height as isize / 2);
             visited.insert(start);
             map[start] = TileType::Floor;
@@ -70,7 +68,7 @@ impl MapBuilder for PrimMazeBuilder {
         });
 
         while !frontier_cells.is_empty() {
-            map.with_snapshot(|map| {
+            map.with_snapshot("carved a corridor", |map| {
                 // Select random frontier cell
                 let index = rng.gen_range(0, frontier_cells.len());
                 let frontier_cell = frontier_cells.remove(index);
@@ -115,13 +113,13 @@ impl MapBuilder for PrimMazeBuilder {
         //}
 
         // Set a random exit for now
-        map.with_snapshot(|map| {
+        map.with_snapshot("placed the exit", |map| {
             if let Some((tile_idx, _)) = map
                 .tiles
                 .iter()
                 .enumerate()
                 .filter(|t| *t.1 == TileType::Floor)
-                .choose(&mut rng)
+                .choose(&mut *rng)
             {
                 map.tiles[tile_idx] = TileType::Exit;
             }
@@ -0,0 +1,94 @@
+use super::{Coord, Map, MapBuilder, SnapshotableMap, TileType};
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+/// Carves rectangular rooms around junctions of the corridors that are already on the map. It is
+/// meant to run after a maze builder, e.g. `(PrimMazeBuilder, RoomCarver::default())`, to add
+/// open arenas to a map that otherwise consists of narrow corridors.
+pub struct RoomCarver {
+    /// The maximum number of rooms to carve
+    pub rooms: usize,
+
+    /// The smallest width and height of a room
+    pub min_size: usize,
+
+    /// The largest width and height of a room
+    pub max_size: usize,
+}
+
+impl Default for RoomCarver {
+    fn default() -> Self {
+        RoomCarver {
+            rooms: 6,
+            min_size: 3,
+            max_size: 7,
+        }
+    }
+}
+
+/// Returns true if at least three neighbors of the tile are floor
+fn is_junction(map: &Map, coord: Coord) -> bool {
+    map[coord] == TileType::Floor
+        && coord
+            .neighbors()
+            .filter(|&neighbor| map.in_bounds(neighbor) && map[neighbor] == TileType::Floor)
+            .count()
+            >= 3
+}
+
+impl MapBuilder for RoomCarver {
+    fn build<T: SnapshotableMap, R: Rng>(&mut self, map: &mut T, rng: &mut R) {
+        let (min_size, max_size) = (self.min_size.max(1), self.max_size.max(self.min_size));
+
+        // Rooms are spread over random junctions, junctions inside a room are skipped
+        let mut junctions = map.with_snapshot("found the junctions", |map| {
+            (0..map.width * map.height)
+                .map(|index| Coord::new(index % map.width, index / map.width))
+                .filter(|&coord| is_junction(map, coord))
+                .collect::<Vec<_>>()
+        });
+        junctions.shuffle(&mut *rng);
+        let mut rooms: Vec<(Coord, Coord)> = Vec::with_capacity(self.rooms);
+
+        for junction in junctions {
+            if rooms.len() >= self.rooms {
+                break;
+            }
+            let inside_room = rooms.iter().any(|(from, to)| {
+                junction.x >= from.x
+                    && junction.x <= to.x
+                    && junction.y >= from.y
+                    && junction.y <= to.y
+            });
+            if inside_room {
+                continue;
+            }
+
+            let (width, height) = (
+                rng.gen_range(min_size, max_size + 1) as isize,
+                rng.gen_range(min_size, max_size + 1) as isize,
+            );
+            let label = format!("carved a room at ({}, {})", junction.x, junction.y);
+            map.with_snapshot(&label, |map| {
+                // Rooms stay clear of the outer border of the map
+                let from = Coord::new(
+                    (junction.x - width / 2).max(1),
+                    (junction.y - height / 2).max(1),
+                );
+                let to = Coord::new(
+                    (from.x + width - 1).min(map.width as isize - 2),
+                    (from.y + height - 1).min(map.height as isize - 2),
+                );
+                for y in from.y..=to.y {
+                    for x in from.x..=to.x {
+                        let coord = Coord::new(x, y);
+                        if map[coord] == TileType::Wall {
+                            map[coord] = TileType::Floor;
+                        }
+                    }
+                }
+                rooms.push((from, to));
+            });
+        }
+    }
+}
@@ -1,55 +1,101 @@
 use super::Map;
+use crate::MapDiff;
 
 /// A trait that optionally enables creating snapshots of `Map`s
 pub trait SnapshotableMap {
-    /// Calls the given function with a mutable map that can be edited.
-    fn with_snapshot<T, F: FnMut(&mut Map) -> T>(&mut self, f: F) -> T;
+    /// Calls the given function with a mutable map that can be edited. The label describes the
+    /// edit, e.g. "carve out a room", and is shown along with the snapshot.
+    fn with_snapshot<T, F: FnMut(&mut Map) -> T>(&mut self, label: &str, f: F) -> T;
 }
 
-#[derive(Default)]
+/// A step in the construction of a map
+#[derive(Clone, Debug)]
+pub struct Snapshot {
+    /// Describes the edit the builder made to the previous version of the map
+    pub label: String,
+
+    /// The tiles the edit changed
+    pub diff: MapDiff,
+}
+
+/// Records every version of a map during its construction. Only the tiles that changed are stored
+/// for every snapshot, the first snapshot is the initial map itself.
 pub struct MapWithSnapshots {
-    snapshots: Vec<Map>,
+    initial: Map,
+    latest: Map,
+    snapshots: Vec<Snapshot>,
+}
+
+impl MapWithSnapshots {
+    /// Returns the map before any edit was made
+    pub fn initial_map(&self) -> &Map {
+        &self.initial
+    }
+
+    /// Returns the map after all the edits were made
+    pub fn latest_map(&self) -> &Map {
+        &self.latest
+    }
+
+    /// Returns the steps that lead from the initial map to the latest map
+    pub fn snapshots(&self) -> &[Snapshot] {
+        &self.snapshots
+    }
 }
 
 impl From<Map> for MapWithSnapshots {
     fn from(map: Map) -> Self {
         MapWithSnapshots {
-            snapshots: vec![map],
+            initial: map.clone(),
+            latest: map,
+            snapshots: vec![Snapshot {
+                label: "initial map".to_string(),
+                diff: MapDiff::default(),
+            }],
         }
     }
 }
 
 impl From<MapWithSnapshots> for Map {
     fn from(history: MapWithSnapshots) -> Self {
-        history
-            .snapshots
-            .into_iter()
-            .last()
-            .expect("there has to be an initial map version")
+        history.latest
     }
 }
 
 impl From<MapWithSnapshots> for Vec<Map> {
     fn from(history: MapWithSnapshots) -> Self {
-        history.snapshots
+        let mut map = history.initial;
+        let mut maps = history
+            .snapshots
+            .iter()
+            .map(|snapshot| {
+                snapshot.diff.apply(&mut map);
+                map.clone()
+            })
+            .collect::<Vec<_>>();
+
+        // Diffs only hold tiles, the latest map also has everything else a builder computed
+        if let Some(last) = maps.last_mut() {
+            *last = history.latest;
+        }
+        maps
     }
 }
 
 impl SnapshotableMap for MapWithSnapshots {
-    fn with_snapshot<T, F: FnMut(&mut Map) -> T>(&mut self, mut f: F) -> T {
-        let mut new_map = self
-            .snapshots
-            .last()
-            .expect("there has to be an initial map version")
-            .clone();
-        let result = f(&mut new_map);
-        self.snapshots.push(new_map);
+    fn with_snapshot<T, F: FnMut(&mut Map) -> T>(&mut self, label: &str, mut f: F) -> T {
+        let previous = self.latest.clone();
+        let result = f(&mut self.latest);
+        self.snapshots.push(Snapshot {
+            label: label.to_string(),
+            diff: MapDiff::between(&previous, &self.latest),
+        });
         result
     }
 }
 
 impl SnapshotableMap for Map {
-    fn with_snapshot<T, F: FnMut(&mut Map) -> T>(&mut self, mut f: F) -> T {
+    fn with_snapshot<T, F: FnMut(&mut Map) -> T>(&mut self, _label: &str, mut f: F) -> T {
         f(self)
     }
 }
@@ -0,0 +1,155 @@
+use crate::Map;
+use mlr_api::{Coord, PlayerTile, TileType};
+use serde_derive::{Deserialize, Serialize};
+
+/// A single tile that changed between two versions of a map
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub struct TileChange {
+    pub coord: Coord,
+    pub from: TileType,
+    pub to: TileType,
+}
+
+/// The tiles that changed between two versions of a map of the same size. Applying the diff to
+/// the old version results in the new version, applying the inverted diff to the new version
+/// results in the old version again. Storing diffs is a lot cheaper than storing every version of
+/// a map in full when only a few tiles change at a time.
+#[derive(Debug, Clone, Default, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub struct MapDiff {
+    pub changes: Vec<TileChange>,
+}
+
+impl MapDiff {
+    /// Computes the tiles that changed from `old` to `new`. Both maps must be of the same size.
+    pub fn between(old: &Map, new: &Map) -> MapDiff {
+        assert!(
+            old.width == new.width && old.height == new.height,
+            "cannot compute the difference between maps of different sizes"
+        );
+        let changes = old
+            .tiles
+            .iter()
+            .zip(new.tiles.iter())
+            .enumerate()
+            .filter(|(_, (from, to))| from != to)
+            .map(|(index, (&from, &to))| TileChange {
+                coord: Coord::new(index % old.width, index / old.width),
+                from,
+                to,
+            })
+            .collect();
+        MapDiff { changes }
+    }
+
+    /// Returns true if no tile changed
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Changes the tiles of the map. The distances to the exits are not updated, call
+    /// `Map::compute_distance_to_exit` once all diffs are applied.
+    pub fn apply(&self, map: &mut Map) {
+        for change in self.changes.iter() {
+            map[change.coord] = change.to;
+        }
+    }
+
+    /// Undoes the changes of the diff on a map it was applied to, like applying the inverted diff
+    /// without constructing it first. The distances to the exits are not updated either.
+    pub fn revert(&self, map: &mut Map) {
+        for change in self.changes.iter().rev() {
+            map[change.coord] = change.from;
+        }
+    }
+
+    /// Returns the diff that undoes this diff
+    pub fn invert(&self) -> MapDiff {
+        MapDiff {
+            changes: self
+                .changes
+                .iter()
+                .rev()
+                .map(|change| TileChange {
+                    coord: change.coord,
+                    from: change.to,
+                    to: change.from,
+                })
+                .collect(),
+        }
+    }
+
+    /// Returns the new type of every changed tile, the way players are told about them
+    pub fn tiles(&self) -> Vec<PlayerTile> {
+        self.changes
+            .iter()
+            .map(|change| PlayerTile {
+                coord: change.coord,
+                tile_type: change.to,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map_builder::BuilderKind;
+
+    /// Returns three versions of the same map, each with a few more tiles changed
+    fn versions() -> (Map, Map, Map) {
+        let first = BuilderKind::CellularAutomata.new_seeded_map(20, 15, 3);
+        let mut second = first.clone();
+        second[Coord::new(1, 1)] = TileType::Wall;
+        second[Coord::new(5, 7)] = TileType::Goal;
+        let mut third = second.clone();
+        third[Coord::new(5, 7)] = TileType::Floor;
+        third[Coord::new(18, 13)] = TileType::Exit;
+        second.compute_distance_to_exit();
+        third.compute_distance_to_exit();
+        (first, second, third)
+    }
+
+    fn applied(diff: &MapDiff, map: &Map) -> Map {
+        let mut map = map.clone();
+        diff.apply(&mut map);
+        map.compute_distance_to_exit();
+        map
+    }
+
+    #[test]
+    fn applying_a_diff_results_in_the_new_map() {
+        let (first, second, _) = versions();
+        let diff = MapDiff::between(&first, &second);
+        assert!(!diff.is_empty());
+        assert_eq!(applied(&diff, &first), second);
+        assert!(MapDiff::between(&first, &first).is_empty());
+    }
+
+    #[test]
+    fn inverting_a_diff_results_in_the_old_map() {
+        let (first, second, _) = versions();
+        let diff = MapDiff::between(&first, &second);
+        assert_eq!(applied(&diff.invert(), &second), first);
+        assert_eq!(diff.invert().invert(), diff);
+
+        let mut reverted = second;
+        diff.revert(&mut reverted);
+        reverted.compute_distance_to_exit();
+        assert_eq!(reverted, first);
+    }
+
+    #[test]
+    fn diffs_compose() {
+        let (first, second, third) = versions();
+        let (to_second, to_third) = (
+            MapDiff::between(&first, &second),
+            MapDiff::between(&second, &third),
+        );
+        let composed = applied(&to_third, &applied(&to_second, &first));
+        assert_eq!(composed, third);
+        assert_eq!(composed, applied(&MapDiff::between(&first, &third), &first));
+
+        let undone = applied(&to_second.invert(), &applied(&to_third.invert(), &third));
+        assert_eq!(undone, first);
+    }
+}
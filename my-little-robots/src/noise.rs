@@ -0,0 +1,100 @@
+use crate::{GameEvent, World};
+use mlr_api::{Coord, Direction, Noise, PlayerId};
+
+/// A noise that was made during the current turn and still has to be propagated
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub(crate) struct NoiseSource {
+    player: PlayerId,
+    location: Coord,
+    loudness: usize,
+}
+
+impl World {
+    /// Makes a noise at `location` that can be heard by the units of other players within
+    /// `loudness` tiles, regardless of walls or their field of view. Noises are propagated at the
+    /// end of the turn. A loudness of zero makes no noise at all.
+    pub fn make_noise(&mut self, player: PlayerId, location: Coord, loudness: usize) {
+        if loudness > 0 {
+            self.noise_sources.push(NoiseSource {
+                player,
+                location,
+                loudness,
+            });
+        }
+    }
+
+    /// Returns the noises the units of the given player heard during the last turn
+    pub fn noises_heard_by(&self, player: PlayerId) -> Vec<Noise> {
+        self.noises
+            .iter()
+            .filter(|(p, _)| *p == player)
+            .map(|(_, noise)| *noise)
+            .collect()
+    }
+
+    /// Determines which players heard the noises made during the turn. Every player hears a noise
+    /// at most once, through the unit closest to it.
+    pub(crate) fn propagate_noise(&mut self, events: &mut Vec<GameEvent>) {
+        self.noises.clear();
+        let mut players = self
+            .units
+            .iter()
+            .map(|unit| unit.player)
+            .collect::<Vec<_>>();
+        players.sort_by_key(|player| player.0);
+        players.dedup();
+
+        for source in std::mem::take(&mut self.noise_sources) {
+            events.push(GameEvent::NoiseMade {
+                player: source.player,
+                location: source.location,
+                loudness: source.loudness,
+            });
+            for &player in players.iter().filter(|&&player| player != source.player) {
+                let closest = self
+                    .units
+                    .iter()
+                    .filter(|unit| unit.player == player)
+                    .min_by_key(|unit| unit.location.manhattan_distance(source.location));
+                let unit = match closest {
+                    Some(unit) => unit,
+                    None => continue,
+                };
+                let distance = unit.location.manhattan_distance(source.location);
+                let direction = match approximate_direction(unit.location, source.location) {
+                    Some(direction) if distance <= source.loudness => direction,
+                    _ => continue,
+                };
+                self.noises.push((
+                    player,
+                    Noise {
+                        direction,
+                        intensity: source.loudness - distance + 1,
+                    },
+                ));
+            }
+        }
+    }
+}
+
+/// Returns the direction along the axis in which `to` lies furthest from `from`, or `None` if both
+/// are the same location.
+fn approximate_direction(from: Coord, to: Coord) -> Option<Direction> {
+    let dx = to.x - from.x;
+    let dy = to.y - from.y;
+    if dx == 0 && dy == 0 {
+        None
+    } else if dx.abs() >= dy.abs() {
+        Some(if dx > 0 {
+            Direction::Right
+        } else {
+            Direction::Left
+        })
+    } else {
+        Some(if dy > 0 {
+            Direction::Down
+        } else {
+            Direction::Up
+        })
+    }
+}
@@ -0,0 +1,170 @@
+use crate::{GameEvent, GameRules, WinCondition, World};
+use mlr_api::{Coord, Item, ItemId, ItemKind, PlayerId, TileType, UnitId};
+
+impl World {
+    /// Places an item on the ground at `location`
+    pub fn spawn_item(&mut self, kind: ItemKind, location: Coord) -> ItemId {
+        let id = ItemId(self.items.len() + self.delivered.len());
+        self.items.push(Item {
+            id,
+            kind,
+            location,
+            carrier: None,
+            next_move: 0,
+        });
+        id
+    }
+
+    /// Returns the item carried by the given unit, if any. A unit carries at most one item.
+    pub fn item_carried_by(&self, unit: UnitId) -> Option<&Item> {
+        self.items.iter().find(|item| item.carrier == Some(unit))
+    }
+
+    /// Returns the item that lies on the ground at `location`, if any
+    pub fn item_at(&self, location: Coord) -> Option<&Item> {
+        self.items
+            .iter()
+            .find(|item| item.carrier.is_none() && item.location == location)
+    }
+
+    /// Returns true if the given unit carries an item that still slows it down this turn
+    pub fn is_encumbered(&self, unit: UnitId) -> bool {
+        self.item_carried_by(unit)
+            .map_or(false, |item| item.next_move > self.turn)
+    }
+
+    /// Returns the number of items the given player delivered to a goal tile
+    pub fn delivered_by(&self, player: PlayerId) -> usize {
+        self.delivered.iter().filter(|(p, _)| *p == player).count()
+    }
+
+    /// Lets the unit pick up the item on the ground where it stands. Nothing happens if the unit
+    /// already carries an item or another unit picked up the item first.
+    pub(crate) fn pick_up(&mut self, unit: UnitId, events: &mut Vec<GameEvent>) {
+        let (player, location) = match self.unit(unit) {
+            Some(unit) => (unit.player, unit.location),
+            None => return,
+        };
+        if self.item_carried_by(unit).is_some() {
+            return;
+        }
+        if let Some(item) = self
+            .items
+            .iter_mut()
+            .find(|item| item.carrier.is_none() && item.location == location)
+        {
+            item.carrier = Some(unit);
+            events.push(GameEvent::ItemPickedUp {
+                player,
+                unit,
+                item: item.id,
+                location,
+            });
+        }
+    }
+
+    /// Lets the unit drop the item it carries where it stands
+    pub(crate) fn drop_item(&mut self, unit: UnitId, events: &mut Vec<GameEvent>) {
+        let player = match self.unit(unit) {
+            Some(unit) => unit.player,
+            None => return,
+        };
+        if let Some(item) = self
+            .items
+            .iter_mut()
+            .find(|item| item.carrier == Some(unit))
+        {
+            item.carrier = None;
+            events.push(GameEvent::ItemDropped {
+                player,
+                unit,
+                item: item.id,
+                location: item.location,
+            });
+        }
+    }
+
+    /// Moves the item carried by `unit` along with it and slows the unit down for `slowdown` turns
+    pub(crate) fn carry_item(&mut self, unit: UnitId, location: Coord, slowdown: usize) {
+        let turn = self.turn;
+        if let Some(item) = self
+            .items
+            .iter_mut()
+            .find(|item| item.carrier == Some(unit))
+        {
+            item.location = location;
+            item.next_move = turn + slowdown.max(1);
+        }
+    }
+
+    /// Removes the items that are carried onto a goal tile from the world and credits the player
+    /// of the carrier
+    pub(crate) fn deliver_items(&mut self, events: &mut Vec<GameEvent>) {
+        let map = &self.map;
+        let units = &self.units;
+        let delivered = &mut self.delivered;
+        self.items.retain(|item| {
+            let carrier = match item.carrier.and_then(|id| {
+                units
+                    .binary_search_by_key(&id.0, |unit| unit.id.0)
+                    .ok()
+                    .map(|index| &units[index])
+            }) {
+                Some(carrier) if map[carrier.location] == TileType::Goal => carrier,
+                _ => return true,
+            };
+            delivered.push((carrier.player, item.id));
+            events.push(GameEvent::ItemDelivered {
+                player: carrier.player,
+                unit: carrier.id,
+                item: item.id,
+                location: carrier.location,
+            });
+            false
+        });
+    }
+
+    /// Returns how close the player is to completing the objective of the battle described by the
+    /// win condition of the `rules`, higher is closer. Used to rank the players of battles that are
+    /// cut short, see `MatchResult::ranking`.
+    pub fn progress(&self, player: PlayerId, rules: &GameRules) -> i64 {
+        let units = self.units.iter().filter(|unit| unit.player == player);
+        match rules.win_condition {
+            WinCondition::ReachExit => units
+                .filter_map(|unit| self.map.get_distance_to_exit(unit.location))
+                .min()
+                .map_or(i64::MIN, |distance| -(distance as i64)),
+            WinCondition::DeliverItems => self.delivered_by(player) as i64,
+            WinCondition::Explore => self.explored_percentage(player) as i64,
+            WinCondition::LastPlayerStanding => units.count() as i64,
+        }
+    }
+
+    /// Returns the players that completed the objective of the battle described by the win
+    /// condition of the `rules`, ordered by their id. More than one player means the battle ends
+    /// in a tie.
+    pub fn finished_players(&self, rules: &GameRules) -> Vec<PlayerId> {
+        let mut finished = match rules.win_condition {
+            WinCondition::ReachExit => self
+                .units_on_exits()
+                .map(|unit| unit.player)
+                .collect::<Vec<_>>(),
+            WinCondition::DeliverItems => self
+                .delivered
+                .iter()
+                .map(|(player, _)| *player)
+                .filter(|&player| self.delivered_by(player) >= rules.items_to_deliver.max(1))
+                .collect(),
+            WinCondition::Explore => self
+                .explored
+                .iter()
+                .map(|exploration| exploration.player)
+                .filter(|&player| self.explored_percentage(player) >= rules.exploration_target)
+                .collect(),
+            WinCondition::LastPlayerStanding => Vec::new(),
+        };
+        finished.sort_by_key(|player| player.0);
+        finished.dedup();
+        finished
+    }
+}
@@ -0,0 +1,66 @@
+use crate::{GameEvent, World};
+use mlr_api::{Coord, Direction, Obstacle, ObstacleKind, UnitId};
+
+impl World {
+    /// Places a new obstacle on a tile. Obstacles can only be placed on tiles that can be entered
+    /// and that are not occupied by a unit or another obstacle, returns false otherwise.
+    pub fn place_obstacle(&mut self, kind: ObstacleKind, location: Coord) -> bool {
+        if !self.map.can_enter_tile(location)
+            || self.obstacle_at(location).is_some()
+            || self.units.iter().any(|unit| unit.location == location)
+        {
+            return false;
+        }
+        self.obstacles.push(Obstacle {
+            kind,
+            location,
+            durability: kind.durability(),
+        });
+        true
+    }
+
+    /// Returns the obstacle at the given location, if any
+    pub fn obstacle_at(&self, location: Coord) -> Option<&Obstacle> {
+        self.obstacles
+            .iter()
+            .find(|obstacle| obstacle.location == location)
+    }
+
+    /// Returns true if a unit can enter the tile at the given location, it must be a tile that can
+    /// be entered without an obstacle on it
+    pub fn can_enter(&self, location: Coord) -> bool {
+        self.map.can_enter_tile(location) && self.obstacle_at(location).is_none()
+    }
+
+    /// Lets the unit damage the obstacle next to it in `direction`. The obstacle is destroyed once
+    /// its durability runs out.
+    pub(crate) fn break_obstacle(
+        &mut self,
+        unit: UnitId,
+        direction: Direction,
+        events: &mut Vec<GameEvent>,
+    ) {
+        let (player, location) = match self.unit(unit) {
+            Some(unit) => (unit.player, unit.location + direction),
+            None => return,
+        };
+        let index = match self
+            .obstacles
+            .iter()
+            .position(|obstacle| obstacle.location == location)
+        {
+            Some(index) => index,
+            None => return,
+        };
+        let obstacle = &mut self.obstacles[index];
+        obstacle.durability = obstacle.durability.saturating_sub(1);
+        if obstacle.durability == 0 {
+            self.obstacles.remove(index);
+            events.push(GameEvent::ObstacleDestroyed {
+                player,
+                unit,
+                location,
+            });
+        }
+    }
+}
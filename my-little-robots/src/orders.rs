@@ -0,0 +1,220 @@
+use crate::{Action, ActionValidationError, GameRules, Plugins, World};
+use mlr_api::{PlayerAction, PlayerId, UnitId};
+use std::collections::HashMap;
+
+/// The orders of a single player for a single turn. All the actions a player submitted are
+/// validated up front, the actions that passed validation are applied to the world together in a
+/// single resolution pass.
+#[derive(Clone, Debug)]
+pub(crate) struct TurnOrders {
+    /// The player that issued the orders
+    pub player: PlayerId,
+
+    /// The actions that passed validation, in the order they were submitted
+    pub actions: Vec<Action>,
+
+    /// The actions that did not pass validation
+    pub rejected: Vec<RejectedAction>,
+}
+
+/// An action submitted by a player that did not pass validation
+#[derive(Clone, Debug)]
+pub(crate) struct RejectedAction {
+    /// The position of the action in the list of actions submitted by the player
+    pub index: usize,
+    pub action: PlayerAction,
+    pub error: ActionValidationError,
+}
+
+impl TurnOrders {
+    /// Validates the actions submitted by `player` against the current state of the world. Custom
+    /// actions are validated by the handler the plugins registered for them.
+    pub fn validate(
+        player: PlayerId,
+        actions: Vec<PlayerAction>,
+        world: &World,
+        rules: &GameRules,
+        plugins: &Plugins,
+    ) -> TurnOrders {
+        let mut orders = TurnOrders {
+            player,
+            actions: Vec::with_capacity(actions.len()),
+            rejected: Vec::new(),
+        };
+        let mut budgets = ActionBudgets::new(rules.action_points);
+        for (index, action) in actions.into_iter().enumerate() {
+            let validated =
+                validate_action(&action, player, world, rules, plugins).and_then(|validated| {
+                    budgets.spend(action.unit(), action_cost(&action, rules, plugins))?;
+                    Ok(validated)
+                });
+            match validated {
+                Ok(action) => orders.actions.push(action),
+                Err(error) => orders.rejected.push(RejectedAction {
+                    index,
+                    action,
+                    error,
+                }),
+            }
+        }
+        orders
+    }
+}
+
+/// Given an action from a player turn it into an action that can be applied to the world. Returns
+/// an error if the action cannot be performed by the player.
+fn validate_action(
+    action: &PlayerAction,
+    player: PlayerId,
+    world: &World,
+    rules: &GameRules,
+    plugins: &Plugins,
+) -> Result<Action, ActionValidationError> {
+    if !rules.actions.contains(&action.kind()) {
+        return Err(ActionValidationError::NotAllowed {
+            kind: action.kind(),
+        });
+    }
+
+    let unit = action.unit();
+    match world.unit(unit) {
+        None => return Err(ActionValidationError::UnknownUnit { unit }),
+        Some(u) if u.player != player => return Err(ActionValidationError::NotOwned { unit }),
+        Some(_) => {}
+    }
+
+    match action {
+        PlayerAction::Move { direction, .. } => {
+            if world.is_encumbered(unit) {
+                let until = world.item_carried_by(unit).map_or(0, |item| item.next_move);
+                return Err(ActionValidationError::Encumbered { unit, until });
+            }
+            Ok(Action::Move {
+                unit,
+                direction: *direction,
+            })
+        }
+        PlayerAction::Mark { tag, .. } => Ok(Action::Mark { unit, tag: *tag }),
+        PlayerAction::PickUp { .. } => {
+            let location = world.unit(unit).map(|unit| unit.location);
+            if world.item_carried_by(unit).is_some()
+                || location
+                    .and_then(|location| world.item_at(location))
+                    .is_none()
+            {
+                return Err(ActionValidationError::NothingToPickUp { unit });
+            }
+            Ok(Action::PickUp { unit })
+        }
+        PlayerAction::Drop { .. } => {
+            if world.item_carried_by(unit).is_none() {
+                return Err(ActionValidationError::NotCarrying { unit });
+            }
+            Ok(Action::Drop { unit })
+        }
+        PlayerAction::Link { target, .. } => {
+            match world.unit(*target) {
+                None => return Err(ActionValidationError::UnknownUnit { unit: *target }),
+                Some(t) if t.player != player => {
+                    return Err(ActionValidationError::NotOwned { unit: *target })
+                }
+                Some(_) => {}
+            }
+            match world.unit(unit).and_then(|unit| unit.energy) {
+                Some(available) if available < rules.link_energy => {
+                    Err(ActionValidationError::NotEnoughEnergy {
+                        unit,
+                        needed: rules.link_energy,
+                        available,
+                    })
+                }
+                _ => Ok(Action::Link {
+                    unit,
+                    target: *target,
+                }),
+            }
+        }
+        PlayerAction::Break { direction, .. } => {
+            let location = world.unit(unit).map(|unit| unit.location + *direction);
+            if location
+                .and_then(|location| world.obstacle_at(location))
+                .is_none()
+            {
+                return Err(ActionValidationError::NothingToBreak { unit });
+            }
+            Ok(Action::Break {
+                unit,
+                direction: *direction,
+            })
+        }
+        PlayerAction::Custom { name, data, .. } => {
+            let handler = plugins
+                .action(name)
+                .ok_or_else(|| ActionValidationError::UnknownAction { name: name.clone() })?;
+            handler
+                .validate(player, unit, data, world)
+                .map_err(|reason| ActionValidationError::Rejected {
+                    unit,
+                    name: name.clone(),
+                    reason,
+                })?;
+            Ok(Action::Custom {
+                unit,
+                name: name.clone(),
+                data: data.clone(),
+            })
+        }
+    }
+}
+
+/// Returns the number of action points the action costs
+fn action_cost(action: &PlayerAction, rules: &GameRules, plugins: &Plugins) -> usize {
+    let handler_cost = match action {
+        PlayerAction::Custom { name, data, .. } => {
+            plugins.action(name).and_then(|handler| handler.cost(data))
+        }
+        PlayerAction::Move { .. }
+        | PlayerAction::Mark { .. }
+        | PlayerAction::PickUp { .. }
+        | PlayerAction::Drop { .. }
+        | PlayerAction::Link { .. }
+        | PlayerAction::Break { .. } => None,
+    };
+    handler_cost.unwrap_or_else(|| rules.action_costs.cost(action.kind()))
+}
+
+/// Keeps track of the action points the units of a player spent during a turn
+struct ActionBudgets {
+    /// The action points every unit can spend, `None` if there is no limit
+    action_points: Option<usize>,
+    spent: HashMap<UnitId, usize>,
+}
+
+impl ActionBudgets {
+    fn new(action_points: Option<usize>) -> Self {
+        ActionBudgets {
+            action_points,
+            spent: HashMap::new(),
+        }
+    }
+
+    /// Spends `cost` action points of `unit`. Returns an error and spends nothing if the unit does
+    /// not have enough points left.
+    fn spend(&mut self, unit: UnitId, cost: usize) -> Result<(), ActionValidationError> {
+        let action_points = match self.action_points {
+            Some(action_points) => action_points,
+            None => return Ok(()),
+        };
+        let spent = self.spent.entry(unit).or_insert(0);
+        let remaining = action_points.saturating_sub(*spent);
+        if cost > remaining {
+            return Err(ActionValidationError::OverBudget {
+                unit,
+                cost,
+                remaining,
+            });
+        }
+        *spent += cost;
+        Ok(())
+    }
+}
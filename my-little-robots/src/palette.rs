@@ -0,0 +1,52 @@
+use mlr_api::PlayerId;
+
+/// The colors of the first players, further colors are generated
+const PLAYER_COLORS: [[u8; 3]; 4] = [[144, 238, 144], [138, 43, 226], [255, 69, 0], [255, 215, 0]];
+
+/// The symbols of the first players, all of them exist in code page 437. Further players are drawn
+/// with letters.
+#[cfg(feature = "render")]
+const PLAYER_SYMBOLS: &str = "♦♣¶♠♥☺☻♪♫§Ωδ∞φΣτ";
+
+/// Returns the color in which the units of a player are drawn. Every player has a different color,
+/// colors of players beyond the first few are spread around the color wheel.
+pub(crate) fn player_rgb(player: PlayerId) -> [u8; 3] {
+    match PLAYER_COLORS.get(player.0) {
+        Some(&color) => color,
+        None => {
+            // Stepping by the golden angle keeps consecutive hues far apart
+            let hue = ((player.0 - PLAYER_COLORS.len()) as f32 * 137.508 + 200.0) % 360.0;
+            hsv_to_rgb(hue, 0.65, 0.95)
+        }
+    }
+}
+
+/// Returns the symbol with which the units of a player are drawn. Symbols are reused once there
+/// are more players than symbols, those players still differ in color.
+#[cfg(feature = "render")]
+pub(crate) fn player_symbol(player: PlayerId) -> char {
+    let letters = (b'A'..=b'Z').map(char::from);
+    PLAYER_SYMBOLS
+        .chars()
+        .chain(letters)
+        .cycle()
+        .nth(player.0)
+        .expect("the symbols are cycled endlessly")
+}
+
+/// Converts a color given by its hue in degrees, saturation and value to RGB
+fn hsv_to_rgb(hue: f32, saturation: f32, value: f32) -> [u8; 3] {
+    let chroma = value * saturation;
+    let x = chroma * (1.0 - ((hue / 60.0) % 2.0 - 1.0).abs());
+    let (r, g, b) = match (hue / 60.0) as u32 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    let m = value - chroma;
+    let channel = |c: f32| ((c + m) * 255.0).round() as u8;
+    [channel(r), channel(g), channel(b)]
+}
@@ -0,0 +1,26 @@
+/// Applies `f` to every item and returns the results in the same order. With the `native` feature
+/// the items are processed on the rayon thread pool, otherwise they are processed one after the
+/// other, e.g. in the browser where threads are not available.
+#[cfg(feature = "native")]
+pub(crate) fn map_parallel<T, U, F>(items: Vec<T>, f: F) -> Vec<U>
+where
+    T: Send,
+    U: Send,
+    F: Fn(T) -> U + Send + Sync,
+{
+    use rayon::prelude::*;
+    items.into_par_iter().map(f).collect()
+}
+
+/// Applies `f` to every item and returns the results in the same order. With the `native` feature
+/// the items are processed on the rayon thread pool, otherwise they are processed one after the
+/// other, e.g. in the browser where threads are not available.
+#[cfg(not(feature = "native"))]
+pub(crate) fn map_parallel<T, U, F>(items: Vec<T>, f: F) -> Vec<U>
+where
+    T: Send,
+    U: Send,
+    F: Fn(T) -> U + Send + Sync,
+{
+    items.into_iter().map(f).collect()
+}
@@ -0,0 +1,175 @@
+use serde_derive::{Deserialize, Serialize};
+
+/// Describes how players are punished for misbehaving. Every invalid action, failed run or timeout
+/// counts as a strike against the player.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PenaltyRules {
+    /// After this many strikes the player has to sit out `skip_turns` turns, after which the
+    /// count starts over. `None` disables skipping turns.
+    pub skip_after: Option<usize>,
+
+    /// The number of turns a player sits out once `skip_after` strikes have been reached
+    pub skip_turns: usize,
+
+    /// After this many strikes in total the player is eliminated from the battle, its units remain
+    /// in the world but never move again. `None` disables elimination.
+    pub eliminate_after: Option<usize>,
+}
+
+/// The misbehavior of a single player during a battle and the penalties that were applied
+#[derive(Clone, Debug, Default, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub struct PlayerPenalties {
+    /// The number of actions of the player that were rejected
+    pub invalid_actions: usize,
+
+    /// The number of turns in which the runner of the player failed or took too long
+    pub runner_errors: usize,
+
+    /// The number of turns the player had to sit out
+    pub skipped_turns: usize,
+
+    /// True if the player was eliminated from the battle
+    pub eliminated: bool,
+
+    /// The strikes since the player last had to sit out
+    #[serde(skip)]
+    strikes_since_skip: usize,
+
+    /// The turns the player still has to sit out
+    #[serde(skip)]
+    pending_skips: usize,
+}
+
+/// The penalty that is applied to a player after new strikes were recorded
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub(crate) enum Penalty {
+    /// The player has to sit out the next turns
+    SkipTurns,
+
+    /// The player is eliminated
+    Eliminate,
+}
+
+impl PlayerPenalties {
+    /// Returns the total number of strikes against the player
+    pub fn strikes(&self) -> usize {
+        self.invalid_actions + self.runner_errors
+    }
+
+    /// Returns true if the player is allowed to play the current turn. If the player has to sit
+    /// out the turn this is recorded.
+    pub(crate) fn take_turn(&mut self) -> bool {
+        if self.eliminated {
+            return false;
+        }
+        if self.pending_skips > 0 {
+            self.pending_skips -= 1;
+            self.skipped_turns += 1;
+            return false;
+        }
+        true
+    }
+
+    /// Records the strikes a player received during a turn and returns the penalty that applies
+    /// as a consequence, if any.
+    pub(crate) fn record(
+        &mut self,
+        invalid_actions: usize,
+        runner_errors: usize,
+        rules: &PenaltyRules,
+    ) -> Option<Penalty> {
+        let strikes = invalid_actions + runner_errors;
+        if strikes == 0 || self.eliminated {
+            return None;
+        }
+        self.invalid_actions += invalid_actions;
+        self.runner_errors += runner_errors;
+        self.strikes_since_skip += strikes;
+
+        if matches!(rules.eliminate_after, Some(limit) if self.strikes() >= limit) {
+            self.eliminated = true;
+            return Some(Penalty::Eliminate);
+        }
+        match rules.skip_after {
+            Some(limit) if self.strikes_since_skip >= limit && rules.skip_turns > 0 => {
+                self.strikes_since_skip = 0;
+                self.pending_skips += rules.skip_turns;
+                Some(Penalty::SkipTurns)
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules(
+        skip_after: Option<usize>,
+        skip_turns: usize,
+        eliminate_after: Option<usize>,
+    ) -> PenaltyRules {
+        PenaltyRules {
+            skip_after,
+            skip_turns,
+            eliminate_after,
+        }
+    }
+
+    #[test]
+    fn skipping_resets_the_strikes_since_the_last_skip() {
+        let rules = rules(Some(2), 1, None);
+        let mut penalties = PlayerPenalties::default();
+        assert_eq!(penalties.record(1, 0, &rules), None);
+        assert_eq!(penalties.record(0, 1, &rules), Some(Penalty::SkipTurns));
+        assert_eq!(penalties.strikes_since_skip, 0);
+        assert!(!penalties.take_turn());
+        assert!(penalties.take_turn());
+        assert_eq!(penalties.skipped_turns, 1);
+
+        assert_eq!(penalties.record(1, 0, &rules), None);
+        assert_eq!(penalties.record(1, 0, &rules), Some(Penalty::SkipTurns));
+        assert_eq!(penalties.strikes(), 4);
+    }
+
+    #[test]
+    fn skipping_zero_turns_is_no_penalty() {
+        let rules = rules(Some(1), 0, None);
+        let mut penalties = PlayerPenalties::default();
+        assert_eq!(penalties.record(3, 0, &rules), None);
+        assert!(penalties.take_turn());
+        assert_eq!(penalties.skipped_turns, 0);
+        assert_eq!(penalties.strikes(), 3);
+    }
+
+    #[test]
+    fn elimination_takes_precedence_over_skipping() {
+        let rules = rules(Some(2), 3, Some(2));
+        let mut penalties = PlayerPenalties::default();
+        assert_eq!(penalties.record(2, 0, &rules), Some(Penalty::Eliminate));
+        assert!(penalties.eliminated);
+        assert_eq!(penalties.pending_skips, 0);
+        assert!(!penalties.take_turn());
+        assert_eq!(penalties.skipped_turns, 0);
+    }
+
+    #[test]
+    fn strikes_after_elimination_are_ignored() {
+        let rules = rules(Some(1), 1, Some(1));
+        let mut penalties = PlayerPenalties::default();
+        assert_eq!(penalties.record(0, 1, &rules), Some(Penalty::Eliminate));
+        assert_eq!(penalties.record(5, 5, &rules), None);
+        assert_eq!(penalties.strikes(), 1);
+        assert_eq!((penalties.invalid_actions, penalties.runner_errors), (0, 1));
+    }
+
+    #[test]
+    fn turns_without_strikes_are_not_recorded() {
+        let rules = rules(Some(1), 1, Some(1));
+        let mut penalties = PlayerPenalties::default();
+        assert_eq!(penalties.record(0, 0, &rules), None);
+        assert_eq!(penalties, PlayerPenalties::default());
+    }
+}
@@ -0,0 +1,159 @@
+use crate::{GameEvent, World};
+use mlr_api::{PlayerId, UnitId};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// A `Plugin` extends the engine with new actions, tiles and rules without having to change the
+/// engine itself. Plugins are added to a battle with `Battle::add_plugin`, which calls `build` to
+/// let the plugin register everything it provides.
+///
+/// ```ignore
+/// struct Lava;
+///
+/// impl Plugin for Lava {
+///     fn name(&self) -> &str {
+///         "lava"
+///     }
+///
+///     fn build(&self, plugins: &mut Plugins) {
+///         plugins.add_tile(CustomTile::new(LAVA, "lava", true));
+///         plugins.add_phase(BurnUnitsOnLava);
+///     }
+/// }
+/// ```
+pub trait Plugin {
+    /// The name of the plugin, used in log messages
+    fn name(&self) -> &str;
+
+    /// Registers the actions, tiles and resolution phases of the plugin
+    fn build(&self, plugins: &mut Plugins);
+}
+
+/// Validates and applies a custom action. Players order custom actions with
+/// `PlayerAction::Custom`, the `name` of the action selects the handler and `data` is passed to
+/// the handler as is.
+pub trait ActionHandler: Send + Sync {
+    /// Returns an error that is reported to the player if the unit cannot perform the action.
+    /// The engine has already checked that the unit exists and is owned by `player`.
+    fn validate(
+        &self,
+        _player: PlayerId,
+        _unit: UnitId,
+        _data: &str,
+        _world: &World,
+    ) -> Result<(), String> {
+        Ok(())
+    }
+
+    /// Returns the number of action points the action costs, `None` to use the cost of custom
+    /// actions from the rules
+    fn cost(&self, _data: &str) -> Option<usize> {
+        None
+    }
+
+    /// Applies a validated action to the world, the consequences of the action are added to
+    /// `events`. Actions are applied in the order in which they were submitted, interleaved with
+    /// the built-in actions.
+    fn apply(&self, unit: UnitId, data: &str, world: &mut World, events: &mut Vec<GameEvent>);
+}
+
+/// A `ResolutionPhase` is run at the end of every turn after the actions of all players have been
+/// applied. Phases run in the order in which they were registered.
+pub trait ResolutionPhase: Send + Sync {
+    /// Updates the world in place, the consequences are added to `events`
+    fn resolve(&self, world: &mut World, events: &mut Vec<GameEvent>);
+}
+
+/// Describes a tile type added by a plugin. Maps contain custom tiles as `TileType::Custom`.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct CustomTile {
+    /// The number that identifies the tile in `TileType::Custom`
+    pub id: u8,
+
+    /// The human readable name of the tile
+    pub name: String,
+
+    /// True if units can enter the tile
+    pub passable: bool,
+
+    /// The character with which the tile is drawn in scenario files
+    pub symbol: Option<char>,
+}
+
+impl CustomTile {
+    /// Constructs a tile that is not drawn with a specific character
+    pub fn new(id: u8, name: impl Into<String>, passable: bool) -> Self {
+        CustomTile {
+            id,
+            name: name.into(),
+            passable,
+            symbol: None,
+        }
+    }
+}
+
+/// Everything that was registered by the plugins of a battle
+#[derive(Clone, Default)]
+pub struct Plugins {
+    actions: HashMap<String, Arc<dyn ActionHandler>>,
+    tiles: Vec<CustomTile>,
+    phases: Vec<Arc<dyn ResolutionPhase>>,
+}
+
+impl Plugins {
+    /// Registers the handler of the custom action with the given name, replacing any previous
+    /// handler of the action.
+    pub fn add_action(&mut self, name: impl Into<String>, handler: impl ActionHandler + 'static) {
+        let name = name.into();
+        if self
+            .actions
+            .insert(name.clone(), Arc::new(handler))
+            .is_some()
+        {
+            log::warn!("the handler of action '{}' was replaced", name);
+        }
+    }
+
+    /// Registers a custom tile type, replacing any previous tile with the same id
+    pub fn add_tile(&mut self, tile: CustomTile) {
+        self.tiles.retain(|existing| existing.id != tile.id);
+        self.tiles.push(tile);
+    }
+
+    /// Registers a phase that is run at the end of every turn
+    pub fn add_phase(&mut self, phase: impl ResolutionPhase + 'static) {
+        self.phases.push(Arc::new(phase));
+    }
+
+    /// Returns the handler of the custom action with the given name
+    pub fn action(&self, name: &str) -> Option<&dyn ActionHandler> {
+        self.actions.get(name).map(|handler| handler.as_ref())
+    }
+
+    /// Returns the custom tile with the given id
+    pub fn tile(&self, id: u8) -> Option<&CustomTile> {
+        self.tiles.iter().find(|tile| tile.id == id)
+    }
+
+    /// Returns all registered custom tiles
+    pub fn tiles(&self) -> &[CustomTile] {
+        &self.tiles
+    }
+
+    /// Runs all resolution phases in order
+    pub(crate) fn resolve(&self, world: &mut World, events: &mut Vec<GameEvent>) {
+        for phase in self.phases.iter() {
+            phase.resolve(world, events);
+        }
+    }
+}
+
+impl std::fmt::Debug for Plugins {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Plugins")
+            .field("actions", &self.actions.keys().collect::<Vec<_>>())
+            .field("tiles", &self.tiles)
+            .field("phases", &self.phases.len())
+            .finish()
+    }
+}
@@ -0,0 +1,50 @@
+use crate::raster::rasterize;
+use crate::{BattleObserver, GameEvent, GameRules, World};
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+
+/// An observer that rasterizes every turn of a battle to a PNG file without opening a window. The
+/// frames are written to `$OUT/turn_$TURN.png`.
+pub struct PngRenderer {
+    out: PathBuf,
+}
+
+impl PngRenderer {
+    /// Constructs a renderer that writes the frames to the specified directory
+    pub fn new(out: impl Into<PathBuf>) -> Self {
+        PngRenderer { out: out.into() }
+    }
+
+    /// Rasterizes the world and writes it to the output directory
+    fn render(&self, world: &World) {
+        let path = self.out.join(format!("turn_{:05}.png", world.turn));
+        if let Err(err) = std::fs::create_dir_all(&self.out)
+            .map_err(anyhow::Error::from)
+            .and_then(|_| write_png(&path, world))
+        {
+            log::error!("could not write frame to {}: {}", path.display(), err);
+        }
+    }
+}
+
+impl BattleObserver for PngRenderer {
+    fn on_start(&mut self, world: &World, _players: &[String], _rules: &GameRules) {
+        self.render(world);
+    }
+
+    fn on_turn(&mut self, world: &World, _events: &[GameEvent]) {
+        self.render(world);
+    }
+}
+
+/// Writes the rasterized world to a PNG file at the specified path
+fn write_png(path: &Path, world: &World) -> anyhow::Result<()> {
+    let (width, height, data) = rasterize(&world.spectator_world());
+    let writer = BufWriter::new(File::create(path)?);
+    let mut encoder = png::Encoder::new(writer, width as u32, height as u32);
+    encoder.set_color(png::ColorType::RGB);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.write_header()?.write_image_data(&data)?;
+    Ok(())
+}
@@ -0,0 +1,61 @@
+use crate::palette::player_rgb;
+use mlr_api::{SpectatorWorld, TileType};
+
+/// The size in pixels of a single tile in the rasterized images
+const TILE_SIZE: usize = 8;
+
+const WALL_COLOR: [u8; 3] = [200, 200, 200];
+const FLOOR_COLOR: [u8; 3] = [32, 32, 32];
+const EXIT_COLOR: [u8; 3] = [0, 200, 200];
+const GOAL_COLOR: [u8; 3] = [200, 160, 0];
+const ITEM_COLOR: [u8; 3] = [255, 215, 0];
+const OBSTACLE_COLOR: [u8; 3] = [222, 184, 135];
+const CUSTOM_COLOR: [u8; 3] = [200, 0, 200];
+
+/// Rasterizes the world to an RGB image, returns the width, height and pixel data
+pub(crate) fn rasterize(world: &SpectatorWorld) -> (usize, usize, Vec<u8>) {
+    let width = world.width * TILE_SIZE;
+    let height = world.height * TILE_SIZE;
+    let mut data = vec![0; width * height * 3];
+
+    let mut fill = |x: usize, y: usize, margin: usize, color: [u8; 3]| {
+        for py in y * TILE_SIZE + margin..(y + 1) * TILE_SIZE - margin {
+            for px in x * TILE_SIZE + margin..(x + 1) * TILE_SIZE - margin {
+                let offset = (py * width + px) * 3;
+                data[offset..offset + 3].copy_from_slice(&color);
+            }
+        }
+    };
+
+    for (coord, tile) in world.iter_tiles() {
+        let color = match tile {
+            TileType::Wall => WALL_COLOR,
+            TileType::Floor => FLOOR_COLOR,
+            TileType::Exit => EXIT_COLOR,
+            TileType::Goal => GOAL_COLOR,
+            TileType::Custom { .. } => CUSTOM_COLOR,
+        };
+        fill(coord.x as usize, coord.y as usize, 0, color);
+    }
+
+    for obstacle in world.obstacles.iter() {
+        let location = obstacle.location;
+        fill(location.x as usize, location.y as usize, 1, OBSTACLE_COLOR);
+    }
+
+    for item in world.items.iter().filter(|item| item.carrier.is_none()) {
+        fill(
+            item.location.x as usize,
+            item.location.y as usize,
+            2,
+            ITEM_COLOR,
+        );
+    }
+
+    for unit in world.units.iter() {
+        let color = player_rgb(unit.player);
+        fill(unit.location.x as usize, unit.location.y as usize, 1, color);
+    }
+
+    (width, height, data)
+}
@@ -0,0 +1,313 @@
+use crate::{
+    BattleObserver, GameEvent, GameRules, GameState, Map, MapDiff, MatchResult, Player, Plugins,
+    World,
+};
+use mlr_api::{
+    Coord, PlayerAction, PlayerId, PlayerInput, PlayerOutput, RunnerError, SpectatorWorld,
+    API_VERSION,
+};
+use serde_derive::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// The version of the replay format. Bumped whenever a change is made that prevents older replays
+/// from being read.
+pub const REPLAY_VERSION: usize = 2;
+
+/// A `Replay` contains everything that happened during a battle.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Replay {
+    /// The version of the format of the replay
+    pub version: usize,
+
+    /// The names of the players indexed by `PlayerId`
+    pub players: Vec<String>,
+
+    /// The state of the world before the first turn was played
+    pub world: World,
+
+    /// The rules under which the battle was played
+    #[serde(default)]
+    pub rules: GameRules,
+
+    /// The events of every turn that was played
+    pub turns: Vec<Vec<GameEvent>>,
+
+    /// The hash of the world after every turn that was played, see `World::state_hash`. Empty for
+    /// replays that were recorded before hashes were stored.
+    #[serde(default)]
+    pub hashes: Vec<u64>,
+
+    /// The tiles of the map that changed during every turn that was played, see `Replay::map_at`.
+    /// Empty for replays that were recorded before map diffs were stored.
+    #[serde(default)]
+    pub map_diffs: Vec<MapDiff>,
+
+    /// The outcome of the battle, `None` if the battle didn't finish
+    pub result: Option<MatchResult>,
+}
+
+/// The encodings in which a replay can be stored
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum ReplayFormat {
+    /// Human readable JSON
+    Json,
+
+    /// Compact binary MessagePack, only available with the `msgpack` feature
+    #[cfg(feature = "msgpack")]
+    MessagePack,
+}
+
+impl ReplayFormat {
+    /// Determines the format from the extension of a path, `.msgpack` and `.mpk` files are stored
+    /// as MessagePack when the `msgpack` feature is enabled, anything else as JSON.
+    pub fn from_path(path: &Path) -> ReplayFormat {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            #[cfg(feature = "msgpack")]
+            Some("msgpack") | Some("mpk") => ReplayFormat::MessagePack,
+            _ => ReplayFormat::Json,
+        }
+    }
+}
+
+impl Replay {
+    /// Reads a replay from the file at the specified path, the format is determined by the
+    /// extension of the file.
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Replay> {
+        let path = path.as_ref();
+        let reader = BufReader::new(File::open(path)?);
+        let replay: Replay = match ReplayFormat::from_path(path) {
+            ReplayFormat::Json => serde_json::from_reader(reader)?,
+            #[cfg(feature = "msgpack")]
+            ReplayFormat::MessagePack => rmp_serde::from_read(reader)?,
+        };
+        if replay.version != REPLAY_VERSION {
+            anyhow::bail!(
+                "unsupported replay version {}, expected {}",
+                replay.version,
+                REPLAY_VERSION
+            );
+        }
+        Ok(replay)
+    }
+
+    /// Writes the replay to the file at the specified path, the format is determined by the
+    /// extension of the file.
+    pub fn save(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        let path = path.as_ref();
+        let mut writer = BufWriter::new(File::create(path)?);
+        match ReplayFormat::from_path(path) {
+            ReplayFormat::Json => serde_json::to_writer(&mut writer, self)?,
+            #[cfg(feature = "msgpack")]
+            ReplayFormat::MessagePack => rmp_serde::encode::write_named(&mut writer, self)?,
+        }
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+/// The outcome of re-simulating a replay, see `Replay::verify`
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Verification {
+    /// Every turn resulted in the same world as recorded
+    Identical { turns: usize },
+
+    /// The world diverged from the recording after playing `turn`
+    Diverged {
+        turn: usize,
+        expected: u64,
+        actual: u64,
+    },
+}
+
+/// What a player submitted during a single turn of a replay
+type RecordedOutput = Result<Vec<PlayerAction>, RunnerError>;
+
+impl Replay {
+    /// Returns a snapshot of the world before the first turn was played, for viewers that replay
+    /// the recorded events
+    pub fn spectator_world(&self) -> SpectatorWorld {
+        self.world.spectator_world()
+    }
+
+    /// Returns the map after the given number of turns was played, without re-simulating the
+    /// battle
+    pub fn map_at(&self, turns: usize) -> Map {
+        let mut map = (*self.world.map).clone();
+        let diffs = &self.map_diffs[..turns.min(self.map_diffs.len())];
+        if diffs.iter().any(|diff| !diff.is_empty()) {
+            for diff in diffs {
+                diff.apply(&mut map);
+            }
+            map.compute_distance_to_exit();
+        }
+        map
+    }
+
+    /// Re-simulates the battle by feeding the recorded actions of every player to the engine and
+    /// compares the resulting world after every turn with the recorded hashes. Reports the first
+    /// turn at which the world diverged from the recording.
+    pub async fn verify(&self) -> anyhow::Result<Verification> {
+        self.verify_with_plugins(Plugins::default()).await
+    }
+
+    /// Same as `verify` but for battles that were played with plugins, `plugins` must contain
+    /// everything that was registered for the recorded battle.
+    pub async fn verify_with_plugins(&self, plugins: Plugins) -> anyhow::Result<Verification> {
+        if self.hashes.len() != self.turns.len() {
+            anyhow::bail!("the replay does not contain a world hash for every turn");
+        }
+
+        let players = (0..self.players.len())
+            .map(|index| {
+                let id = PlayerId(index);
+                let mut outputs = self.recorded_outputs(id);
+                Player {
+                    id,
+                    name: self.players[index].clone(),
+                    runner: Box::new(
+                        move |input: PlayerInput| -> Result<PlayerOutput, RunnerError> {
+                            let actions = outputs.remove(&input.turn).unwrap_or_else(|| {
+                                Err(RunnerError::DataError(
+                                    "the player did not play this turn in the recording"
+                                        .to_string(),
+                                ))
+                            })?;
+                            Ok(PlayerOutput {
+                                version: Some(API_VERSION),
+                                actions,
+                                memory: input.memory,
+                            })
+                        },
+                    ),
+                    memory: Default::default(),
+                    penalties: Default::default(),
+                    lives: Default::default(),
+                }
+            })
+            .collect();
+
+        let mut game_state = GameState {
+            players,
+            world: self.world.clone(),
+            rules: self.rules.clone(),
+            plugins,
+            spawns: (0..self.players.len())
+                .map(|index| {
+                    self.world
+                        .units
+                        .iter()
+                        .find(|unit| unit.player == PlayerId(index))
+                        .map_or(Coord::new(0, 0), |unit| unit.location)
+                })
+                .collect(),
+            views: Default::default(),
+        };
+        for &expected in self.hashes.iter() {
+            let turn = game_state.world.turn;
+            game_state.turn().await;
+            let actual = game_state.world.state_hash();
+            if actual != expected {
+                return Ok(Verification::Diverged {
+                    turn,
+                    expected,
+                    actual,
+                });
+            }
+        }
+
+        Ok(Verification::Identical {
+            turns: self.hashes.len(),
+        })
+    }
+
+    /// Returns what the specified player submitted in every turn, indexed by turn
+    fn recorded_outputs(&self, player: PlayerId) -> HashMap<usize, RecordedOutput> {
+        let mut outputs = HashMap::new();
+        for (turn, events) in self.turns.iter().enumerate() {
+            let turn = self.world.turn + turn;
+            for event in events {
+                match event {
+                    GameEvent::ActionsSubmitted { player: p, actions } if *p == player => {
+                        outputs.insert(turn, Ok(actions.clone()));
+                    }
+                    GameEvent::RunnerError {
+                        player: p, error, ..
+                    } if *p == player => {
+                        outputs.insert(turn, Err(RunnerError::DataError(error.clone())));
+                    }
+                    GameEvent::Timeout { player: p } if *p == player => {
+                        outputs.insert(turn, Err(RunnerError::Timeout(Default::default())));
+                    }
+                    _ => {}
+                }
+            }
+        }
+        outputs
+    }
+}
+
+/// A `BattleObserver` that records a `Replay` of a battle and writes it to disk when the battle
+/// has ended.
+pub struct ReplayRecorder {
+    path: PathBuf,
+    replay: Option<Replay>,
+
+    /// The map at the end of the previous turn, to compute the tiles that changed
+    map: Option<Arc<Map>>,
+}
+
+impl ReplayRecorder {
+    /// Constructs a recorder that writes the replay to the specified path
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        ReplayRecorder {
+            path: path.into(),
+            replay: None,
+            map: None,
+        }
+    }
+}
+
+impl BattleObserver for ReplayRecorder {
+    fn on_start(&mut self, world: &World, players: &[String], rules: &GameRules) {
+        self.replay = Some(Replay {
+            version: REPLAY_VERSION,
+            players: players.to_vec(),
+            world: world.clone(),
+            rules: rules.clone(),
+            turns: Vec::new(),
+            hashes: Vec::new(),
+            map_diffs: Vec::new(),
+            result: None,
+        });
+        self.map = Some(world.map.clone());
+    }
+
+    fn on_turn(&mut self, world: &World, events: &[GameEvent]) {
+        if let Some(replay) = &mut self.replay {
+            replay.turns.push(events.to_vec());
+            replay.hashes.push(world.state_hash());
+
+            // Holding on to the previous map makes the world copy its map before changing it, so
+            // an unchanged pointer means nothing changed
+            let diff = match &self.map {
+                Some(map) if !Arc::ptr_eq(map, &world.map) => MapDiff::between(map, &world.map),
+                _ => MapDiff::default(),
+            };
+            replay.map_diffs.push(diff);
+            self.map = Some(world.map.clone());
+        }
+    }
+
+    fn on_end(&mut self, _world: &World, result: &MatchResult) {
+        if let Some(mut replay) = self.replay.take() {
+            replay.result = Some(result.clone());
+            if let Err(err) = replay.save(&self.path) {
+                log::error!("could not write replay to {}: {}", self.path.display(), err);
+            }
+        }
+    }
+}
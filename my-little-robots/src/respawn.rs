@@ -0,0 +1,155 @@
+use crate::{GameEvent, GameState};
+use mlr_api::{Coord, PlayerId};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use serde_derive::{Deserialize, Serialize};
+
+/// Describes whether and how units that died come back into the battle
+#[derive(Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RespawnRules {
+    /// True if units that died respawn
+    pub enabled: bool,
+
+    /// The number of turns after which a unit that died respawns
+    pub delay: usize,
+
+    /// Where respawned units appear
+    pub spawn: SpawnSelection,
+
+    /// The number of times units of a single player can respawn, `None` for no limit
+    pub max_lives: Option<usize>,
+}
+
+impl Default for RespawnRules {
+    fn default() -> Self {
+        RespawnRules {
+            enabled: false,
+            delay: 3,
+            spawn: SpawnSelection::default(),
+            max_lives: None,
+        }
+    }
+}
+
+/// Selects the location at which a unit respawns from the locations where the players started
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SpawnSelection {
+    /// The location where the player started the battle
+    Own,
+
+    /// A random location where any player started the battle
+    Random,
+
+    /// The location where any player started the battle that is the furthest away from the
+    /// closest unit of another player
+    FarthestFromEnemies,
+}
+
+impl Default for SpawnSelection {
+    fn default() -> Self {
+        SpawnSelection::Own
+    }
+}
+
+/// The units of a single player that died and respawned during a battle
+#[derive(Clone, Debug, Default, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub struct PlayerLives {
+    /// The number of units of the player that died
+    pub deaths: usize,
+
+    /// The number of units of the player that respawned
+    pub respawns: usize,
+
+    /// The turns at the end of which a unit of the player respawns
+    #[serde(skip)]
+    pending: Vec<usize>,
+}
+
+impl PlayerLives {
+    /// Returns true if a unit of the player is waiting to respawn
+    pub fn is_respawning(&self) -> bool {
+        !self.pending.is_empty()
+    }
+}
+
+impl GameState {
+    /// Schedules respawns for the units that died during the turn and spawns the units whose
+    /// respawn is due. Units that cannot be placed because their spawn point is blocked try again
+    /// next turn.
+    pub(crate) fn respawn_units(&mut self, events: &mut Vec<GameEvent>) {
+        let rules = &self.rules.respawn;
+        let turn = self.world.turn;
+        let dead = events
+            .iter()
+            .filter_map(|event| match event {
+                GameEvent::UnitDied { player, .. } => Some(*player),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        for player in dead {
+            let lives = &mut self.players[player.0].lives;
+            lives.deaths += 1;
+            let used = lives.respawns + lives.pending.len();
+            if rules.enabled && rules.max_lives.map_or(true, |max| used < max) {
+                lives.pending.push(turn + rules.delay);
+            }
+        }
+
+        for index in 0..self.players.len() {
+            let player = PlayerId(index);
+            let due = self.players[index]
+                .lives
+                .pending
+                .iter()
+                .filter(|&&respawn_turn| respawn_turn <= turn)
+                .count();
+            for _ in 0..due {
+                let location = match self.spawn_location(player) {
+                    Some(location) => location,
+                    None => break,
+                };
+                let unit = self.world.spawn_unit(player, location);
+                if let Some(unit) = self.world.unit_mut(unit) {
+                    unit.energy = self.rules.starting_energy;
+                }
+                let lives = &mut self.players[index].lives;
+                lives.pending.remove(0);
+                lives.respawns += 1;
+                events.push(GameEvent::UnitRespawned {
+                    player,
+                    unit,
+                    location,
+                });
+            }
+        }
+    }
+
+    /// Returns the location at which a unit of the given player respawns, `None` if the location
+    /// cannot be entered
+    fn spawn_location(&self, player: PlayerId) -> Option<Coord> {
+        let location = match self.rules.respawn.spawn {
+            SpawnSelection::Own => *self.spawns.get(player.0)?,
+            SpawnSelection::Random => {
+                let seed = self.rules.seed ^ ((self.world.turn as u64) << 16) ^ player.0 as u64;
+                *self.spawns.choose(&mut StdRng::seed_from_u64(seed))?
+            }
+            SpawnSelection::FarthestFromEnemies => *self.spawns.iter().max_by_key(|spawn| {
+                self.world
+                    .units
+                    .iter()
+                    .filter(|unit| unit.player != player)
+                    .map(|unit| unit.location.manhattan_distance(**spawn))
+                    .min()
+                    .unwrap_or(usize::MAX)
+            })?,
+        };
+        if self.world.can_enter(location) {
+            Some(location)
+        } else {
+            None
+        }
+    }
+}
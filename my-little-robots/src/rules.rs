@@ -0,0 +1,170 @@
+use crate::{MapChange, PenaltyRules, RespawnRules, Weather};
+use mlr_api::{ActionCosts, ActionKind};
+use serde_derive::{Deserialize, Serialize};
+
+/// The rules under which a battle is played. Rules that are missing when deserializing take their
+/// default value.
+#[derive(Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GameRules {
+    /// The number of tiles a unit can see in every direction, unless the weather limits it
+    pub vision_range: usize,
+
+    /// The weather during the battle, see `GameRules::vision_range_at`
+    pub weather: Vec<Weather>,
+
+    /// The maximum number of turns after which the battle ends. The player that made the most
+    /// progress towards the objective wins, see `MatchResult::ranking`.
+    pub max_turns: Option<usize>,
+
+    /// The kinds of actions that players are allowed to perform, other actions are rejected
+    pub actions: Vec<ActionKind>,
+
+    /// The number of action points every unit can spend per turn. `None` lets units perform any
+    /// number of actions.
+    pub action_points: Option<usize>,
+
+    /// The number of action points every kind of action costs
+    pub action_costs: ActionCosts,
+
+    /// How many tiles far a unit that moves can be heard by the units of other players, zero
+    /// makes moving silent
+    pub move_noise: usize,
+
+    /// The maximum size in bytes of the memory a player can keep between turns, measured as it is
+    /// sent to the player, see `Memory::size`
+    pub max_memory_size: usize,
+
+    /// How players that send invalid actions or fail to run are punished
+    pub penalties: PenaltyRules,
+
+    /// How the winner of the battle is decided
+    pub win_condition: WinCondition,
+
+    /// The number of items a player has to deliver to a goal tile to win, only used with the
+    /// `deliver_items` win condition
+    pub items_to_deliver: usize,
+
+    /// The percentage of the floor tiles a player has to see with its own units to win, only used
+    /// with the `explore` win condition
+    pub exploration_target: usize,
+
+    /// A unit that carries an item can move only once every this many turns
+    pub carry_slowdown: usize,
+
+    /// The energy units start with, `None` if units do not use energy
+    pub starting_energy: Option<u32>,
+
+    /// The energy a unit spends on a sensor link
+    pub link_energy: u32,
+
+    /// How many tiles further a unit sees during the turn after another unit linked to it
+    pub link_range: usize,
+
+    /// How the winner is decided when multiple players complete the objective in the same turn
+    pub tie_break: TieBreak,
+
+    /// The scripted changes of the map during the battle
+    pub map_changes: Vec<MapChange>,
+
+    /// If specified every exit moves to a random reachable floor tile every this many turns
+    pub moving_exit: Option<usize>,
+
+    /// The largest difference in walking distance to the closest exit between the spawn points
+    /// the engine picks for the players, `None` to not balance the spawn points. Spawn points
+    /// that are set explicitly are used as is.
+    pub spawn_tolerance: Option<usize>,
+
+    /// Whether and how units that died come back
+    pub respawn: RespawnRules,
+
+    /// The seed of all random choices the engine makes during the battle, like where exits move
+    pub seed: u64,
+
+    /// If specified every player is sent a `MatchStart` before the first turn and may take this
+    /// many milliseconds to prepare for the battle, apart from the time limit of every turn
+    pub warm_up: Option<u64>,
+}
+
+/// Describes how the winner of a battle is decided. When all other players have been eliminated
+/// the last remaining player always wins.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WinCondition {
+    /// The first player to move a unit onto an exit wins
+    ReachExit,
+
+    /// The first player to deliver enough items to goal tiles wins, see
+    /// `GameRules::items_to_deliver`
+    DeliverItems,
+
+    /// The first player to see enough of the floor tiles of the map with its own units wins, see
+    /// `GameRules::exploration_target`
+    Explore,
+
+    /// Exits don't end the battle, only outlasting the other players does
+    LastPlayerStanding,
+}
+
+impl Default for WinCondition {
+    fn default() -> Self {
+        WinCondition::ReachExit
+    }
+}
+
+/// Decides the outcome of a battle in which multiple players complete the objective of the battle,
+/// like reaching an exit, in the same turn.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TieBreak {
+    /// The battle ends in a draw
+    Draw,
+
+    /// The player whose runner took the least time over all turns wins. If those times are equal
+    /// as well the battle ends in a draw. Without the `native` feature runners are not timed and
+    /// this is the same as `Draw`.
+    LowestTime,
+}
+
+impl Default for TieBreak {
+    fn default() -> Self {
+        TieBreak::Draw
+    }
+}
+
+impl Default for GameRules {
+    fn default() -> Self {
+        GameRules {
+            vision_range: 7,
+            weather: Vec::new(),
+            max_turns: None,
+            actions: vec![
+                ActionKind::Move,
+                ActionKind::Mark,
+                ActionKind::PickUp,
+                ActionKind::Drop,
+                ActionKind::Link,
+                ActionKind::Break,
+            ],
+            action_points: None,
+            action_costs: ActionCosts::default(),
+            move_noise: 5,
+            max_memory_size: 64 * 1024,
+            penalties: PenaltyRules::default(),
+            win_condition: WinCondition::default(),
+            items_to_deliver: 1,
+            exploration_target: 80,
+            carry_slowdown: 2,
+            starting_energy: None,
+            link_energy: 10,
+            link_range: 3,
+            tie_break: TieBreak::default(),
+            map_changes: Vec::new(),
+            moving_exit: None,
+            spawn_tolerance: None,
+            respawn: RespawnRules::default(),
+            seed: 0,
+            warm_up: None,
+        }
+    }
+}
@@ -1,21 +1,56 @@
+#[cfg(feature = "native")]
 mod async_runner;
+mod builtin;
+#[cfg(feature = "native")]
 mod native_runner;
+#[cfg(feature = "native")]
+mod process_group;
+#[cfg(feature = "native")]
+mod scheduler;
+#[cfg(feature = "native")]
+mod transcript;
+#[cfg(feature = "native")]
 mod wasi_runner;
 
+#[cfg(feature = "native")]
+pub use crate::runner::async_runner::MessageLimits;
+pub use crate::runner::builtin::BuiltinRunner;
+#[cfg(feature = "native")]
+pub use crate::runner::native_runner::CommandOptions;
+#[cfg(feature = "native")]
 use crate::runner::native_runner::CommandRunner;
+#[cfg(feature = "native")]
+pub use crate::runner::scheduler::{Acquire, RunnerPermit, RunnerScheduler};
+#[cfg(feature = "native")]
+use crate::runner::transcript::Transcript;
+#[cfg(feature = "native")]
 use crate::runner::wasi_runner::WasiRunner;
 use crate::PlayerRunner;
-use mlr_api::{PlayerInput, PlayerOutput, RunnerError};
+#[cfg(feature = "native")]
+use async_io::Timer;
+#[cfg(feature = "native")]
+use futures::future::{self, Either};
+use mlr_api::{MatchStart, PlayerInput, PlayerOutput, RunnerError};
+#[cfg(feature = "native")]
 use std::ffi::OsStr;
-use std::path::PathBuf;
+#[cfg(feature = "native")]
+use std::future::Future;
+#[cfg(feature = "native")]
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
-/// A runner is something that can perform a player step
+/// A runner is something that can perform a player step. Runners that start programs are only
+/// available with the `native` feature.
 pub enum Runner {
+    #[cfg(feature = "native")]
     Command(CommandRunner),
+    #[cfg(feature = "native")]
     Wasi(WasiRunner),
+    Builtin(BuiltinRunner),
 }
 
 impl Runner {
+    #[cfg(feature = "native")]
     pub fn new_cmd(
         command: impl AsRef<OsStr>,
         args: impl IntoIterator<Item = impl AsRef<OsStr>>,
@@ -23,17 +58,92 @@ impl Runner {
         Runner::Command(CommandRunner::new(command, args))
     }
 
+    /// Same as `new_cmd` but starts the command with the given working directory and environment
+    #[cfg(feature = "native")]
+    pub fn new_cmd_with_options(
+        command: impl AsRef<OsStr>,
+        args: impl IntoIterator<Item = impl AsRef<OsStr>>,
+        options: CommandOptions,
+    ) -> Runner {
+        Runner::Command(CommandRunner::new(command, args).with_options(options))
+    }
+
+    #[cfg(feature = "native")]
     pub fn new_wasm(path_to_module: PathBuf) -> anyhow::Result<Runner> {
         Ok(Runner::Wasi(WasiRunner::new(path_to_module)?))
     }
+
+    pub fn new_builtin(builtin: BuiltinRunner) -> Runner {
+        Runner::Builtin(builtin)
+    }
+
+    /// Writes the exact bytes exchanged with the program every turn to the file at `path`. Builtin
+    /// runners do not exchange any bytes, nothing is written for them.
+    #[cfg(feature = "native")]
+    pub fn with_transcript(self, path: &Path) -> std::io::Result<Runner> {
+        Ok(match self {
+            Runner::Command(cmd) => Runner::Command(cmd.with_transcript(Transcript::create(path)?)),
+            Runner::Wasi(wasi) => Runner::Wasi(wasi.with_transcript(Transcript::create(path)?)),
+            Runner::Builtin(builtin) => Runner::Builtin(builtin),
+        })
+    }
+
+    /// Makes the runner wait for room in the scheduler before it starts its program every turn.
+    /// Builtin runners do not start a program, they are never limited.
+    #[cfg(feature = "native")]
+    pub fn with_scheduler(self, scheduler: RunnerScheduler) -> Runner {
+        match self {
+            Runner::Command(cmd) => Runner::Command(cmd.with_scheduler(scheduler)),
+            Runner::Wasi(wasi) => Runner::Wasi(wasi.with_scheduler(scheduler)),
+            Runner::Builtin(builtin) => Runner::Builtin(builtin),
+        }
+    }
 }
 
 #[async_trait::async_trait]
 impl PlayerRunner for Runner {
+    async fn ready(&mut self) {
+        match self {
+            #[cfg(feature = "native")]
+            Runner::Command(cmd) => cmd.ready().await,
+            #[cfg(feature = "native")]
+            Runner::Wasi(wasi) => wasi.ready().await,
+            Runner::Builtin(builtin) => builtin.ready().await,
+        }
+    }
+
     async fn run(&mut self, input: PlayerInput) -> Result<PlayerOutput, RunnerError> {
         match self {
+            #[cfg(feature = "native")]
             Runner::Command(cmd) => cmd.run(input).await,
+            #[cfg(feature = "native")]
             Runner::Wasi(wasi) => wasi.run(input).await,
+            Runner::Builtin(builtin) => builtin.run(input).await,
+        }
+    }
+
+    async fn start_match(
+        &mut self,
+        start: MatchStart,
+        timeout: Duration,
+    ) -> Result<PlayerOutput, RunnerError> {
+        match self {
+            #[cfg(feature = "native")]
+            Runner::Command(cmd) => cmd.start_match(start, timeout).await,
+            #[cfg(feature = "native")]
+            Runner::Wasi(wasi) => wasi.start_match(start, timeout).await,
+            Runner::Builtin(builtin) => builtin.start_match(start, timeout).await,
         }
     }
 }
+
+/// Awaits the given future but gives up once `duration` has passed. Relies only on `async-io`
+/// timers so it works regardless of the executor the engine is running on.
+#[cfg(feature = "native")]
+pub(crate) async fn timeout<F: Future>(duration: Duration, future: F) -> Option<F::Output> {
+    futures::pin_mut!(future);
+    match future::select(future, Timer::after(duration)).await {
+        Either::Left((output, _)) => Some(output),
+        Either::Right(_) => None,
+    }
+}
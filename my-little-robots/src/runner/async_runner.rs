@@ -1,43 +1,245 @@
 use crate::PlayerRunner;
-use futures::{stream::StreamExt, AsyncBufRead, AsyncBufReadExt, AsyncWrite, AsyncWriteExt};
-use mlr_api::{PlayerInput, PlayerMemory, PlayerOutput, RunnerError};
+use futures::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use mlr_api::{
+    MatchStart, MatchStartMessage, PlayerId, PlayerInput, PlayerMemory, PlayerOutput, RunnerError,
+    INPUT_CHUNK_PREFIX, INPUT_END,
+};
+
+/// Limits the size of the messages exchanged with a program. Some languages read lines into a
+/// fixed size buffer, inputs longer than `max_line_length` are therefore written in parts, see
+/// `INPUT_CHUNK_PREFIX`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct MessageLimits {
+    /// The longest line written to the program, without the line break
+    pub max_line_length: usize,
+
+    /// The largest input or output in bytes, larger messages fail the turn of the player
+    pub max_message_size: usize,
+}
+
+impl Default for MessageLimits {
+    fn default() -> Self {
+        MessageLimits {
+            max_line_length: 1024 * 1024,
+            max_message_size: 64 * 1024 * 1024,
+        }
+    }
+}
+
+/// A message the engine sends to a program, which answers each with a `PlayerOutput`
+pub(crate) enum Message {
+    Turn(PlayerInput),
+    MatchStart(MatchStart),
+}
+
+impl Message {
+    pub fn player_id(&self) -> PlayerId {
+        match self {
+            Message::Turn(input) => input.player_id,
+            Message::MatchStart(start) => start.player_id,
+        }
+    }
+
+    /// Describes the message in a transcript
+    pub fn heading(&self) -> String {
+        match self {
+            Message::Turn(input) => format!("turn {}", input.turn),
+            Message::MatchStart(_) => "match start".to_string(),
+        }
+    }
+
+    pub fn to_json(self) -> serde_json::Result<String> {
+        match self {
+            Message::Turn(input) => serde_json::to_string(&input),
+            Message::MatchStart(start) => {
+                serde_json::to_string(&MatchStartMessage { match_start: start })
+            }
+        }
+    }
+}
 
 pub struct AsyncRunner<W: AsyncWrite, R: AsyncBufRead> {
     stdout: R,
     stdin: W,
+    limits: MessageLimits,
 }
 
 impl<W: AsyncWrite + Unpin + Send, R: AsyncBufRead + Unpin + Send> AsyncRunner<W, R> {
     pub fn new(stdin: W, stdout: R) -> Self {
-        Self { stdin, stdout }
+        Self {
+            stdin,
+            stdout,
+            limits: MessageLimits::default(),
+        }
+    }
+
+    pub fn with_limits(mut self, limits: MessageLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Writes the input as a single line, or in parts followed by `INPUT_END` if it does not fit
+    async fn write_input(&mut self, input_json: &str) -> Result<(), RunnerError> {
+        if input_json.len() <= self.limits.max_line_length {
+            self.stdin.write_all(input_json.as_bytes()).await?;
+            self.stdin.write_all(b"\n").await?;
+            return Ok(());
+        }
+
+        let chunk_size = self
+            .limits
+            .max_line_length
+            .saturating_sub(INPUT_CHUNK_PREFIX.len())
+            .max(4);
+        for chunk in split_at_char_boundaries(input_json, chunk_size) {
+            self.stdin.write_all(INPUT_CHUNK_PREFIX.as_bytes()).await?;
+            self.stdin.write_all(chunk.as_bytes()).await?;
+            self.stdin.write_all(b"\n").await?;
+        }
+        self.stdin.write_all(INPUT_END.as_bytes()).await?;
+        self.stdin.write_all(b"\n").await?;
+        Ok(())
     }
 }
 
-#[async_trait::async_trait]
-impl<W: AsyncWrite + Unpin + Send, R: AsyncBufRead + Unpin + Send> PlayerRunner
-    for AsyncRunner<W, R>
-{
-    async fn run(
-        &mut self,
-        input: PlayerInput<PlayerMemory>,
-    ) -> Result<PlayerOutput<PlayerMemory>, RunnerError> {
-        let mut input_json = serde_json::to_vec(&input)?;
-        input_json.push(b'\n');
-        self.stdin.write(&input_json).await?;
+/// Splits the text in parts of at most `size` bytes without splitting a character. `size` must be
+/// at least 4, the length of the longest character.
+fn split_at_char_boundaries(text: &str, size: usize) -> Vec<&str> {
+    let mut chunks = Vec::with_capacity(text.len() / size + 1);
+    let mut start = 0;
+    while start < text.len() {
+        let mut end = (start + size).min(text.len());
+        while !text.is_char_boundary(end) {
+            end -= 1;
+        }
+        chunks.push(&text[start..end]);
+        start = end;
+    }
+    chunks
+}
+
+impl<W: AsyncWrite + Unpin + Send, R: AsyncBufRead + Unpin + Send> AsyncRunner<W, R> {
+    /// Writes the message to the program and reads its output
+    pub async fn send(&mut self, message: Message) -> Result<PlayerOutput, RunnerError> {
+        let player_id = message.player_id();
+        let input_json = message.to_json()?;
+        if input_json.len() > self.limits.max_message_size {
+            return Err(RunnerError::MessageTooLarge {
+                size: input_json.len(),
+                limit: self.limits.max_message_size,
+            });
+        }
+        self.write_input(&input_json).await?;
         self.stdin.flush().await?;
 
-        let mut lines = (&mut self.stdout).lines();
+        let max_message_size = self.limits.max_message_size;
+        let mut buffer = Vec::new();
         loop {
-            let line = lines
-                .next()
+            // Reads at most the line break past the limit, so a program that writes a huge line
+            // without a line break is stopped once it passes the limit instead of filling up the
+            // memory
+            buffer.clear();
+            let read = (&mut self.stdout)
+                .take(max_message_size as u64 + 2)
+                .read_until(b'\n', &mut buffer)
                 .await
-                .ok_or(RunnerError::NoData)?
                 .map_err(|_| RunnerError::NoData)?;
+            if read == 0 {
+                return Err(RunnerError::NoData);
+            }
+            if buffer.ends_with(b"\n") {
+                buffer.pop();
+                if buffer.ends_with(b"\r") {
+                    buffer.pop();
+                }
+            }
+            if buffer.len() > max_message_size {
+                return Err(RunnerError::MessageTooLarge {
+                    size: buffer.len(),
+                    limit: max_message_size,
+                });
+            }
+            let line = std::str::from_utf8(&buffer)
+                .map_err(|_| RunnerError::DataError("the output is not valid UTF-8".to_string()))?;
             if let Some(output) = line.strip_prefix("__mlr_output:") {
                 return Ok(serde_json::from_str::<PlayerOutput>(output)?);
             } else {
-                println!("Player {:?}: {}", input.player_id, line);
+                println!("Player {:?}: {}", player_id, line);
             }
         }
     }
 }
+
+#[async_trait::async_trait]
+impl<W: AsyncWrite + Unpin + Send, R: AsyncBufRead + Unpin + Send> PlayerRunner
+    for AsyncRunner<W, R>
+{
+    async fn run(
+        &mut self,
+        input: PlayerInput<PlayerMemory>,
+    ) -> Result<PlayerOutput<PlayerMemory>, RunnerError> {
+        self.send(Message::Turn(input)).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{views::ViewCache, GameRules, World};
+    use futures::executor::block_on;
+    use futures::io::{self, BufReader, Cursor};
+    use mlr_api::API_VERSION;
+
+    const MAX_MESSAGE_SIZE: usize = 64 * 1024;
+
+    fn match_start() -> Message {
+        let world = World::default();
+        let rules = GameRules::default();
+        let (player_world, _) = world.player_world(PlayerId(0), &rules, &ViewCache::default());
+        Message::MatchStart(MatchStart {
+            version: API_VERSION,
+            player_id: PlayerId(0),
+            world_info: world.world_info(&rules),
+            world: player_world,
+            memory: PlayerMemory::default(),
+        })
+    }
+
+    fn limits() -> MessageLimits {
+        MessageLimits {
+            max_message_size: MAX_MESSAGE_SIZE,
+            ..MessageLimits::default()
+        }
+    }
+
+    #[test]
+    fn output_line_without_end_is_rejected() {
+        let stdout = BufReader::new(io::repeat(b'x'));
+        let mut runner = AsyncRunner::new(io::sink(), stdout).with_limits(limits());
+        let result = block_on(runner.send(match_start()));
+        assert!(matches!(
+            result,
+            Err(RunnerError::MessageTooLarge { size, limit: MAX_MESSAGE_SIZE })
+                if size > MAX_MESSAGE_SIZE
+        ));
+    }
+
+    #[test]
+    fn output_lines_up_to_the_limit_are_accepted() {
+        let output = "__mlr_output:{\"actions\":[],\"memory\":{}}";
+        let log = "x".repeat(MAX_MESSAGE_SIZE);
+        let stdout = Cursor::new(format!("{}\r\n{}\n", log, output).into_bytes());
+        let mut runner = AsyncRunner::new(io::sink(), stdout).with_limits(limits());
+        let output = block_on(runner.send(match_start())).unwrap();
+        assert!(output.actions.is_empty());
+    }
+
+    #[test]
+    fn output_lines_past_the_limit_are_rejected() {
+        let log = "x".repeat(MAX_MESSAGE_SIZE + 1);
+        let stdout = Cursor::new(format!("{}\n", log).into_bytes());
+        let mut runner = AsyncRunner::new(io::sink(), stdout).with_limits(limits());
+        let result = block_on(runner.send(match_start()));
+        assert!(matches!(result, Err(RunnerError::MessageTooLarge { .. })));
+    }
+}
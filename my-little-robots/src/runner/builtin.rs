@@ -0,0 +1,304 @@
+use crate::{Map, PlayerRunner};
+use mlr_api::{
+    ActionKind, Coord, Direction, PlayerAction, PlayerInput, PlayerMemory, PlayerOutput,
+    PlayerWorld, RunnerError, TileGrid, TileType, Unit, API_VERSION,
+};
+use rand::seq::SliceRandom;
+use rand::Rng;
+use std::str::FromStr;
+use thiserror::Error;
+
+/// A bot that is built into the engine and doesn't require an external program to run.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum BuiltinRunner {
+    /// Moves every unit in a random direction each turn
+    Random,
+
+    /// Walks every unit around randomly without bumping into walls it can see
+    Easy,
+
+    /// Walks every unit along the shortest path to the closest exit it can see, tiles it cannot
+    /// see are assumed to be floor
+    Medium,
+
+    /// Remembers every tile it has seen in its memory and walks every unit along the shortest
+    /// path to the closest exit it knows of, exploring the closest unknown tile otherwise
+    Hard,
+}
+
+impl BuiltinRunner {
+    /// Returns all built-in bots
+    pub fn all() -> Vec<BuiltinRunner> {
+        vec![
+            BuiltinRunner::Random,
+            BuiltinRunner::Easy,
+            BuiltinRunner::Medium,
+            BuiltinRunner::Hard,
+        ]
+    }
+
+    /// Returns the name by which this bot can be selected
+    pub fn name(self) -> &'static str {
+        match self {
+            BuiltinRunner::Random => "random",
+            BuiltinRunner::Easy => "easy",
+            BuiltinRunner::Medium => "medium",
+            BuiltinRunner::Hard => "hard",
+        }
+    }
+
+    /// Returns a short description of the behavior of this bot
+    pub fn description(self) -> &'static str {
+        match self {
+            BuiltinRunner::Random => "moves every unit in a random direction each turn",
+            BuiltinRunner::Easy => "walks around randomly without bumping into walls",
+            BuiltinRunner::Medium => "walks to the closest exit in sight",
+            BuiltinRunner::Hard => "remembers the map and explores it to find the exit",
+        }
+    }
+}
+
+/// An error that is returned when a built-in bot is requested that doesn't exist
+#[derive(Error, Debug)]
+#[error("unknown built-in bot {0:?}")]
+pub struct UnknownBuiltinError(String);
+
+impl FromStr for BuiltinRunner {
+    type Err = UnknownBuiltinError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        BuiltinRunner::all()
+            .into_iter()
+            .find(|builtin| builtin.name() == s)
+            .ok_or_else(|| UnknownBuiltinError(s.to_string()))
+    }
+}
+
+#[async_trait::async_trait]
+impl PlayerRunner for BuiltinRunner {
+    async fn run(&mut self, input: PlayerInput) -> Result<PlayerOutput, RunnerError> {
+        let mut rng = rand::thread_rng();
+        let mut memory = input.memory;
+        let units = input.world.my_units(input.player_id);
+        let actions = match self {
+            _ if !input.world_info.is_allowed(ActionKind::Move) => Vec::new(),
+            BuiltinRunner::Random => units
+                .filter_map(|unit| move_unit(unit, Some(Direction::random(&mut rng))))
+                .collect(),
+            BuiltinRunner::Easy => {
+                let grid = input.world.tile_grid();
+                units
+                    .filter_map(|unit| move_unit(unit, random_step(&grid, unit.location, &mut rng)))
+                    .collect()
+            }
+            BuiltinRunner::Medium => {
+                let grid = input.world.tile_grid();
+                let map = known_map(input.world_info.width, input.world_info.height, grid.iter());
+                let exit_in_sight = grid.iter().any(|(_, tile)| tile == TileType::Exit);
+                units
+                    .filter_map(|unit| {
+                        let step = if exit_in_sight {
+                            step_towards_exit(&map, unit.location)
+                        } else {
+                            None
+                        };
+                        move_unit(
+                            unit,
+                            step.or_else(|| random_step(&grid, unit.location, &mut rng)),
+                        )
+                    })
+                    .collect()
+            }
+            BuiltinRunner::Hard => {
+                let mut known = KnownTiles::from_memory(
+                    &memory,
+                    input.world_info.width,
+                    input.world_info.height,
+                );
+                known.update(&input.world);
+                let map = known.to_map();
+                let grid = input.world.tile_grid();
+                let actions = units
+                    .filter_map(|unit| {
+                        let step = known.step_towards_goal(&map, unit.location);
+                        move_unit(
+                            unit,
+                            step.or_else(|| random_step(&grid, unit.location, &mut rng)),
+                        )
+                    })
+                    .collect();
+                memory = known.into_memory();
+                actions
+            }
+        };
+
+        Ok(PlayerOutput {
+            actions,
+            memory,
+            version: Some(API_VERSION),
+        })
+    }
+}
+
+/// Returns the action that moves `unit` in `direction`, if there is a direction to move in
+fn move_unit(unit: &Unit, direction: Option<Direction>) -> Option<PlayerAction> {
+    direction.map(|direction| PlayerAction::Move {
+        unit: unit.id,
+        direction,
+    })
+}
+
+/// Returns a random direction in which a visible tile can be entered from `location`
+fn random_step(grid: &TileGrid, location: Coord, rng: &mut impl Rng) -> Option<Direction> {
+    let directions = Direction::all_directions()
+        .into_iter()
+        .filter(|&direction| {
+            grid.get(location + direction)
+                .map_or(false, TileType::can_enter)
+        })
+        .collect::<Vec<_>>();
+    directions.choose(rng).copied()
+}
+
+/// Constructs a map of the given size from the known tiles, all other tiles are assumed to be floor
+fn known_map(width: usize, height: usize, tiles: impl Iterator<Item = (Coord, TileType)>) -> Map {
+    let mut map = Map::new(width, height);
+    for (coord, tile) in tiles {
+        if map.in_bounds(coord) {
+            map[coord] = tile;
+        }
+    }
+    map.compute_distance_to_exit();
+    map
+}
+
+/// Returns the direction of the neighbor of `location` that is closest to an exit
+fn step_towards_exit(map: &Map, location: Coord) -> Option<Direction> {
+    Direction::all_directions()
+        .into_iter()
+        .filter(|&direction| map.can_enter_tile(location + direction))
+        .filter_map(|direction| {
+            map.get_distance_to_exit(location + direction)
+                .map(|distance| (distance, direction))
+        })
+        .min_by_key(|&(distance, _)| distance)
+        .map(|(_, direction)| direction)
+}
+
+/// The tiles the `Hard` bot has seen so far, stored in its memory with a single byte per tile
+struct KnownTiles {
+    width: usize,
+    height: usize,
+    tiles: Vec<Option<TileType>>,
+}
+
+impl KnownTiles {
+    /// Restores the known tiles from memory, starts over if the memory does not describe a map of
+    /// the given size
+    fn from_memory(memory: &PlayerMemory, width: usize, height: usize) -> Self {
+        let tiles = match memory.as_bytes() {
+            Some(bytes) if bytes.len() == width * height => {
+                bytes.iter().map(|&byte| decode_tile(byte)).collect()
+            }
+            _ => vec![None; width * height],
+        };
+        KnownTiles {
+            width,
+            height,
+            tiles,
+        }
+    }
+
+    /// Stores the known tiles in memory
+    fn into_memory(self) -> PlayerMemory {
+        self.tiles
+            .into_iter()
+            .map(encode_tile)
+            .collect::<Vec<_>>()
+            .into()
+    }
+
+    /// Remembers the tiles that are visible this turn
+    fn update(&mut self, world: &PlayerWorld) {
+        for tile in world.tiles.iter() {
+            if let Some(index) = self.index(tile.coord) {
+                self.tiles[index] = Some(tile.tile_type);
+            }
+        }
+    }
+
+    /// Constructs a map in which all unknown tiles are assumed to be floor
+    fn to_map(&self) -> Map {
+        known_map(
+            self.width,
+            self.height,
+            self.coords()
+                .zip(self.tiles.iter())
+                .filter_map(|(coord, tile)| tile.map(|tile| (coord, tile))),
+        )
+    }
+
+    /// Returns the first step on the shortest path to the closest known exit, or to the closest
+    /// unknown tile if no exit is known
+    fn step_towards_goal(&self, map: &Map, location: Coord) -> Option<Direction> {
+        let exits = self
+            .coords()
+            .zip(self.tiles.iter())
+            .filter(|(_, tile)| **tile == Some(TileType::Exit))
+            .map(|(coord, _)| coord)
+            .collect::<Vec<_>>();
+        let path = if exits.is_empty() {
+            let unknown = self
+                .coords()
+                .zip(self.tiles.iter())
+                .filter(|(coord, tile)| tile.is_none() && *coord != location)
+                .map(|(coord, _)| coord)
+                .min_by_key(|coord| coord.manhattan_distance(location))?;
+            map.find_path(location, unknown)
+        } else {
+            exits
+                .into_iter()
+                .filter_map(|exit| map.find_path(location, exit))
+                .min_by_key(Vec::len)
+        };
+        path.and_then(|path| path.first().copied())
+    }
+
+    fn index(&self, coord: Coord) -> Option<usize> {
+        if coord.x < 0
+            || coord.y < 0
+            || coord.x as usize >= self.width
+            || coord.y as usize >= self.height
+        {
+            None
+        } else {
+            Some(coord.x as usize + coord.y as usize * self.width)
+        }
+    }
+
+    /// Returns the coordinates of all tiles, in the order in which they are stored
+    fn coords(&self) -> impl Iterator<Item = Coord> {
+        let width = self.width;
+        (0..self.width * self.height).map(move |index| Coord::new(index % width, index / width))
+    }
+}
+
+/// Encodes a tile into the byte stored in the memory of the `Hard` bot
+fn encode_tile(tile: Option<TileType>) -> u8 {
+    match tile {
+        None => 0,
+        Some(TileType::Exit) => 3,
+        Some(tile) if tile.can_enter() => 2,
+        Some(_) => 1,
+    }
+}
+
+/// Decodes a tile stored in the memory of the `Hard` bot
+fn decode_tile(byte: u8) -> Option<TileType> {
+    match byte {
+        1 => Some(TileType::Wall),
+        2 => Some(TileType::Floor),
+        3 => Some(TileType::Exit),
+        _ => None,
+    }
+}
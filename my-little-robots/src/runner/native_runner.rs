@@ -1,15 +1,41 @@
-use crate::{runner::async_runner::AsyncRunner, PlayerRunner};
+use crate::{
+    runner::{
+        async_runner::{AsyncRunner, Message, MessageLimits},
+        process_group::{spawn_in_new_group, ProcessGroup},
+        scheduler::{RunnerScheduler, SchedulerSlot},
+        timeout,
+        transcript::{Recorded, Recording, Transcript},
+    },
+    PlayerRunner,
+};
 use async_process::{Command, Stdio};
-use async_std::io::{BufReader, BufWriter};
-use mlr_api::{PlayerInput, PlayerOutput, RunnerError};
+use futures::io::{BufReader, BufWriter};
+use mlr_api::{MatchStart, PlayerInput, PlayerOutput, RunnerError};
 use std::{
     ffi::{OsStr, OsString},
+    path::PathBuf,
     time::Duration,
 };
 
+/// Where and with which environment a `CommandRunner` starts its process and how it talks to it
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct CommandOptions {
+    /// The working directory of the process, the working directory of the engine if `None`
+    pub current_dir: Option<PathBuf>,
+
+    /// Environment variables that are set for the process on top of those of the engine
+    pub env: Vec<(String, String)>,
+
+    /// The size of the lines and messages the process can handle
+    pub limits: MessageLimits,
+}
+
 pub struct CommandRunner {
     command: OsString,
     args: Vec<OsString>,
+    options: CommandOptions,
+    transcript: Option<Transcript>,
+    scheduler: SchedulerSlot,
 }
 
 impl CommandRunner {
@@ -20,39 +46,101 @@ impl CommandRunner {
         CommandRunner {
             command: command.as_ref().into(),
             args: args.into_iter().map(|arg| arg.as_ref().into()).collect(),
+            options: CommandOptions::default(),
+            transcript: None,
+            scheduler: SchedulerSlot::default(),
         }
     }
+
+    /// Starts the process with the given working directory and environment variables
+    pub fn with_options(mut self, options: CommandOptions) -> CommandRunner {
+        self.options = options;
+        self
+    }
+
+    /// Writes everything that is exchanged with the process to the transcript
+    pub(crate) fn with_transcript(mut self, transcript: Transcript) -> CommandRunner {
+        self.transcript = Some(transcript);
+        self
+    }
+
+    /// Waits for room in the scheduler before starting the process every turn
+    pub fn with_scheduler(mut self, scheduler: RunnerScheduler) -> CommandRunner {
+        self.scheduler.scheduler = Some(scheduler);
+        self
+    }
 }
 
 #[async_trait::async_trait]
 impl PlayerRunner for CommandRunner {
+    async fn ready(&mut self) {
+        self.scheduler.ready().await
+    }
+
     async fn run(&mut self, input: PlayerInput) -> Result<PlayerOutput, RunnerError> {
-        let mut proc = Command::new(&self.command)
+        self.exchange(Message::Turn(input), Duration::from_millis(500))
+            .await
+    }
+
+    async fn start_match(
+        &mut self,
+        start: MatchStart,
+        timeout: Duration,
+    ) -> Result<PlayerOutput, RunnerError> {
+        self.exchange(Message::MatchStart(start), timeout).await
+    }
+}
+
+impl CommandRunner {
+    /// Starts the process, writes the message to it and reads its output within `duration`
+    async fn exchange(
+        &mut self,
+        message: Message,
+        duration: Duration,
+    ) -> Result<PlayerOutput, RunnerError> {
+        // Wait for room to run, the time spent waiting does not count towards the timeout
+        let _permit = self.scheduler.take().await;
+
+        let mut command = Command::new(&self.command);
+        command
             .args(&self.args)
             .stdin(Stdio::piped())
             .stdout(Stdio::piped())
-            .spawn()?;
+            .envs(self.options.env.iter().map(|(name, value)| (name, value)))
+            .kill_on_drop(true);
+        if let Some(dir) = &self.options.current_dir {
+            command.current_dir(dir);
+        }
+        let mut proc = spawn_in_new_group(&mut command).spawn()?;
+        let recording = self.transcript.as_ref().map(|_| Recording::default());
+        let stdin = BufWriter::new(Recorded::new(
+            proc.stdin.take().unwrap(),
+            recording.as_ref().map(|recording| &recording.stdin),
+        ));
+        let stdout = BufReader::new(Recorded::new(
+            proc.stdout.take().unwrap(),
+            recording.as_ref().map(|recording| &recording.stdout),
+        ));
 
-        let stdin = BufWriter::new(proc.stdin.take().unwrap());
-        let stdout = BufReader::new(proc.stdout.take().unwrap());
+        // Kills the process and everything it started once the turn is over, however it ends
+        let group = ProcessGroup::new(&proc);
 
         // Construct a runner that performs the communication with the process
-        let mut runner = AsyncRunner::new(stdin, stdout);
+        let mut runner = AsyncRunner::new(stdin, stdout).with_limits(self.options.limits);
 
         // Time the process out if it doesnt return a value without a certain time
-        let timeout = Duration::from_millis(500);
-        let result = async_std::future::timeout(timeout, runner.run(input))
+        let heading = message.heading();
+        let result = timeout(duration, runner.send(message))
             .await
-            .map_err(|_| RunnerError::Timeout(timeout))?;
-
-        // Kill the process if it doesnt quit in time
-        if async_std::future::timeout(Duration::from_millis(1), proc.status())
-            .await
-            .is_err()
-        {
-            let _err = proc.kill();
+            .unwrap_or(Err(RunnerError::Timeout(duration)));
+        if let (Some(transcript), Some(recording)) = (&mut self.transcript, &recording) {
+            transcript.write_exchange(&heading, recording, &result);
         }
 
+        // The process has no reason to live on once its output has been read. It is killed before it
+        // is waited on, the process itself is reaped in the background once it is dropped.
+        drop(group);
+
         result
     }
 }
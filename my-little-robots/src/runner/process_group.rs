@@ -0,0 +1,77 @@
+use async_process::{Child, Command};
+
+/// Makes the command start its process in a process group of its own. Everything the process
+/// starts in turn, like the interpreter started by a shell script, joins that group so the whole
+/// group can be killed at once with `ProcessGroup`.
+#[cfg(unix)]
+pub(crate) fn spawn_in_new_group(command: &mut Command) -> &mut Command {
+    use async_process::unix::CommandExt;
+
+    // Safe because `setpgid` is async-signal-safe and touches no memory of the parent
+    unsafe {
+        command.pre_exec(|| {
+            if libc::setpgid(0, 0) == 0 {
+                Ok(())
+            } else {
+                Err(std::io::Error::last_os_error())
+            }
+        })
+    }
+}
+
+/// Makes the command start its process in a process group of its own. Everything the process
+/// starts in turn is killed along with it by `ProcessGroup`.
+#[cfg(windows)]
+pub(crate) fn spawn_in_new_group(command: &mut Command) -> &mut Command {
+    use async_process::windows::CommandExt;
+
+    const CREATE_NEW_PROCESS_GROUP: u32 = 0x0000_0200;
+    command.creation_flags(CREATE_NEW_PROCESS_GROUP)
+}
+
+/// Kills a process that was started with `spawn_in_new_group` together with all the processes it
+/// started when dropped, so no process outlives the turn of a player, even if the turn timed out
+/// or the battle was aborted.
+///
+/// The group borrows the process so it is dropped before the process is waited on. Until then the
+/// process cannot be reaped, which keeps its id, and the id of its group, from being reused by an
+/// unrelated process.
+pub(crate) struct ProcessGroup<'a> {
+    child: &'a Child,
+}
+
+impl<'a> ProcessGroup<'a> {
+    pub fn new(child: &'a Child) -> ProcessGroup<'a> {
+        ProcessGroup { child }
+    }
+
+    /// Kills all processes in the group. The group outlives its leader, so this also kills the
+    /// processes that are left behind when the leader exited.
+    #[cfg(unix)]
+    fn kill(&self) {
+        // The group was created with the id of the process that leads it
+        unsafe {
+            libc::killpg(self.child.id() as libc::pid_t, libc::SIGKILL);
+        }
+    }
+
+    /// Kills the process and all the processes it started. Windows only knows which processes
+    /// belong together through the process that started them, so processes that are left behind
+    /// by a process that already exited are not found.
+    #[cfg(windows)]
+    fn kill(&self) {
+        use std::process::Stdio;
+
+        let _ = std::process::Command::new("taskkill")
+            .args(&["/T", "/F", "/PID", &self.child.id().to_string()])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status();
+    }
+}
+
+impl Drop for ProcessGroup<'_> {
+    fn drop(&mut self) {
+        self.kill()
+    }
+}
@@ -0,0 +1,172 @@
+use std::collections::VecDeque;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+
+/// Limits the number of processes and wasm instances that run at the same time. Every runner that
+/// is given a clone of the same scheduler shares the limit, whichever battle it plays in, so a
+/// backend that plays many battles in parallel does not start a process for every player at once.
+/// Runners that have to wait for their turn are served in the order in which they asked.
+#[derive(Clone)]
+pub struct RunnerScheduler {
+    state: Arc<Mutex<SchedulerState>>,
+}
+
+struct SchedulerState {
+    limit: usize,
+    running: usize,
+    next_ticket: u64,
+
+    /// The tickets of the runners that are waiting, in the order in which they asked
+    queue: VecDeque<(u64, Option<Waker>)>,
+}
+
+impl SchedulerState {
+    /// Wakes the runner at the front of the queue if there is room for it to run
+    fn wake_next(&mut self) {
+        if self.running < self.limit {
+            if let Some((_, Some(waker))) = self.queue.front_mut() {
+                waker.wake_by_ref();
+            }
+        }
+    }
+}
+
+impl RunnerScheduler {
+    /// Constructs a scheduler that lets at most `limit` runners run at the same time
+    pub fn new(limit: usize) -> RunnerScheduler {
+        assert!(limit > 0, "a scheduler must let at least one runner run");
+        RunnerScheduler {
+            state: Arc::new(Mutex::new(SchedulerState {
+                limit,
+                running: 0,
+                next_ticket: 0,
+                queue: VecDeque::new(),
+            })),
+        }
+    }
+
+    /// Constructs a scheduler that lets as many runners run at the same time as there are CPUs
+    pub fn with_cpu_limit() -> RunnerScheduler {
+        RunnerScheduler::new(rayon::current_num_threads())
+    }
+
+    /// The number of runners that may run at the same time
+    pub fn limit(&self) -> usize {
+        self.lock().limit
+    }
+
+    /// The number of runners that are running right now
+    pub fn running(&self) -> usize {
+        self.lock().running
+    }
+
+    /// The number of runners that are waiting for room to run
+    pub fn waiting(&self) -> usize {
+        self.lock().queue.len()
+    }
+
+    /// Waits until there is room for another runner to run. The runner may run until the returned
+    /// permit is dropped.
+    pub fn acquire(&self) -> Acquire<'_> {
+        Acquire {
+            scheduler: self,
+            ticket: None,
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, SchedulerState> {
+        self.state.lock().expect("the scheduler is never poisoned")
+    }
+}
+
+/// A future that resolves once a runner may run, see `RunnerScheduler::acquire`. Dropping it
+/// before it resolved gives up the place in the queue.
+pub struct Acquire<'a> {
+    scheduler: &'a RunnerScheduler,
+    ticket: Option<u64>,
+}
+
+impl<'a> Future for Acquire<'a> {
+    type Output = RunnerPermit;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<RunnerPermit> {
+        let scheduler = self.scheduler.clone();
+        let mut state = scheduler.lock();
+        let ticket = match self.ticket {
+            Some(ticket) => ticket,
+            None => {
+                let ticket = state.next_ticket;
+                state.next_ticket += 1;
+                state.queue.push_back((ticket, None));
+                self.ticket = Some(ticket);
+                ticket
+            }
+        };
+
+        let at_front = matches!(state.queue.front(), Some((front, _)) if *front == ticket);
+        if at_front && state.running < state.limit {
+            state.queue.pop_front();
+            state.running += 1;
+            self.ticket = None;
+
+            // More than one runner may have finished since the next runner was last woken
+            state.wake_next();
+            drop(state);
+            return Poll::Ready(RunnerPermit { scheduler });
+        }
+
+        if let Some((_, waker)) = state.queue.iter_mut().find(|(t, _)| *t == ticket) {
+            *waker = Some(cx.waker().clone());
+        }
+        Poll::Pending
+    }
+}
+
+impl<'a> Drop for Acquire<'a> {
+    fn drop(&mut self) {
+        if let Some(ticket) = self.ticket {
+            let mut state = self.scheduler.lock();
+            state.queue.retain(|(t, _)| *t != ticket);
+            state.wake_next();
+        }
+    }
+}
+
+/// Lets a runner run until it is dropped, see `RunnerScheduler::acquire`
+pub struct RunnerPermit {
+    scheduler: RunnerScheduler,
+}
+
+impl Drop for RunnerPermit {
+    fn drop(&mut self) {
+        let mut state = self.scheduler.lock();
+        state.running -= 1;
+        state.wake_next();
+    }
+}
+
+/// The scheduler of a runner together with the permit it acquired ahead of its turn
+#[derive(Default)]
+pub(crate) struct SchedulerSlot {
+    pub scheduler: Option<RunnerScheduler>,
+    permit: Option<RunnerPermit>,
+}
+
+impl SchedulerSlot {
+    /// Waits for room to run, unless the runner already has a permit or is not limited
+    pub async fn ready(&mut self) {
+        if self.permit.is_none() {
+            if let Some(scheduler) = &self.scheduler {
+                self.permit = Some(scheduler.acquire().await);
+            }
+        }
+    }
+
+    /// Returns the permit to run this turn, waits for it if `ready` was not called before
+    pub async fn take(&mut self) -> Option<RunnerPermit> {
+        self.ready().await;
+        self.permit.take()
+    }
+}
@@ -0,0 +1,129 @@
+use futures::{AsyncRead, AsyncWrite};
+use mlr_api::{PlayerOutput, RunnerError};
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::Path;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+
+/// A file to which the exact bytes exchanged with a program are written every turn, to find out
+/// why the engine does not understand a program without adding prints to the engine
+pub(crate) struct Transcript {
+    file: File,
+}
+
+impl Transcript {
+    /// Creates the file, an existing file is overwritten
+    pub fn create(path: &Path) -> io::Result<Transcript> {
+        Ok(Transcript {
+            file: File::create(path)?,
+        })
+    }
+
+    /// Appends what was exchanged for a message, like a turn, under the given heading and how the
+    /// exchange ended. Failures are logged, the battle continues without the transcript.
+    pub fn write_exchange(
+        &mut self,
+        heading: &str,
+        recording: &Recording,
+        result: &Result<PlayerOutput, RunnerError>,
+    ) {
+        if let Err(err) = self.try_write_exchange(heading, recording, result) {
+            log::warn!("could not write the transcript of the {}: {}", heading, err);
+        }
+    }
+
+    fn try_write_exchange(
+        &mut self,
+        heading: &str,
+        recording: &Recording,
+        result: &Result<PlayerOutput, RunnerError>,
+    ) -> io::Result<()> {
+        writeln!(self.file, "=== {} ===", heading)?;
+        for (name, bytes) in [("stdin", &recording.stdin), ("stdout", &recording.stdout)].iter() {
+            let bytes = bytes.lock().expect("the recording is never poisoned");
+            writeln!(self.file, "--- {} ({} bytes) ---", name, bytes.len())?;
+            self.file.write_all(&bytes)?;
+            if !bytes.ends_with(b"\n") {
+                writeln!(self.file)?;
+            }
+        }
+        match result {
+            Ok(output) => writeln!(
+                self.file,
+                "--- accepted {} actions ---",
+                output.actions.len()
+            )?,
+            Err(err) => writeln!(self.file, "--- error E{}: {} ---", err.code(), err)?,
+        }
+        self.file.flush()
+    }
+}
+
+/// The bytes written to and read from a program for a single message
+#[derive(Default)]
+pub(crate) struct Recording {
+    pub stdin: Arc<Mutex<Vec<u8>>>,
+    pub stdout: Arc<Mutex<Vec<u8>>>,
+}
+
+/// Wraps a stream and copies every byte that passes through it to a recording, if there is one
+pub(crate) struct Recorded<T> {
+    inner: T,
+    bytes: Option<Arc<Mutex<Vec<u8>>>>,
+}
+
+impl<T> Recorded<T> {
+    pub fn new(inner: T, bytes: Option<&Arc<Mutex<Vec<u8>>>>) -> Self {
+        Recorded {
+            inner,
+            bytes: bytes.cloned(),
+        }
+    }
+
+    fn record(&self, bytes: &[u8]) {
+        if let Some(recording) = &self.bytes {
+            recording
+                .lock()
+                .expect("the recording is never poisoned")
+                .extend_from_slice(bytes);
+        }
+    }
+}
+
+impl<T: AsyncRead + Unpin> AsyncRead for Recorded<T> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let poll = Pin::new(&mut self.inner).poll_read(cx, buf);
+        if let Poll::Ready(Ok(read)) = poll {
+            self.record(&buf[..read]);
+        }
+        poll
+    }
+}
+
+impl<T: AsyncWrite + Unpin> AsyncWrite for Recorded<T> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let poll = Pin::new(&mut self.inner).poll_write(cx, buf);
+        if let Poll::Ready(Ok(written)) = poll {
+            self.record(&buf[..written]);
+        }
+        poll
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_close(cx)
+    }
+}
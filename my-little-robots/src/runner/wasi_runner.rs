@@ -1,28 +1,51 @@
-use crate::{runner::async_runner::AsyncRunner, PlayerRunner};
-use async_std::{
-    io,
-    io::BufReader,
-    pin::Pin,
-    task::{Context, JoinHandle, Poll},
+use crate::{
+    runner::{
+        async_runner::{AsyncRunner, Message},
+        scheduler::{RunnerScheduler, SchedulerSlot},
+        timeout,
+        transcript::{Recorded, Recording, Transcript},
+    },
+    PlayerRunner,
 };
 use futures::{
     channel::{mpsc, oneshot},
+    executor::block_on,
+    io::BufReader,
     stream::IntoAsyncRead,
     AsyncRead, AsyncReadExt, AsyncWrite, SinkExt, TryStreamExt,
 };
-use mlr_api::{PlayerInput, PlayerMemory, PlayerOutput, RunnerError};
+use mlr_api::{MatchStart, PlayerInput, PlayerMemory, PlayerOutput, RunnerError};
 use std::{
+    io,
     io::{Read, Write},
     path::PathBuf,
+    pin::Pin,
+    task::{Context, Poll},
+    thread::JoinHandle,
     time::Duration,
 };
 use wasi_common::virtfs::pipe::{ReadPipe, WritePipe};
-use wasmtime::{Config, Engine, InterruptHandle, Linker, Module, OptLevel, Store};
+use wasmtime::{Config, Engine, Instance, InterruptHandle, Linker, Module, OptLevel, Store, Val};
 use wasmtime_wasi::{Wasi, WasiCtxBuilder};
 
+/// The function a reactor module exports to play a turn, see `WasiRunner::run_reactor`
+const TICK_EXPORT: &str = "mlr_tick";
+
+/// The function a reactor module exports to allocate memory for the input of a turn
+const ALLOC_EXPORT: &str = "mlr_alloc";
+
 pub struct WasiRunner {
     engine: Engine,
     module: Module,
+    transcript: Option<Transcript>,
+    scheduler: SchedulerSlot,
+
+    /// True if the module exports `TICK_EXPORT`, it is then called directly instead of `_start`
+    is_reactor: bool,
+
+    /// The instance of a reactor module, kept between turns so the module keeps its state. A new
+    /// instance is created on the first turn and after the module failed or took too long.
+    reactor: Option<Reactor>,
 }
 
 impl WasiRunner {
@@ -35,43 +58,161 @@ impl WasiRunner {
 
         let engine = Engine::new(&config);
         let module = Module::from_file(&engine, &path_to_module)?;
-        Ok(WasiRunner { engine, module })
+        let is_reactor = module.get_export(TICK_EXPORT).is_some();
+        Ok(WasiRunner {
+            engine,
+            module,
+            transcript: None,
+            scheduler: SchedulerSlot::default(),
+            is_reactor,
+            reactor: None,
+        })
+    }
+
+    /// Writes everything that is exchanged with the module to the transcript
+    pub(crate) fn with_transcript(mut self, transcript: Transcript) -> WasiRunner {
+        self.transcript = Some(transcript);
+        self
+    }
+
+    /// Waits for room in the scheduler before running the module every turn
+    pub fn with_scheduler(mut self, scheduler: RunnerScheduler) -> WasiRunner {
+        self.scheduler.scheduler = Some(scheduler);
+        self
     }
 }
 
 #[async_trait::async_trait]
 impl PlayerRunner for WasiRunner {
+    async fn ready(&mut self) {
+        self.scheduler.ready().await
+    }
+
     async fn run(
         &mut self,
         input: PlayerInput<PlayerMemory>,
     ) -> Result<PlayerOutput<PlayerMemory>, RunnerError> {
+        self.exchange(Message::Turn(input), Duration::from_millis(10))
+            .await
+    }
+
+    async fn start_match(
+        &mut self,
+        start: MatchStart,
+        timeout: Duration,
+    ) -> Result<PlayerOutput, RunnerError> {
+        self.exchange(Message::MatchStart(start), timeout).await
+    }
+}
+
+impl WasiRunner {
+    /// Instantiates the module, writes the message to it and reads its output within `duration`
+    async fn exchange(
+        &mut self,
+        message: Message,
+        duration: Duration,
+    ) -> Result<PlayerOutput, RunnerError> {
+        // Wait for room to run, the time spent waiting does not count towards the timeout
+        let _permit = self.scheduler.take().await;
+
+        if self.is_reactor {
+            return self.run_reactor(message, duration).await;
+        }
+
         let (host_stdout, client_stdout) = wasi_stdout();
         let (host_stdin, client_stdin) = wasi_stdin();
 
-        // Start the tick function
+        // Start the tick function. The instance is interrupted when this function returns or when
+        // the turn is abandoned, so it never outlives the turn.
         let (interrupt_handle, handle) = self.start(client_stdin, client_stdout).await?;
+        let _interrupt_guard = InterruptOnDrop(interrupt_handle);
 
         // Construct a runner that performs the communication with the process
-        let mut runner = AsyncRunner::new(host_stdin, BufReader::new(host_stdout));
+        let recording = self.transcript.as_ref().map(|_| Recording::default());
+        let mut runner = AsyncRunner::new(
+            Recorded::new(
+                host_stdin,
+                recording.as_ref().map(|recording| &recording.stdin),
+            ),
+            BufReader::new(Recorded::new(
+                host_stdout,
+                recording.as_ref().map(|recording| &recording.stdout),
+            )),
+        );
 
         // Time the process out if it doesnt return a value without a certain time
-        let timeout = Duration::from_millis(10);
-        let result = match async_std::future::timeout(timeout, runner.run(input)).await {
-            Ok(result) => result,
-            Err(_) => {
-                interrupt_handle.interrupt();
-                return Err(RunnerError::Timeout(timeout));
-            }
-        };
+        let heading = message.heading();
+        let result = timeout(duration, runner.send(message))
+            .await
+            .unwrap_or(Err(RunnerError::Timeout(duration)));
+        if let (Some(transcript), Some(recording)) = (&mut self.transcript, &recording) {
+            transcript.write_exchange(&heading, recording, &result);
+        }
+        if let Err(RunnerError::Timeout(_)) = &result {
+            return result;
+        }
 
         drop(handle);
 
         result
     }
-}
 
-impl WasiRunner {
-    /// Starts the runner on a separate thread. Receives the `stdin` and `stdout` streams which are
+    /// Plays a turn with a module that follows the reactor model. Instead of reading the input
+    /// from stdin in `_start`, the module exports `mlr_alloc(len) -> ptr` which returns room for
+    /// `len` bytes in its memory and `mlr_tick(ptr, len) -> (ptr, len)` which receives the JSON of
+    /// the input and returns the location of the JSON of its output. Since multiple return values
+    /// are awkward to export from most languages, `mlr_tick` may also return a single i64 with the
+    /// pointer in the high and the length in the low 32 bits. A `MatchStart` is passed to
+    /// `mlr_tick` the same way. The same instance plays every turn, so the module keeps its state
+    /// between turns unless it failed.
+    async fn run_reactor(
+        &mut self,
+        message: Message,
+        duration: Duration,
+    ) -> Result<PlayerOutput<PlayerMemory>, RunnerError> {
+        let heading = message.heading();
+        let input_json = message.to_json()?.into_bytes();
+        let reactor = match self.reactor.take() {
+            Some(reactor) => reactor,
+            None => Reactor::start(self.engine.clone(), self.module.clone()).await?,
+        };
+
+        // Time the module out if it doesnt return a value without a certain time
+        let output = timeout(duration, reactor.tick(input_json.clone()))
+            .await
+            .unwrap_or(Ok(Err(RunnerError::Timeout(duration))))
+            .unwrap_or(Err(RunnerError::InternalError));
+
+        // A module that failed may be left in a broken state, it is dropped and interrupted in case
+        // it is still running
+        if output.is_ok() {
+            self.reactor = Some(reactor);
+        }
+
+        // There are no streams to record, the input and output are recorded as if there were
+        let recording = Recording::default();
+        if self.transcript.is_some() {
+            recording
+                .stdin
+                .lock()
+                .expect("the recording is never poisoned")
+                .extend_from_slice(&input_json);
+            if let Ok(output) = &output {
+                recording
+                    .stdout
+                    .lock()
+                    .expect("the recording is never poisoned")
+                    .extend_from_slice(output);
+            }
+        }
+        let result = output.and_then(|output| Ok(serde_json::from_slice::<PlayerOutput>(&output)?));
+        if let Some(transcript) = &mut self.transcript {
+            transcript.write_exchange(&heading, &recording, &result);
+        }
+        result
+    }
+
+    /// Starts the runner on a dedicated thread. Receives the `stdin` and `stdout` streams which are
     /// used to communicate with the wasi "process". Returns a tuple containing an interrupt handle
     /// to cancel all pending WASI operations and a join handle that can be used to await the
     /// closure of the WASI process.
@@ -84,7 +225,7 @@ impl WasiRunner {
         let module = self.module.clone();
         let (tx, rx) = oneshot::channel();
 
-        let handle = async_std::task::spawn_blocking(move || -> Result<(), RunnerError> {
+        let handle = std::thread::spawn(move || -> Result<(), RunnerError> {
             let store = Store::new(&engine);
             let mut linker = Linker::new(&store);
 
@@ -138,6 +279,153 @@ impl WasiRunner {
     }
 }
 
+/// An instance of a reactor module that lives on a thread of its own, since instances cannot be
+/// moved between threads. The thread ends when the `Reactor` is dropped.
+struct Reactor {
+    requests: std::sync::mpsc::Sender<TickRequest>,
+    _interrupt_guard: InterruptOnDrop,
+}
+
+/// The input of a tick and the channel over which its output is sent back
+type TickRequest = (Vec<u8>, oneshot::Sender<Result<Vec<u8>, RunnerError>>);
+
+impl Reactor {
+    /// Starts the thread of a new instance of the module. The module is instantiated on the thread
+    /// before the first tick, so the time it takes counts towards the first turn.
+    async fn start(engine: Engine, module: Module) -> Result<Reactor, RunnerError> {
+        let (requests, requests_rx) = std::sync::mpsc::channel::<TickRequest>();
+        let (interrupt_tx, interrupt_rx) = oneshot::channel();
+        std::thread::spawn(move || {
+            let store = Store::new(&engine);
+            match store.interrupt_handle() {
+                Ok(interrupt) => {
+                    let _ = interrupt_tx.send(Ok(interrupt));
+                }
+                Err(e) => {
+                    let _ = interrupt_tx.send(Err(RunnerError::InitError(format!(
+                        "unable to create interrupt handle: {}",
+                        e
+                    ))));
+                    return;
+                }
+            }
+            let instance = match instantiate_reactor(&store, &module) {
+                Ok(instance) => instance,
+                Err(e) => {
+                    if let Ok((_, output)) = requests_rx.recv() {
+                        let _ = output.send(Err(e));
+                    }
+                    return;
+                }
+            };
+            for (input, output) in requests_rx {
+                let _ = output.send(tick(&instance, &input));
+            }
+        });
+
+        let interrupt = interrupt_rx
+            .await
+            .unwrap_or(Err(RunnerError::InternalError))?;
+        Ok(Reactor {
+            requests,
+            _interrupt_guard: InterruptOnDrop(interrupt),
+        })
+    }
+
+    /// Passes the input to the tick function of the instance, the output is sent back over the
+    /// returned channel
+    fn tick(&self, input: Vec<u8>) -> oneshot::Receiver<Result<Vec<u8>, RunnerError>> {
+        let (output_tx, output_rx) = oneshot::channel();
+
+        // If the thread is gone the output is dropped along with the request and never sent
+        let _ = self.requests.send((input, output_tx));
+        output_rx
+    }
+}
+
+/// Instantiates a reactor module and calls its `_initialize` function, if it exports one
+fn instantiate_reactor(store: &Store, module: &Module) -> Result<Instance, RunnerError> {
+    let mut linker = Linker::new(store);
+    let wasi_ctx = WasiCtxBuilder::new()
+        .inherit_stderr()
+        .build()
+        .map_err(|e| RunnerError::InitError(format!("error initializing wasi: {:?}", e)))?;
+    let wasi = Wasi::new(store, wasi_ctx);
+    wasi.add_to_linker(&mut linker)
+        .map_err(|e| RunnerError::InitError(format!("error adding wasi to linker: {}", e)))?;
+    let instance = linker
+        .instantiate(module)
+        .map_err(|e| RunnerError::InitError(format!("error instantiating wasm module: {}", e)))?;
+    if let Some(initialize) = instance.get_func("_initialize") {
+        initialize.call(&[]).map_err(|e| {
+            RunnerError::InitError(format!("error initializing wasm module: {}", e))
+        })?;
+    }
+    Ok(instance)
+}
+
+/// Copies the input into the memory of a reactor instance, calls its tick function and returns a
+/// copy of the output
+fn tick(instance: &Instance, input: &[u8]) -> Result<Vec<u8>, RunnerError> {
+    let missing =
+        |name: &str| RunnerError::InitError(format!("the module does not export {}", name));
+    let memory = instance
+        .get_memory("memory")
+        .ok_or_else(|| missing("memory"))?;
+    let alloc = instance
+        .get_func(ALLOC_EXPORT)
+        .ok_or_else(|| missing(ALLOC_EXPORT))?
+        .get1::<i32, i32>()
+        .map_err(|e| {
+            RunnerError::InitError(format!("{} has the wrong signature: {}", ALLOC_EXPORT, e))
+        })?;
+    let tick = instance
+        .get_func(TICK_EXPORT)
+        .ok_or_else(|| missing(TICK_EXPORT))?;
+
+    let trapped = |e: &dyn std::fmt::Display| {
+        log::debug!("the reactor module trapped: {}", e);
+        RunnerError::Trapped(e.to_string())
+    };
+    let out_of_bounds =
+        || RunnerError::DataError("the module returned memory outside of its bounds".to_string());
+
+    // The memory is only accessed while the instance is not running, so no one else touches it
+    let ptr = alloc(input.len() as i32).map_err(|e| trapped(&e))? as u32 as usize;
+    unsafe { memory.data_unchecked_mut() }
+        .get_mut(ptr..ptr + input.len())
+        .ok_or_else(out_of_bounds)?
+        .copy_from_slice(input);
+
+    let results = tick
+        .call(&[Val::I32(ptr as i32), Val::I32(input.len() as i32)])
+        .map_err(|e| trapped(&e))?;
+    let (ptr, len) = match &*results {
+        [Val::I32(ptr), Val::I32(len)] => (*ptr as u32 as usize, *len as u32 as usize),
+        [Val::I64(packed)] => ((*packed >> 32) as u32 as usize, *packed as u32 as usize),
+        _ => {
+            return Err(RunnerError::InitError(format!(
+                "{} must return (i32, i32) or i64",
+                TICK_EXPORT
+            )))
+        }
+    };
+    let output = unsafe { memory.data_unchecked() }
+        .get(ptr..ptr + len)
+        .ok_or_else(out_of_bounds)?
+        .to_vec();
+    Ok(output)
+}
+
+/// Interrupts a running wasm instance when dropped
+struct InterruptOnDrop(InterruptHandle);
+
+impl Drop for InterruptOnDrop {
+    fn drop(&mut self) {
+        self.0.interrupt();
+    }
+}
+
 fn wasi_stdin() -> (HostWasiStdin, ClientWasiStdin) {
     let (tx, rx) = mpsc::channel(8);
     (
@@ -207,7 +495,7 @@ pub struct ClientWasiStdin {
 
 impl Read for ClientWasiStdin {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-        async_std::task::block_on(async { self.inner.read(buf).await })
+        block_on(async { self.inner.read(buf).await })
     }
 }
 
@@ -234,7 +522,7 @@ impl AsyncRead for HostWasiStdout {
 
 impl Write for ClientWasiStdout {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-        async_std::task::block_on(async move {
+        block_on(async move {
             self.inner
                 .send(Ok(buf.to_vec()))
                 .await
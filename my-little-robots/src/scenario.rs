@@ -0,0 +1,240 @@
+use crate::map_builder::{BuilderKind, LibraryMap};
+use crate::{Battle, CustomTile, GameRules, Map, TileProperties};
+use mlr_api::{Coord, ItemKind, ObstacleKind, TileType};
+use serde_derive::{Deserialize, Serialize};
+use std::path::Path;
+
+/// The size of maps that are generated when a scenario does not specify one
+const DEFAULT_WIDTH: usize = 80;
+const DEFAULT_HEIGHT: usize = 50;
+
+/// A `Scenario` describes a challenge that can be authored and shared as a TOML file: the map it
+/// is played on, where the units of the players start and the rules of the game, including the
+/// win condition.
+///
+/// ```toml
+/// name = "Escape the maze"
+/// spawns = [[1, 1]]
+///
+/// [map]
+/// tiles = """
+/// #######
+/// #.....#
+/// #.###.#
+/// #...#E#
+/// #######
+/// """
+///
+/// [rules]
+/// max_turns = 50
+/// ```
+///
+/// Objective items are placed with `items`, they have to be delivered to goal tiles drawn as `G`
+/// with the `deliver_items` win condition. Crates and rubble are placed with `obstacles`:
+///
+/// ```toml
+/// [[obstacles]]
+/// kind = "crate"
+/// location = [3, 1]
+/// ```
+///
+/// Properties are attached to tiles with `properties`, plugins can read them from the map with
+/// `Map::property`:
+///
+/// ```toml
+/// [[properties]]
+/// coord = [5, 3]
+/// values = { region = "vault" }
+/// ```
+///
+/// NPCs are not part of the game yet, scenarios that
+/// describe them are rejected.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Scenario {
+    /// The human readable name of the scenario
+    #[serde(default)]
+    pub name: Option<String>,
+
+    /// A description of the challenge
+    #[serde(default)]
+    pub description: Option<String>,
+
+    /// The map the scenario is played on
+    pub map: MapSource,
+
+    /// The locations at which the units of the players start, see `Battle::set_spawns`
+    #[serde(default)]
+    pub spawns: Vec<Coord>,
+
+    /// The locations of the data cores that lie on the map when the battle starts
+    #[serde(default)]
+    pub items: Vec<Coord>,
+
+    /// The obstacles that stand on the map when the battle starts
+    #[serde(default)]
+    pub obstacles: Vec<ScenarioObstacle>,
+
+    /// Extra data attached to tiles of the map, see `Map::set_property`
+    #[serde(default)]
+    pub properties: Vec<TileProperties>,
+
+    /// The rules of the game, missing rules take their default value
+    #[serde(default)]
+    pub rules: GameRules,
+}
+
+/// An obstacle that stands on the map of a scenario when the battle starts
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ScenarioObstacle {
+    pub kind: ObstacleKind,
+    pub location: Coord,
+}
+
+/// Describes where the map of a scenario comes from
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum MapSource {
+    /// The map is drawn as text, one line per row. `#` is a wall, `.` is floor, `E` is an exit and
+    /// `G` is a goal. Custom tiles of plugins are drawn with their `symbol`.
+    Tiles { tiles: String },
+
+    /// A map from the library of curated maps, run `mlr list` to see all maps in the library
+    Library { library: String },
+
+    /// The map is generated by one of the map builders, run `mlr list` to see all map builders
+    Builder {
+        builder: String,
+        #[serde(default)]
+        width: Option<usize>,
+        #[serde(default)]
+        height: Option<usize>,
+    },
+}
+
+impl Scenario {
+    /// Reads a scenario from the TOML file at the specified path
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Scenario> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path)?;
+        Ok(toml::from_str(&contents)?)
+    }
+
+    /// Configures the battle to play this scenario. Plugins must be added to the battle before the
+    /// scenario is applied for their tiles to be recognized.
+    pub fn apply(&self, battle: &mut Battle) -> anyhow::Result<()> {
+        let mut map = self.map.build_with_tiles(battle.plugins().tiles())?;
+        for tile in self.properties.iter() {
+            if !map.in_bounds(tile.coord) {
+                anyhow::bail!("properties cannot be attached to {:?}", tile.coord);
+            }
+            for (key, value) in tile.values.iter() {
+                map.set_property(tile.coord, key.as_str(), value.as_str());
+            }
+        }
+        if let Some(spawn) = self
+            .spawns
+            .iter()
+            .find(|&&spawn| !map.can_enter_tile(spawn))
+        {
+            anyhow::bail!("units cannot be spawned at {:?}", spawn);
+        }
+        if let Some(item) = self.items.iter().find(|&&item| !map.can_enter_tile(item)) {
+            anyhow::bail!("items cannot be placed at {:?}", item);
+        }
+        if let Some(obstacle) = self
+            .obstacles
+            .iter()
+            .find(|obstacle| !map.can_enter_tile(obstacle.location))
+        {
+            anyhow::bail!("obstacles cannot be placed at {:?}", obstacle.location);
+        }
+        battle.set_map(map);
+        battle.set_spawns(self.spawns.clone());
+        battle.set_items(
+            self.items
+                .iter()
+                .map(|&location| (ItemKind::DataCore, location))
+                .collect(),
+        );
+        battle.set_obstacles(
+            self.obstacles
+                .iter()
+                .map(|obstacle| (obstacle.kind, obstacle.location))
+                .collect(),
+        );
+        battle.set_rules(self.rules.clone());
+        Ok(())
+    }
+}
+
+impl MapSource {
+    /// Constructs the map described by the source
+    pub fn build(&self) -> anyhow::Result<Map> {
+        self.build_with_tiles(&[])
+    }
+
+    /// Constructs the map described by the source, `custom_tiles` are the tiles registered by
+    /// plugins that may appear in the map
+    pub fn build_with_tiles(&self, custom_tiles: &[CustomTile]) -> anyhow::Result<Map> {
+        match self {
+            MapSource::Tiles { tiles } => parse_tiles(tiles, custom_tiles),
+            MapSource::Library { library } => match LibraryMap::find(library) {
+                Some(map) => Ok(map.build()),
+                None => anyhow::bail!("there is no map named '{}' in the library", library),
+            },
+            MapSource::Builder {
+                builder,
+                width,
+                height,
+            } => {
+                let builder: BuilderKind = builder.parse()?;
+                Ok(builder.new_map(
+                    width.unwrap_or(DEFAULT_WIDTH),
+                    height.unwrap_or(DEFAULT_HEIGHT),
+                ))
+            }
+        }
+    }
+}
+
+/// Parses a map drawn as text. Rows that are shorter than the widest row are padded with walls.
+fn parse_tiles(tiles: &str, custom_tiles: &[CustomTile]) -> anyhow::Result<Map> {
+    let rows = tiles
+        .lines()
+        .map(str::trim_end)
+        .filter(|row| !row.is_empty())
+        .collect::<Vec<_>>();
+    let width = rows
+        .iter()
+        .map(|row| row.chars().count())
+        .max()
+        .unwrap_or(0);
+    if width == 0 {
+        anyhow::bail!("the map does not contain any tiles");
+    }
+
+    let mut map = Map::new_closed(width, rows.len());
+    for (y, row) in rows.iter().enumerate() {
+        for (x, c) in row.chars().enumerate() {
+            map[(x as isize, y as isize)] = match c {
+                '#' => TileType::Wall,
+                '.' => TileType::Floor,
+                'E' => TileType::Exit,
+                'G' => TileType::Goal,
+                c => match custom_tiles.iter().find(|tile| tile.symbol == Some(c)) {
+                    Some(tile) => TileType::Custom {
+                        id: tile.id,
+                        passable: tile.passable,
+                    },
+                    None => {
+                        anyhow::bail!("unknown tile '{}' at row {}, column {}", c, y + 1, x + 1)
+                    }
+                },
+            };
+        }
+    }
+    map.compute_distance_to_exit();
+    Ok(map)
+}
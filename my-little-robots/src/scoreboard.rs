@@ -0,0 +1,93 @@
+use crate::{BattleObserver, GameEvent, GameRules, World};
+use mlr_api::PlayerId;
+use serde_derive::{Deserialize, Serialize};
+
+/// Statistics of a single player during a battle
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct PlayerStats {
+    /// The human readable name of the player
+    pub name: String,
+
+    /// The number of units the player owns
+    pub units: usize,
+
+    /// The number of turns in which the runner of the player failed
+    pub errors: usize,
+
+    /// The number of turns in which the runner of the player took too long
+    pub timeouts: usize,
+
+    /// The number of actions of the player that were rejected
+    pub invalid_actions: usize,
+
+    /// The time in microseconds the runner of the player took in the last turn it played
+    pub turn_time: Option<u64>,
+
+    /// The longest time in microseconds the runner of the player took for a single turn
+    pub max_turn_time: u64,
+
+    /// The walking distance from the unit of the player that is closest to an exit to that exit
+    pub distance_to_exit: Option<usize>,
+
+    /// The percentage of the floor tiles the player has seen with its own units
+    #[serde(default)]
+    pub explored: usize,
+}
+
+/// A `Scoreboard` summarizes the state of all players in a battle. It is a `BattleObserver` that
+/// updates itself every turn.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Scoreboard {
+    /// The turn the scoreboard was last updated
+    pub turn: usize,
+
+    /// The statistics of every player indexed by `PlayerId`
+    pub players: Vec<PlayerStats>,
+}
+
+impl Scoreboard {
+    /// Updates the unit counts and distances from the current state of the world
+    fn update_world(&mut self, world: &World) {
+        self.turn = world.turn;
+        for (index, stats) in self.players.iter_mut().enumerate() {
+            let units = world.units.iter().filter(|unit| unit.player.0 == index);
+            stats.units = units.clone().count();
+            stats.distance_to_exit = units
+                .filter_map(|unit| world.map.get_distance_to_exit(unit.location))
+                .min();
+            stats.explored = world.explored_percentage(PlayerId(index));
+        }
+    }
+}
+
+impl BattleObserver for Scoreboard {
+    fn on_start(&mut self, world: &World, players: &[String], _rules: &GameRules) {
+        self.players = players
+            .iter()
+            .map(|name| PlayerStats {
+                name: name.clone(),
+                ..Default::default()
+            })
+            .collect();
+        self.update_world(world);
+    }
+
+    fn on_turn(&mut self, world: &World, events: &[GameEvent]) {
+        for event in events {
+            match event {
+                GameEvent::RunnerError { player, .. } => self.players[player.0].errors += 1,
+                GameEvent::Timeout { player } => self.players[player.0].timeouts += 1,
+                GameEvent::RunnerTimed { player, micros } => {
+                    let stats = &mut self.players[player.0];
+                    stats.turn_time = Some(*micros);
+                    stats.max_turn_time = stats.max_turn_time.max(*micros);
+                }
+                GameEvent::InvalidAction { player, .. } => {
+                    self.players[player.0].invalid_actions += 1
+                }
+                _ => {}
+            }
+        }
+        self.update_world(world);
+    }
+}
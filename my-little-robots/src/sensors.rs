@@ -0,0 +1,53 @@
+use crate::{GameEvent, GameRules, World};
+use mlr_api::UnitId;
+
+impl World {
+    /// Sets up a sensor link from `unit` to `target`. The unit spends `GameRules::link_energy`
+    /// and the target sees `GameRules::link_range` tiles further during the next turn, for every
+    /// link that targets it. Units without energy link for free. Nothing happens if the unit
+    /// does not have enough energy left or either unit died earlier during the turn.
+    pub(crate) fn link_sensors(
+        &mut self,
+        unit: UnitId,
+        target: UnitId,
+        rules: &GameRules,
+        events: &mut Vec<GameEvent>,
+    ) {
+        if self.unit(target).is_none() {
+            return;
+        }
+        let unit = match self.unit_mut(unit) {
+            Some(unit) => unit,
+            None => return,
+        };
+        if let Some(energy) = unit.energy.as_mut() {
+            match energy.checked_sub(rules.link_energy) {
+                Some(remaining) => *energy = remaining,
+                None => return,
+            }
+        }
+        let (player, unit) = (unit.player, unit.id);
+        self.links.push((unit, target));
+        events.push(GameEvent::SensorsLinked {
+            player,
+            unit,
+            target,
+        });
+    }
+
+    /// Returns the number of tiles the given unit sees in every direction, `vision_range` extended
+    /// by the sensor links that target the unit
+    pub(crate) fn linked_vision_range(
+        &self,
+        unit: UnitId,
+        vision_range: usize,
+        rules: &GameRules,
+    ) -> usize {
+        let links = self
+            .links
+            .iter()
+            .filter(|(_, target)| *target == unit)
+            .count();
+        vision_range + links * rules.link_range
+    }
+}
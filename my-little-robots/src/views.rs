@@ -0,0 +1,60 @@
+use crate::{Map, World};
+use mlr_api::{Coord, UnitId};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+/// The tiles a unit saw during a turn
+#[derive(Clone, Debug)]
+pub(crate) struct UnitView {
+    pub unit: UnitId,
+    pub location: Coord,
+    pub range: usize,
+    pub visible: HashSet<Coord>,
+}
+
+/// The fields of view of the units during the previous turn. Most units stand still or take a
+/// single step every turn, so their next field of view is derived from the previous one with
+/// `Map::field_of_view_from_previous`. The views only apply as long as the map and the obstacles
+/// stay the same.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct ViewCache {
+    /// The map the views were computed on. Holding on to it makes any change to the map of the
+    /// world copy it first, which is how changes are detected.
+    map: Option<Arc<Map>>,
+    obstacles: Vec<Coord>,
+    views: HashMap<UnitId, UnitView>,
+}
+
+impl ViewCache {
+    /// Remembers the views that were computed on the given world
+    pub fn new(world: &World, views: impl IntoIterator<Item = UnitView>) -> ViewCache {
+        ViewCache {
+            map: Some(world.map.clone()),
+            obstacles: obstacle_locations(world),
+            views: views.into_iter().map(|view| (view.unit, view)).collect(),
+        }
+    }
+
+    /// Returns true if the views were computed on the map and obstacles of the given world
+    pub fn applies_to(&self, world: &World) -> bool {
+        match &self.map {
+            Some(map) => {
+                Arc::ptr_eq(map, &world.map) && self.obstacles == obstacle_locations(world)
+            }
+            None => false,
+        }
+    }
+
+    /// Returns the view of the unit during the previous turn, if it saw with the same range
+    pub fn view(&self, unit: UnitId, range: usize) -> Option<&UnitView> {
+        self.views.get(&unit).filter(|view| view.range == range)
+    }
+}
+
+fn obstacle_locations(world: &World) -> Vec<Coord> {
+    world
+        .obstacles
+        .iter()
+        .map(|obstacle| obstacle.location)
+        .collect()
+}
@@ -0,0 +1,91 @@
+use crate::GameRules;
+use mlr_api::WeatherKind;
+use serde_derive::{Deserialize, Serialize};
+
+/// A match-level modifier that limits how far units can see. Weather is part of the rules of the
+/// game:
+///
+/// ```toml
+/// # Units can never see further than 3 tiles
+/// [[rules.weather]]
+/// kind = "fog"
+///
+/// # Every other 10 turns it is dark and units only see the tiles next to them
+/// [[rules.weather]]
+/// kind = "darkness"
+/// period = 10
+/// vision_range = 1
+/// ```
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case", deny_unknown_fields)]
+pub enum Weather {
+    /// Limits the vision range during the whole battle
+    Fog {
+        #[serde(default = "default_fog_range")]
+        vision_range: usize,
+    },
+
+    /// Limits the vision range during every other `period` turns, starting with `period` turns
+    /// of daylight
+    Darkness {
+        #[serde(default = "default_darkness_period")]
+        period: usize,
+        #[serde(default = "default_darkness_range")]
+        vision_range: usize,
+    },
+}
+
+fn default_fog_range() -> usize {
+    3
+}
+
+fn default_darkness_period() -> usize {
+    10
+}
+
+fn default_darkness_range() -> usize {
+    1
+}
+
+impl Weather {
+    /// Returns the kind of weather, as communicated to the players
+    pub fn kind(&self) -> WeatherKind {
+        match self {
+            Weather::Fog { .. } => WeatherKind::Fog,
+            Weather::Darkness { .. } => WeatherKind::Darkness,
+        }
+    }
+
+    /// Returns the vision range the weather limits units to during the given turn, `None` if the
+    /// weather has no effect during the turn
+    pub fn vision_range_at(&self, turn: usize) -> Option<usize> {
+        match *self {
+            Weather::Fog { vision_range } => Some(vision_range),
+            Weather::Darkness {
+                period,
+                vision_range,
+            } if period > 0 && (turn / period) % 2 == 1 => Some(vision_range),
+            Weather::Darkness { .. } => None,
+        }
+    }
+}
+
+impl GameRules {
+    /// Returns the number of tiles units can see in every direction during the given turn, taking
+    /// the weather into account
+    pub fn vision_range_at(&self, turn: usize) -> usize {
+        self.weather
+            .iter()
+            .filter_map(|weather| weather.vision_range_at(turn))
+            .fold(self.vision_range, usize::min)
+    }
+
+    /// Returns the kinds of weather that limit the vision range during the given turn
+    pub fn weather_at(&self, turn: usize) -> Vec<WeatherKind> {
+        self.weather
+            .iter()
+            .filter(|weather| weather.vision_range_at(turn).is_some())
+            .map(Weather::kind)
+            .collect()
+    }
+}